@@ -0,0 +1,54 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tracing_viewer::log_parser::LogEntry;
+use tracing_viewer::ui::App;
+
+const ENTRIES: usize = 50_000;
+const LEVELS: [&str; 5] = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+/// A handful of targets repeated across the whole buffer, as in a typical service log
+const TARGETS: [&str; 6] = [
+    "myapp",
+    "myapp::db",
+    "myapp::db::pool",
+    "myapp::http::handlers",
+    "myapp::services::auth::session",
+    "hyper::proto::h1::conn",
+];
+
+fn app_with_entries() -> App {
+    let logs = (0..ENTRIES)
+        .map(|i| LogEntry {
+            timestamp: format!("2024-01-01T12:{:02}:{:02}.{:06}Z", (i / 60) % 60, i % 60, i),
+            level: LEVELS[i % LEVELS.len()].to_string(),
+            target: TARGETS[i % TARGETS.len()].to_string(),
+            message: format!("request handled id={}", i),
+            ..Default::default()
+        })
+        .collect();
+    let mut app = App::new();
+    app.update_logs(logs);
+    app
+}
+
+fn bench_filter_logs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("filter_logs");
+    group.throughput(Throughput::Elements(ENTRIES as u64));
+
+    // レベルを切り替えるたびにバッファ全体を再フィルタリングする
+    let mut app = app_with_entries();
+    app.module_tree.toggle_selection("hyper");
+    group.bench_function(BenchmarkId::from_parameter("repeated_targets"), |b| {
+        b.iter(|| app.toggle_log_level("DEBUG"))
+    });
+
+    // モジュール単位のしきい値があるとレベルの事前判定は使えない
+    let mut app = app_with_entries();
+    app.module_tree.set_min_level("myapp::db", Some("WARN".to_string()));
+    group.bench_function(BenchmarkId::from_parameter("module_thresholds"), |b| {
+        b.iter(|| app.toggle_log_level("DEBUG"))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_filter_logs);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tracing_viewer::log_parser::LogParser;
+
+const LINES: usize = 10_000;
+const LEVELS: [&str; 5] = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+const TARGETS: [&str; 4] = [
+    "myapp",
+    "myapp::db",
+    "myapp::http::handlers",
+    "myapp::services::auth::session",
+];
+
+/// Single-line tracing output, the common case
+fn plain_sample() -> String {
+    (0..LINES)
+        .map(|i| {
+            format!(
+                "2024-01-01T12:{:02}:{:02}.{:06}Z {} {}: request handled id={} elapsed={}ms\n",
+                (i / 60) % 60,
+                i % 60,
+                i,
+                LEVELS[i % LEVELS.len()],
+                TARGETS[i % TARGETS.len()],
+                i,
+                i % 250
+            )
+        })
+        .collect()
+}
+
+/// `tracing_subscriber::fmt().json()` output
+fn json_sample() -> String {
+    (0..LINES)
+        .map(|i| {
+            format!(
+                "{{\"timestamp\":\"2024-01-01T12:{:02}:{:02}.{:06}Z\",\"level\":\"{}\",\"fields\":{{\"message\":\"request handled\",\"id\":{}}},\"target\":\"{}\"}}\n",
+                (i / 60) % 60,
+                i % 60,
+                i,
+                LEVELS[i % LEVELS.len()],
+                i,
+                TARGETS[i % TARGETS.len()]
+            )
+        })
+        .collect()
+}
+
+/// Entries followed by stack-trace style continuation lines
+fn multiline_sample() -> String {
+    let mut content = String::new();
+    for i in 0..LINES / 5 {
+        content.push_str(&format!(
+            "2024-01-01T12:{:02}:{:02}.{:06}Z ERROR {}: operation failed\n",
+            (i / 60) % 60,
+            i % 60,
+            i,
+            TARGETS[i % TARGETS.len()]
+        ));
+        for frame in 0..4 {
+            content.push_str(&format!("    at myapp::frame_{}::call (src/lib.rs:{})\n", frame, i));
+        }
+    }
+    content
+}
+
+fn bench_parse_line(c: &mut Criterion) {
+    let parser = LogParser::new().unwrap();
+    let mut group = c.benchmark_group("parse_line");
+
+    for (name, sample) in [("plain", plain_sample()), ("json", json_sample())] {
+        let lines: Vec<&str> = sample.lines().collect();
+        group.throughput(Throughput::Elements(lines.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &lines, |b, lines| {
+            b.iter(|| {
+                for line in lines {
+                    black_box(parser.parse_line(black_box(line)));
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_parse_multiline_logs(c: &mut Criterion) {
+    let parser = LogParser::new().unwrap();
+    let mut group = c.benchmark_group("parse_multiline_logs");
+
+    for (name, sample) in [
+        ("plain", plain_sample()),
+        ("json", json_sample()),
+        ("multiline", multiline_sample()),
+    ] {
+        group.throughput(Throughput::Bytes(sample.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &sample, |b, sample| {
+            b.iter(|| black_box(parser.parse_multiline_logs(black_box(sample))))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_line, bench_parse_multiline_logs);
+criterion_main!(benches);
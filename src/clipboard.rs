@@ -0,0 +1,143 @@
+//! Copying text to the system clipboard: arboard first, then the platform's command-line
+//! tools when arboard is unavailable (no display server, missing Wayland protocol, ...).
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// How a fallback command expects its input to be encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    Utf8,
+    /// UTF-16LE with a byte order mark, which `clip.exe` needs to keep non-ASCII text
+    Utf16Le,
+}
+
+/// A command-line tool that reads the text to copy from its standard input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackCommand {
+    pub program: &'static str,
+    pub args: &'static [&'static str],
+    pub encoding: InputEncoding,
+}
+
+const UNIX_FALLBACKS: [FallbackCommand; 2] = [
+    FallbackCommand { program: "xclip", args: &["-selection", "clipboard"], encoding: InputEncoding::Utf8 },
+    FallbackCommand { program: "wl-copy", args: &[], encoding: InputEncoding::Utf8 },
+];
+
+const MACOS_FALLBACKS: [FallbackCommand; 1] = [FallbackCommand { program: "pbcopy", args: &[], encoding: InputEncoding::Utf8 }];
+
+const WINDOWS_FALLBACKS: [FallbackCommand; 2] = [
+    FallbackCommand { program: "clip.exe", args: &[], encoding: InputEncoding::Utf16Le },
+    FallbackCommand {
+        program: "powershell",
+        args: &[
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "[Console]::InputEncoding = [Text.Encoding]::UTF8; Set-Clipboard -Value ([Console]::In.ReadToEnd())",
+        ],
+        encoding: InputEncoding::Utf8,
+    },
+];
+
+/// Fallback commands for `os` (as in `std::env::consts::OS`), tried in order
+pub fn fallback_commands(os: &str) -> &'static [FallbackCommand] {
+    match os {
+        "windows" => &WINDOWS_FALLBACKS,
+        "macos" => &MACOS_FALLBACKS,
+        "linux" | "freebsd" | "openbsd" | "netbsd" | "dragonfly" => &UNIX_FALLBACKS,
+        _ => &[],
+    }
+}
+
+impl FallbackCommand {
+    /// `text` as the bytes written to the command's standard input
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self.encoding {
+            InputEncoding::Utf8 => text.as_bytes().to_vec(),
+            InputEncoding::Utf16Le => std::iter::once(0xfeff)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+        }
+    }
+
+    /// Run the command with `text` on its standard input, waiting for it to exit
+    pub fn run(&self, text: &str) -> anyhow::Result<()> {
+        let mut child = Command::new(self.program)
+            .args(self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&self.encode(text))?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("exited with {}", status);
+        }
+        Ok(())
+    }
+}
+
+/// Copy `text` with the first fallback command for `os` that succeeds. The error lists
+/// why each one failed, or says there is none for the platform.
+pub fn copy_with_fallbacks(text: &str, os: &str) -> anyhow::Result<&'static str> {
+    let commands = fallback_commands(os);
+    if commands.is_empty() {
+        anyhow::bail!("no clipboard command known for {}", os);
+    }
+    let mut failures = Vec::new();
+    for command in commands {
+        match command.run(text) {
+            Ok(()) => return Ok(command.program),
+            Err(e) => failures.push(format!("{}: {}", command.program, e)),
+        }
+    }
+    anyhow::bail!("{}", failures.join(", "))
+}
+
+/// The arboard clipboard, kept open after a successful copy: on X11 the copied text is
+/// only available while the clipboard object that set it is alive
+#[derive(Default)]
+pub struct SystemClipboard {
+    clipboard: Option<arboard::Clipboard>,
+}
+
+impl SystemClipboard {
+    /// Copy `text` through arboard, opening the clipboard on first use
+    pub fn set_text(&mut self, text: &str) -> anyhow::Result<()> {
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(arboard::Clipboard::new()?),
+        };
+        if let Err(e) = clipboard.set_text(text) {
+            // 次回は開き直す
+            self.clipboard = None;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_selection_and_encoding() {
+        let programs = |os| fallback_commands(os).iter().map(|command| command.program).collect::<Vec<_>>();
+        assert_eq!(programs("linux"), ["xclip", "wl-copy"]);
+        assert_eq!(programs("macos"), ["pbcopy"]);
+        assert_eq!(programs("windows"), ["clip.exe", "powershell"]);
+
+        let clip = fallback_commands("windows")[0];
+        assert_eq!(clip.encode("aé"), [0xff, 0xfe, b'a', 0, 0xe9, 0]);
+        assert_eq!(fallback_commands("linux")[0].encode("aé"), "aé".as_bytes());
+
+        let error = copy_with_fallbacks("text", "plan9").unwrap_err();
+        assert_eq!(error.to_string(), "no clipboard command known for plan9");
+    }
+}
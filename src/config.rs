@@ -0,0 +1,218 @@
+//! Settings read from a TOML config file. Top-level keys are defaults for the long
+//! command-line option of the same name (`refresh = 500`, `format = "json"`,
+//! `status-right = ["filters", "clock"]`); flags given on the command line override them.
+//!
+//! Settings without an option have their own tables:
+//!
+//! ```toml
+//! [keys]
+//! x = "toggle-raw"        # a key and the palette command it runs
+//!
+//! [theme]
+//! timestamp = "darkgray"
+//! levels = { WARN = "lightyellow" }
+//!
+//! [[highlight]]
+//! term = "timeout"
+//! color = "red"           # optional; picked like the `h` key does without it
+//! ```
+
+use crate::keys::parse_key_binding;
+use crate::palette::Command;
+use crate::theme::Theme;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches};
+use crossterm::event::KeyCode;
+use indexmap::IndexMap;
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+/// Options that only make sense on the command line
+const COMMAND_LINE_ONLY: [&str; 3] = ["config", "help", "version"];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// `[keys]`: keys bound to palette commands, in file order
+    #[serde(default, deserialize_with = "key_bindings")]
+    pub keys: Vec<(KeyCode, Command)>,
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default)]
+    pub highlight: Vec<HighlightRule>,
+    /// Every other top-level key, as a default for the long option of that name
+    #[serde(flatten)]
+    pub options: toml::Table,
+}
+
+/// A `[[highlight]]` entry: a term highlighted from the start, as with `--highlight`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightRule {
+    pub term: String,
+    pub color: Option<Color>,
+}
+
+fn key_bindings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(KeyCode, Command)>, D::Error> {
+    let table = IndexMap::<String, String>::deserialize(deserializer)?;
+    table
+        .iter()
+        .map(|(key, command)| parse_key_binding(key, command).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// `config.toml` in the platform config directory (e.g. `~/.config/tracing-viewer/`)
+pub fn default_path() -> Option<std::path::PathBuf> {
+    Some(dirs::config_dir()?.join("tracing-viewer").join("config.toml"))
+}
+
+/// Parse a config file
+pub fn parse(text: &str) -> anyhow::Result<Config> {
+    Ok(toml::from_str(text)?)
+}
+
+/// The value as it would be typed after the option on the command line
+fn to_arg(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(text) => Some(text.clone()),
+        toml::Value::Integer(number) => Some(number.to_string()),
+        toml::Value::Float(number) => Some(number.to_string()),
+        toml::Value::Boolean(flag) => Some(flag.to_string()),
+        toml::Value::Datetime(datetime) => Some(datetime.to_string()),
+        toml::Value::Array(_) | toml::Value::Table(_) => None,
+    }
+}
+
+/// Command-line arguments for the config `options` that `matches` didn't get from the
+/// command line itself. Keys may use `-` or `_` between words.
+pub fn to_args(options: &toml::Table, command: &clap::Command, matches: &ArgMatches) -> anyhow::Result<Vec<String>> {
+    let mut args = Vec::new();
+    for (key, value) in options {
+        let long = key.replace('_', "-");
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(long.as_str())) else {
+            anyhow::bail!("unknown option `{}`", key);
+        };
+        if COMMAND_LINE_ONLY.contains(&long.as_str()) {
+            anyhow::bail!("`{}` can only be given on the command line", key);
+        }
+        if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let flag = format!("--{}", long);
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, toml::Value::Boolean(enabled)) => {
+                if *enabled {
+                    args.push(flag);
+                }
+            }
+            (ArgAction::SetTrue, _) => anyhow::bail!("`{}` must be true or false", key),
+            (ArgAction::Append, toml::Value::Array(values)) => {
+                for value in values {
+                    let value = to_arg(value).ok_or_else(|| anyhow::anyhow!("`{}` can't contain arrays or tables", key))?;
+                    args.extend([flag.clone(), value]);
+                }
+            }
+            (_, value) => {
+                let value = to_arg(value).ok_or_else(|| anyhow::anyhow!("`{}` takes a single value, not an array or table", key))?;
+                args.extend([flag, value]);
+            }
+        }
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, Parser as _};
+
+    #[derive(clap::Parser, Debug)]
+    struct TestCli {
+        #[arg(long, default_value = "300")]
+        refresh: u64,
+        #[arg(long)]
+        format: Option<String>,
+        #[arg(long)]
+        header: bool,
+        #[arg(long, value_delimiter = ',')]
+        status_right: Vec<String>,
+        #[arg(long)]
+        config: Option<String>,
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let text = r##"# defaults
+refresh = 1_000
+format = "json"  # trailing comment
+log_regex = '^(?P<message>\S+)$'
+header = true
+status-right = [
+  "filters",
+  "clock", # the time
+]
+
+[keys]
+x = "toggle-raw"
+f5 = "reload"
+
+[theme]
+target = "darkgray"
+levels = { warn = "#ff8800" }
+
+[[highlight]]
+term = "timeout"
+color = "red"
+
+[[highlight]]
+term = "retry"
+"##;
+        let config = parse(text).unwrap();
+        let options: Vec<&str> = config.options.keys().map(String::as_str).collect();
+        assert_eq!(options, ["refresh", "format", "log_regex", "header", "status-right"]);
+        assert_eq!(config.options["refresh"].as_integer(), Some(1000));
+        assert_eq!(config.options["log_regex"].as_str(), Some(r"^(?P<message>\S+)$"));
+        assert_eq!(config.keys, [(KeyCode::Char('x'), Command::ToggleRawView), (KeyCode::F(5), Command::Reload)]);
+        assert_eq!(config.theme.target, Color::DarkGray);
+        assert_eq!(config.theme.timestamp, Theme::default().timestamp);
+        assert_eq!(config.theme.level_style("WARN").fg, Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(
+            config.highlight,
+            [
+                HighlightRule { term: "timeout".to_string(), color: Some(Color::Red) },
+                HighlightRule { term: "retry".to_string(), color: None },
+            ]
+        );
+
+        assert!(parse("").unwrap().keys.is_empty());
+        assert!(parse("a = \"open").is_err());
+        assert!(parse("a = 1\na = 2").is_err());
+        assert!(parse("[keys]\nctrl-x = \"quit\"").unwrap_err().to_string().contains("unknown key `ctrl-x`"));
+        assert!(parse("[keys]\nx = \"explode\"").unwrap_err().to_string().contains("unknown command `explode`"));
+        assert!(parse("[theme]\ntarget = \"notacolor\"").is_err());
+        assert!(parse("[theme]\nbackground = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_command_line_overrides_config() {
+        let config = parse("refresh = 50\nformat = \"json\"\nheader = true\nstatus_right = [\"clock\"]").unwrap();
+        let command = TestCli::command();
+        let matches = command.clone().get_matches_from(["test", "--format", "tracing"]);
+        let args = to_args(&config.options, &command, &matches).unwrap();
+        assert_eq!(args, ["--refresh", "50", "--header", "--status-right", "clock"]);
+
+        let cli = TestCli::parse_from(["test"].into_iter().map(String::from).chain(args).chain(["--format".to_string(), "tracing".to_string()]));
+        assert_eq!((cli.refresh, cli.format.as_deref(), cli.header), (50, Some("tracing"), true));
+        assert_eq!(cli.status_right, ["clock"]);
+
+        let unknown = parse("colour = \"red\"").unwrap();
+        assert_eq!(to_args(&unknown.options, &command, &matches).unwrap_err().to_string(), "unknown option `colour`");
+        let table = parse("[viewer]\nrefresh = 1").unwrap();
+        assert_eq!(to_args(&table.options, &command, &matches).unwrap_err().to_string(), "unknown option `viewer`");
+        let nested = parse("config = \"other.toml\"").unwrap();
+        assert!(to_args(&nested.options, &command, &matches).is_err());
+        let wrong = parse("header = \"yes\"").unwrap();
+        assert!(to_args(&wrong.options, &command, &matches).is_err());
+        let array = parse("refresh = [1, 2]").unwrap();
+        assert!(to_args(&array.options, &command, &matches).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Reports of log entries, for sharing a filtered slice with people outside the terminal
+//! (wiki pages, email): a self-contained HTML page, where levels keep their terminal
+//! colors and multi-line messages collapse to their first line, or plain text.
+
+use crate::log_parser::LogEntry;
+use crate::template::EntryTemplate;
+use crate::ui::{level_style, source_label};
+use ratatui::style::{Color, Modifier, Style};
+use std::collections::HashMap;
+use std::fmt;
+
+const STYLESHEET: &str = "body { background: #1e1e1e; color: #d4d4d4; font: 13px/1.4 ui-monospace, Menlo, Consolas, monospace; margin: 1em; }
+h1 { font-size: 15px; font-weight: normal; color: #9e9e9e; }
+.entry { white-space: pre-wrap; padding: 1px 0; border-bottom: 1px solid #2a2a2a; }
+.timestamp, .fields { color: #808080; }
+.target { color: #9cdcfe; }
+details { display: inline; }
+summary { cursor: pointer; display: inline; }
+.more { color: #808080; }
+.anchor { color: #4a4a4a; text-decoration: none; }
+.anchor:hover { color: #9e9e9e; }
+.entry:target { background: #264f78; }
+.note { color: #e5c07b; margin-left: 2em; }";
+
+/// How an entry is addressed in report links and by `--goto`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryAnchor {
+    /// Line `line` of the input named `file` (see `anchor_file`), e.g. `app.log:120`. It
+    /// doesn't depend on load order, so it holds across reloads and sessions.
+    Line { file: String, line: usize },
+    /// `LogEntry::id`, e.g. `e42`, for entries without a position in their input
+    Id(u64),
+}
+
+impl fmt::Display for EntryAnchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryAnchor::Line { file, line } => write!(f, "{}:{}", file, line),
+            EntryAnchor::Id(id) => write!(f, "e{}", id),
+        }
+    }
+}
+
+/// Anchor of `entry` in reports; `--goto` accepts it as well
+pub fn entry_anchor(entry: &LogEntry) -> EntryAnchor {
+    match entry.line_number {
+        Some(line) => EntryAnchor::Line { file: anchor_file(&entry.source), line },
+        None => EntryAnchor::Id(entry.id),
+    }
+}
+
+/// File name of `source` as used in anchors; whitespace becomes `_` since HTML ids can't hold it
+pub fn anchor_file(source: &str) -> String {
+    source_label(source).chars().map(|c| if c.is_whitespace() { '_' } else { c }).collect()
+}
+
+/// The anchor in `app.log:120`, `42`, `e42` or a `report.html#app.log:120` link
+pub fn parse_entry_anchor(text: &str) -> Option<EntryAnchor> {
+    let text = text.trim();
+    let text = text.rsplit_once('#').map_or(text, |(_, fragment)| fragment);
+    if let Some((file, line)) = text.rsplit_once(':')
+        && !file.is_empty()
+    {
+        return Some(EntryAnchor::Line { file: file.to_string(), line: line.parse().ok()? });
+    }
+    text.strip_prefix('e').unwrap_or(text).parse().ok().map(EntryAnchor::Id)
+}
+
+/// The report for `entries`, headed by `title`, with `notes` (by entry id) under their
+/// entries when given. Each entry carries its `entry_anchor` as id and links to itself, so
+/// a line can be shared as `report.html#app.log:120`.
+pub fn html_report(entries: &[LogEntry], title: &str, notes: Option<&HashMap<u64, String>>) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(title),
+        STYLESHEET,
+        escape(title)
+    );
+    for entry in entries {
+        let anchor = escape(&entry_anchor(entry).to_string());
+        html.push_str(&format!("<div class=\"entry\" id=\"{0}\"><a class=\"anchor\" href=\"#{0}\">#</a> ", anchor));
+        if !entry.timestamp.is_empty() {
+            html.push_str(&format!("<span class=\"timestamp\">{}</span> ", escape(&entry.timestamp)));
+        }
+        html.push_str(&format!("<span style=\"{}\">{:<5}</span> ", css(level_style(&entry.level)), escape(&entry.level)));
+        html.push_str(&format!("<span class=\"target\">{}</span>: ", escape(&entry.target)));
+        let mut lines = entry.message.lines();
+        let first = lines.next().unwrap_or_default();
+        let rest: Vec<&str> = lines.collect();
+        if rest.is_empty() {
+            html.push_str(&escape(first));
+        } else {
+            // 続きの行は折りたたんでおき、クリックで開く
+            html.push_str(&format!(
+                "<details><summary>{} <span class=\"more\">(+{} {})</span></summary>\n{}</details>",
+                escape(first),
+                rest.len(),
+                if rest.len() == 1 { "line" } else { "lines" },
+                escape(&rest.join("\n"))
+            ));
+        }
+        if !entry.fields.is_empty() {
+            let fields: Vec<String> = entry.fields.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            html.push_str(&format!(" <span class=\"fields\">{}</span>", escape(&fields.join(" "))));
+        }
+        if let Some(note) = notes.and_then(|notes| notes.get(&entry.id)) {
+            html.push_str(&format!("<div class=\"note\">✎ {}</div>", escape(note)));
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// `entries` as text, one `template` line each, with `notes` (by entry id) on an indented
+/// line after their entries when given
+pub fn text_report(entries: &[LogEntry], template: &EntryTemplate, notes: Option<&HashMap<u64, String>>) -> String {
+    let mut text = String::new();
+    for entry in entries {
+        text.push_str(&template.render(entry));
+        text.push('\n');
+        if let Some(note) = notes.and_then(|notes| notes.get(&entry.id)) {
+            text.push_str(&note_line(note));
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// How a note follows its entry in text output
+pub fn note_line(note: &str) -> String {
+    format!("    ✎ {}", note)
+}
+
+/// Inline CSS for a terminal style, with the usual terminal palette for named colors
+fn css(style: Style) -> String {
+    let color = match style.fg.unwrap_or(Color::Reset) {
+        Color::Red | Color::LightRed => "#f14c4c".to_string(),
+        Color::Yellow | Color::LightYellow => "#e5c07b".to_string(),
+        Color::Green | Color::LightGreen => "#23d18b".to_string(),
+        Color::Blue | Color::LightBlue => "#3b8eea".to_string(),
+        Color::Magenta | Color::LightMagenta => "#d670d6".to_string(),
+        Color::Cyan | Color::LightCyan => "#29b8db".to_string(),
+        Color::Gray | Color::DarkGray => "#808080".to_string(),
+        Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        _ => "#e5e5e5".to_string(),
+    };
+    if style.add_modifier.contains(Modifier::BOLD) {
+        format!("color: {}; font-weight: bold", color)
+    } else {
+        format!("color: {}", color)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_report_colors_levels_and_collapses_multiline() {
+        let entries = vec![
+            LogEntry {
+                level: "ERROR".to_string(),
+                target: "app".to_string(),
+                message: "boom <here>\n  at main\n  at start".to_string(),
+                source: "/var/log/my app.log".to_string(),
+                line_number: Some(12),
+                ..Default::default()
+            },
+            LogEntry { level: "INFO".to_string(), target: "app".to_string(), message: "ok & done".to_string(), id: 1, ..Default::default() },
+        ];
+        let notes = HashMap::from([(0, "first <failure>".to_string())]);
+        let html = html_report(&entries, "app.log", Some(&notes));
+        assert!(html.contains("<title>app.log</title>"));
+        assert!(html.contains("<div class=\"entry\" id=\"my_app.log:12\"><a class=\"anchor\" href=\"#my_app.log:12\">#</a> "));
+        assert!(html.contains("<div class=\"entry\" id=\"e1\">"));
+        assert!(html.contains("<span style=\"color: #f14c4c\">ERROR</span>"));
+        assert!(html.contains("<details><summary>boom &lt;here&gt; <span class=\"more\">(+2 lines)</span></summary>\n  at main\n  at start</details>"));
+        assert!(html.contains("</details><div class=\"note\">✎ first &lt;failure&gt;</div></div>"));
+        assert!(html.contains("<span style=\"color: #23d18b\">INFO </span> <span class=\"target\">app</span>: ok &amp; done</div>"));
+        assert!(!html_report(&entries, "app.log", None).contains("class=\"note\""));
+    }
+
+    #[test]
+    fn test_text_report_puts_notes_under_entries() {
+        let entries = vec![
+            LogEntry { level: "ERROR".to_string(), message: "boom".to_string(), ..Default::default() },
+            LogEntry { level: "INFO".to_string(), message: "ok".to_string(), id: 1, ..Default::default() },
+        ];
+        let template = EntryTemplate::parse("{level} {message}").unwrap();
+        let notes = HashMap::from([(0, "first failure".to_string())]);
+        assert_eq!(text_report(&entries, &template, Some(&notes)), "ERROR boom\n    ✎ first failure\nINFO ok\n");
+        assert_eq!(text_report(&entries, &template, None), "ERROR boom\nINFO ok\n");
+    }
+
+    #[test]
+    fn test_entry_anchors_round_trip() {
+        let streamed = LogEntry { id: 42, ..Default::default() };
+        assert_eq!(entry_anchor(&streamed).to_string(), "e42");
+        for text in ["42", "e42", "#e42", "report.html#e42", " e42 "] {
+            assert_eq!(parse_entry_anchor(text), Some(EntryAnchor::Id(42)), "{}", text);
+        }
+        let read = LogEntry { source: "logs/app.log".to_string(), line_number: Some(120), id: 7, ..Default::default() };
+        assert_eq!(entry_anchor(&read).to_string(), "app.log:120");
+        let line = Some(EntryAnchor::Line { file: "app.log".to_string(), line: 120 });
+        assert_eq!(parse_entry_anchor("report.html#app.log:120"), line);
+        assert_eq!(parse_entry_anchor("app.log:120"), line);
+        assert_eq!(parse_entry_anchor("app.log:x"), None);
+        assert_eq!(parse_entry_anchor("x42"), None);
+        assert_eq!(parse_entry_anchor(""), None);
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of remembered values per input kind
+const MAX_ENTRIES: usize = 50;
+
+/// Directory holding the viewer's persisted state (`~/.config/tracing-viewer` on Linux)
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tracing-viewer"))
+}
+
+/// Recently committed prompt values, keyed by input kind and ordered oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputHistory {
+    #[serde(default)]
+    entries: HashMap<String, Vec<String>>,
+}
+
+impl InputHistory {
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("history.json"))
+    }
+
+    /// Load history from `path`; a missing or unreadable file yields an empty history
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Record `value` as the most recent entry for `key`, dropping any older duplicate
+    pub fn push(&mut self, key: &str, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        let values = self.entries.entry(key.to_string()).or_default();
+        values.retain(|existing| existing != value);
+        values.push(value.to_string());
+        if values.len() > MAX_ENTRIES {
+            values.drain(..values.len() - MAX_ENTRIES);
+        }
+    }
+
+    pub fn entries(&self, key: &str) -> &[String] {
+        self.entries.get(key).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_dedupes_and_caps() {
+        let mut history = InputHistory::default();
+        history.push("search", "timeout");
+        history.push("search", "retry");
+        history.push("search", "timeout");
+        history.push("search", "");
+        assert_eq!(history.entries("search"), ["retry", "timeout"]);
+        assert!(history.entries("grep").is_empty());
+
+        for i in 0..MAX_ENTRIES + 5 {
+            history.push("search", &i.to_string());
+        }
+        assert_eq!(history.entries("search").len(), MAX_ENTRIES);
+        assert_eq!(history.entries("search").last().unwrap(), &(MAX_ENTRIES + 4).to_string());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let path = std::env::temp_dir().join(format!("tracing-viewer-history-{}.json", std::process::id()));
+        let mut history = InputHistory::default();
+        history.push("search", "connection reset");
+        history.save(&path).unwrap();
+
+        assert_eq!(InputHistory::load(&path), history);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(InputHistory::load(&path), InputHistory::default());
+    }
+}
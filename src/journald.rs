@@ -0,0 +1,102 @@
+//! Reading the systemd journal through `journalctl -o json`.
+//!
+//! Journal records are rewritten into JSON lines the regular JSON parser understands,
+//! so they flow through the same ingest path as every other source.
+
+use crate::log_parser::SeverityOrder;
+
+/// Entries already in the journal shown before following new ones
+pub const JOURNAL_BACKLOG: usize = 1000;
+
+/// Arguments for `journalctl` following `unit` as JSON
+pub fn journalctl_args(unit: &str) -> Vec<String> {
+    ["--follow", "--unit", unit, "--output", "json", "--lines"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain([JOURNAL_BACKLOG.to_string()])
+        .collect()
+}
+
+/// Level for a syslog priority (`0` emerg … `7` debug). Priorities get their own names
+/// (`EMERG`, `NOTICE`, …) when `severity` has them, as the syslog preset does.
+pub fn priority_level(priority: u8, severity: &SeverityOrder) -> &'static str {
+    match priority {
+        0 => severity.level_or("EMERG", "ERROR"),
+        1 => severity.level_or("ALERT", "ERROR"),
+        2 => severity.level_or("CRITICAL", "ERROR"),
+        3 => "ERROR",
+        4 => "WARN",
+        5 => severity.level_or("NOTICE", "INFO"),
+        6 => "INFO",
+        _ => "DEBUG",
+    }
+}
+
+/// Rewrite one `journalctl -o json` record as a JSON line with `timestamp`, `level`,
+/// `target` and `message`, keeping the unit and PID as fields. The target is the syslog
+/// identifier, falling back to the unit. Levels are named as `priority_level` does for
+/// `severity`. Returns `None` for lines that aren't records.
+pub fn normalize_record(line: &str, severity: &SeverityOrder) -> Option<String> {
+    let serde_json::Value::Object(record) = serde_json::from_str(line.trim()).ok()? else {
+        return None;
+    };
+    let text = |key: &str| match record.get(key)? {
+        serde_json::Value::String(text) => Some(text.clone()),
+        // 不正なUTF-8を含むメッセージはバイト列の配列で出力される
+        serde_json::Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes.iter().filter_map(|byte| byte.as_u64()).map(|byte| byte as u8).collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        _ => None,
+    };
+
+    let micros = text("__REALTIME_TIMESTAMP")?.parse::<i64>().ok()?;
+    let timestamp = chrono::DateTime::from_timestamp_micros(micros)?.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+    let level = text("PRIORITY").and_then(|priority| priority.parse().ok()).map_or("INFO", |priority| priority_level(priority, severity));
+    let unit = text("_SYSTEMD_UNIT");
+    let target = text("SYSLOG_IDENTIFIER").or_else(|| unit.clone()).unwrap_or_else(|| "journal".to_string());
+
+    let mut normalized = serde_json::Map::new();
+    normalized.insert("timestamp".to_string(), timestamp.into());
+    normalized.insert("level".to_string(), level.into());
+    normalized.insert("target".to_string(), target.into());
+    normalized.insert("message".to_string(), text("MESSAGE").unwrap_or_default().into());
+    if let Some(unit) = unit {
+        normalized.insert("unit".to_string(), unit.into());
+    }
+    if let Some(pid) = text("_PID") {
+        normalized.insert("pid".to_string(), pid.into());
+    }
+    Some(serde_json::Value::Object(normalized).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_parser::{LogParser, SeverityPreset};
+
+    #[test]
+    fn test_normalize_record() {
+        let record = r#"{"__REALTIME_TIMESTAMP":"1704110400123456","PRIORITY":"4","_SYSTEMD_UNIT":"api.service","SYSLOG_IDENTIFIER":"api","_PID":"812","MESSAGE":"slow request"}"#;
+        let entry = LogParser::new().unwrap().parse_line(&normalize_record(record, &SeverityOrder::default()).unwrap()).unwrap();
+        assert_eq!(entry.timestamp, "2024-01-01T12:00:00.123456Z");
+        assert_eq!((entry.level.as_str(), entry.target.as_str()), ("WARN", "api"));
+        assert_eq!(entry.message, "slow request");
+        assert_eq!(entry.fields.get("unit").map(String::as_str), Some("api.service"));
+        assert_eq!(entry.fields.get("pid").map(String::as_str), Some("812"));
+
+        let binary = r#"{"__REALTIME_TIMESTAMP":"1704110400000000","_SYSTEMD_UNIT":"api.service","MESSAGE":[104,105,255]}"#;
+        let entry = LogParser::new().unwrap().parse_line(&normalize_record(binary, &SeverityOrder::default()).unwrap()).unwrap();
+        assert_eq!((entry.level.as_str(), entry.target.as_str()), ("INFO", "api.service"));
+        assert_eq!(entry.message, "hi\u{fffd}");
+
+        assert_eq!(normalize_record("-- No entries --", &SeverityOrder::default()), None);
+        let tracing = SeverityOrder::default();
+        assert_eq!((priority_level(2, &tracing), priority_level(5, &tracing), priority_level(7, &tracing)), ("ERROR", "INFO", "DEBUG"));
+        // syslogの段階では優先度ごとの名前になる
+        let syslog = SeverityOrder::preset(SeverityPreset::Syslog);
+        let levels: Vec<&str> = (0..8).map(|priority| priority_level(priority, &syslog)).collect();
+        assert_eq!(levels, ["EMERG", "ALERT", "CRITICAL", "ERROR", "WARN", "NOTICE", "INFO", "DEBUG"]);
+        assert!(levels.iter().all(|level| syslog.rank(level) < syslog.levels().len()));
+    }
+}
@@ -0,0 +1,870 @@
+//! Key handling for `App`, kept free of terminal I/O so it can be driven headlessly.
+//! `main` only translates terminal events and calls `App::on_key`.
+//!
+//! Each handler returns whether the key was consumed (and the screen needs a redraw).
+//! Side effects that need the outside world are left on `App` for the caller:
+//! text to copy goes to `clipboard_request`, a reload to `reload_requested`, a report to
+//! write to `export_request`.
+
+use crate::palette::Command;
+use crate::ui::{App, AppMode, InputKind};
+use crossterm::event::KeyCode;
+
+/// Parse a `[keys]` entry of the config file: a single character (`x`, `?`) or a key
+/// name (`enter`, `tab`, `backspace`, `delete`, `home`, `end`, `pageup`, `pagedown`,
+/// `up`, `down`, `left`, `right`, `f1`-`f12`), bound to a palette command name
+pub fn parse_key_binding(key: &str, command: &str) -> Result<(KeyCode, Command), String> {
+    let mut chars = key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match key.to_ascii_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            name => match name.strip_prefix('f').and_then(|number| number.parse().ok()) {
+                Some(number @ 1..=12) => KeyCode::F(number),
+                _ => return Err(format!("unknown key `{}`", key)),
+            },
+        },
+    };
+    let command = Command::from_name(command).ok_or_else(|| format!("unknown command `{}` for key `{}`", command, key))?;
+    Ok((code, command))
+}
+
+impl App {
+    /// Handle a key press: the input prompt and popups take precedence, then the
+    /// handler for the current mode
+    pub fn on_key(&mut self, key: KeyCode) -> bool {
+        // q以外のキーが押されたら終了の確認を取り消す
+        if key != KeyCode::Char('q') {
+            self.quit_armed_at = None;
+        }
+        // フォーマットの警告は最前面に出ているので、どのキーでも閉じるだけにする
+        if self.format_warning.is_some() {
+            self.dismiss_format_warning();
+            return true;
+        }
+        // 入力プロンプトが表示されている間はすべてのキーをプロンプトに渡す
+        if self.input.is_some() {
+            return self.on_input_key(key);
+        }
+        if self.palette.is_some() {
+            return self.on_palette_key(key);
+        }
+        if self.recent_files.is_some() {
+            return self.on_recent_files_key(key);
+        }
+        if self.pattern_tester.is_some() {
+            return self.on_pattern_tester_key(key);
+        }
+        // エラーバナーはどのモードからでもxで閉じられる
+        if self.error_banner.is_some() && key == KeyCode::Char('x') {
+            self.dismiss_error();
+            return true;
+        }
+        // 詳細表示中はEnter/Escで閉じ、yでフィールドの値をコピーする
+        if self.show_details {
+            match key {
+                KeyCode::Esc | KeyCode::Enter => self.toggle_details(),
+                KeyCode::Char('y') => self.start_yank_field(),
+                _ => {}
+            }
+            return true;
+        }
+        // 比較ビューの表示中はEsc/Dで閉じる。qは他の画面と同じく終了する
+        if self.show_compare {
+            match key {
+                KeyCode::Esc | KeyCode::Char('D') => self.toggle_compare_view(),
+                KeyCode::Char('q') => self.quit(),
+                _ => {}
+            }
+            return true;
+        }
+        match self.mode {
+            AppMode::ModuleSelection => self.on_module_selection_key(key),
+            AppMode::LogNavigation => self.on_log_navigation_key(key),
+            AppMode::TextSelection => self.on_text_selection_key(key),
+            AppMode::LogLevelFilter => self.on_log_level_filter_key(key),
+            AppMode::SourceFilter => self.on_source_filter_key(key),
+        }
+    }
+
+    /// Handle a key pressed with Ctrl. Ctrl-d/Ctrl-u scroll half a page in the log view;
+    /// any other key is handled by `on_key` as if pressed alone.
+    pub fn on_control_key(&mut self, key: KeyCode) -> bool {
+        let popup_open = self.format_warning.is_some()
+            || self.input.is_some()
+            || self.palette.is_some()
+            || self.recent_files.is_some()
+            || self.pattern_tester.is_some()
+            || self.show_details
+            || self.show_compare;
+        if popup_open || !matches!(self.mode, AppMode::LogNavigation | AppMode::TextSelection) {
+            return self.on_key(key);
+        }
+        match key {
+            KeyCode::Char('d') => self.half_page_down(self.page_height()),
+            KeyCode::Char('u') => self.half_page_up(self.page_height()),
+            _ => return self.on_key(key),
+        }
+        self.quit_armed_at = None;
+        true
+    }
+
+    pub fn on_input_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Esc => self.cancel_input(),
+            KeyCode::Enter => self.commit_input(),
+            KeyCode::Backspace => self.input_backspace(),
+            KeyCode::Up => self.input_history_previous(),
+            KeyCode::Down => self.input_history_next(),
+            KeyCode::Tab => self.complete_input(),
+            KeyCode::Char(c) => self.input_char(c),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keys while the pattern tester is open: typing edits the pattern
+    pub fn on_pattern_tester_key(&mut self, key: KeyCode) -> bool {
+        let Some(tester) = self.pattern_tester.as_mut() else {
+            return false;
+        };
+        match key {
+            KeyCode::Esc => self.close_pattern_tester(),
+            KeyCode::Enter => self.accept_pattern(),
+            KeyCode::Backspace => {
+                tester.pattern.pop();
+            }
+            KeyCode::Char(c) => tester.pattern.push(c),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn on_palette_key(&mut self, key: KeyCode) -> bool {
+        let Some(palette) = self.palette.as_mut() else {
+            return false;
+        };
+        match key {
+            KeyCode::Esc => self.close_palette(),
+            KeyCode::Enter => {
+                let command = palette.selected_command();
+                self.close_palette();
+                if let Some(command) = command {
+                    self.run_command(command);
+                }
+            }
+            KeyCode::Down => palette.select_next(),
+            KeyCode::Up => palette.select_previous(),
+            KeyCode::Backspace => palette.pop(),
+            KeyCode::Char(c) => palette.push(c),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keys while the recent-files picker is open. Nothing is loaded yet, so closing it quits.
+    pub fn on_recent_files_key(&mut self, key: KeyCode) -> bool {
+        let Some(recent_files) = self.recent_files.as_mut() else {
+            return false;
+        };
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => self.should_quit = true,
+            KeyCode::Enter => {
+                if let Some(path) = recent_files.selected_file() {
+                    self.open_file_request = Some(path.to_string());
+                    self.recent_files = None;
+                }
+            }
+            KeyCode::Down | KeyCode::Char('j') => recent_files.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => recent_files.select_previous(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Run a palette command through the same method its key binding calls
+    pub fn run_command(&mut self, command: Command) {
+        match command {
+            Command::ClearFilters => self.clear_all_filters(),
+            Command::SelectAllModules => self.select_all_modules(),
+            Command::DeselectAllModules => self.deselect_all_modules(),
+            Command::CycleMinLevel => self.cycle_min_level(),
+            Command::LowerMinLevel => self.cycle_min_level_back(),
+            Command::Search => self.start_input(InputKind::Search),
+            Command::Grep => self.toggle_grep(),
+            Command::FieldFilter => self.toggle_field_filter(),
+            Command::CopyField => self.start_yank_field(),
+            Command::Reload => self.reload_requested = true,
+            Command::TestPattern => self.open_pattern_tester(),
+            Command::ToggleSplit => self.toggle_split_view(),
+            Command::ToggleMinimap => self.toggle_minimap(),
+            Command::ToggleMessageOnly => self.toggle_message_only(),
+            Command::ToggleRawView => self.toggle_raw_view(),
+            Command::ToggleFreeze => self.toggle_freeze(),
+            Command::ToggleFilterPanel => self.toggle_filter_panel(),
+            Command::ToggleHeader => self.toggle_header(),
+            Command::CopyViewCommand => self.copy_view_command(),
+            Command::Note => self.start_note(),
+            Command::Highlight => self.start_input(InputKind::Highlight),
+            Command::ClearHighlights => self.clear_highlight_terms(),
+            Command::ExportHtml => self.export_html(),
+            Command::ExportText => self.export_text(),
+            Command::TraceFilter => self.toggle_trace_filter(),
+            Command::MarkA => self.toggle_entry_mark(0),
+            Command::MarkB => self.toggle_entry_mark(1),
+            Command::ToggleCompare => self.toggle_compare_view(),
+            Command::ToggleDetails => self.toggle_details(),
+            Command::CycleTimestamps => self.cycle_timestamp_mode(),
+            Command::CycleLineNumbers => self.cycle_line_number_mode(),
+            Command::GotoBottom => self.scroll_to_bottom(),
+            Command::Quit => self.quit(),
+        }
+    }
+
+    /// Lines in a page for PgUp/PgDn, leaving room for the status bar and pagination line
+    fn page_height(&self) -> usize {
+        self.last_terminal_size.1.saturating_sub(3) as usize
+    }
+
+    /// Quick level toggles shared by every mode except text selection
+    fn on_level_shortcut(&mut self, key: KeyCode) -> bool {
+        if key == KeyCode::Char('L') {
+            self.cycle_min_level();
+            return true;
+        }
+        if key == KeyCode::Char('<') {
+            self.cycle_min_level_back();
+            return true;
+        }
+        // 数字キーは深刻度の順に1から割り当てる
+        let level = match key {
+            KeyCode::Char(digit @ '1'..='9') => self.severity.levels().get(digit as usize - '1' as usize).cloned(),
+            _ => None,
+        };
+        let Some(level) = level else {
+            return false;
+        };
+        self.toggle_log_level(&level);
+        true
+    }
+
+    /// Keys while a visual range is being marked in the module panel
+    pub fn on_module_visual_key(&mut self, key: KeyCode) -> bool {
+        let selected = self.module_list_state.selected().unwrap_or(0);
+        match key {
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_module_visual_range(),
+            // 範囲選択中はリストの端でログレベル側に移らない
+            KeyCode::Down | KeyCode::Char('j') if selected + 1 < self.module_items.len() => self.next_module(),
+            KeyCode::Up | KeyCode::Char('k') if selected > 0 => self.previous_module(),
+            KeyCode::Esc | KeyCode::Char('V') => self.cancel_module_visual(),
+            KeyCode::Char('q') => self.quit(),
+            _ => {}
+        }
+        true
+    }
+
+    pub fn on_module_selection_key(&mut self, key: KeyCode) -> bool {
+        if self.module_visual_anchor.is_some() {
+            return self.on_module_visual_key(key);
+        }
+        if self.on_level_shortcut(key) {
+            return true;
+        }
+        match key {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_selected_module(),
+            KeyCode::Char('l') => self.cycle_selected_module_min_level(),
+            KeyCode::Char('V') => self.start_module_visual(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                // モジュールリストの最下部にいる場合、ログレベル選択に移動
+                if !self.module_items.is_empty()
+                    && self.module_list_state.selected().unwrap_or(0) == self.module_items.len().saturating_sub(1)
+                {
+                    self.switch_to_log_level_mode();
+                } else {
+                    self.next_module();
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                // モジュールリストの最上部にいる場合、入力元一覧（なければログレベル選択）の最下部に移動
+                if self.module_list_state.selected().unwrap_or(0) == 0 && self.has_source_panel() {
+                    self.switch_to_source_mode();
+                    self.selected_source_index = self.sources.len() - 1;
+                } else if self.module_list_state.selected().unwrap_or(0) == 0 {
+                    self.switch_to_log_level_mode();
+                    if !self.available_log_levels.is_empty() {
+                        self.selected_log_level_index = self.available_log_levels.len() - 1;
+                    }
+                } else {
+                    self.previous_module();
+                }
+            }
+            KeyCode::Tab => self.switch_to_log_mode(),
+            KeyCode::Char('r') => self.filter_logs(),
+            KeyCode::Char('C') => self.clear_all_filters(),
+            KeyCode::Char(':') => self.open_palette(),
+            KeyCode::Char('a') => self.select_all_modules(),
+            KeyCode::Char('n') => self.deselect_all_modules(),
+            KeyCode::Char('b') => self.toggle_filter_panel(),
+            KeyCode::Char(',') => self.decrease_panel_width(),
+            KeyCode::Char('.') => self.increase_panel_width(),
+            _ => {}
+        }
+        true
+    }
+
+    pub fn on_log_navigation_key(&mut self, key: KeyCode) -> bool {
+        // 設定ファイルで割り当てたキーは組み込みの割り当てより優先する
+        if let Some(&(_, command)) = self.key_bindings.iter().find(|(bound, _)| *bound == key) {
+            self.run_command(command);
+            return true;
+        }
+        if self.on_level_shortcut(key) {
+            return true;
+        }
+        match key {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Char('R') => self.reload_requested = true,
+            KeyCode::Char('S') => self.toggle_split_view(),
+            KeyCode::Char('m') => self.toggle_minimap(),
+            KeyCode::Char('p') => self.open_pattern_tester(),
+            KeyCode::Char('F') => self.toggle_field_filter(),
+            KeyCode::Char('T') => self.toggle_trace_filter(),
+            KeyCode::Char('a') => self.start_note(),
+            KeyCode::Char('h') => self.start_input(InputKind::Highlight),
+            KeyCode::Char('&') => self.toggle_grep(),
+            KeyCode::Char(':') => self.open_palette(),
+            KeyCode::Char('b') => self.toggle_filter_panel(),
+            KeyCode::Enter => self.toggle_details(),
+            KeyCode::Tab => {
+                self.show_filter_panel = true;
+                self.switch_to_module_mode();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.next_log_entry(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous_log_entry(),
+            KeyCode::PageUp => self.page_up(self.page_height()),
+            KeyCode::PageDown => self.page_down(self.page_height()),
+            KeyCode::Char('v') => self.start_text_selection(),
+            KeyCode::Esc => {
+                self.scroll_to_bottom();
+                self.switch_to_module_mode();
+            }
+            KeyCode::Char('c') => self.clear_status_message(),
+            KeyCode::Char('/') => self.start_input(InputKind::Search),
+            KeyCode::Char('n') => self.search_next(),
+            KeyCode::Char('N') => self.search_previous(),
+            KeyCode::Char('t') => self.cycle_timestamp_mode(),
+            KeyCode::Char('#') => self.cycle_line_number_mode(),
+            KeyCode::Char('M') => self.toggle_message_only(),
+            KeyCode::Char('r') => self.toggle_raw_view(),
+            KeyCode::Char('P') => self.toggle_freeze(),
+            KeyCode::Char('H') => self.toggle_header(),
+            KeyCode::Char('D') => self.toggle_compare_view(),
+            KeyCode::Char('A') => self.toggle_entry_mark(0),
+            KeyCode::Char('B') => self.toggle_entry_mark(1),
+            KeyCode::Char('e') => {
+                if let Some(command) = self.editor_command_for_focused() {
+                    self.status_message = Some(format!("Copied `{}` to clipboard", command));
+                    self.clipboard_request = Some(command);
+                }
+            }
+            KeyCode::Char('E') => self.copy_view_command(),
+            KeyCode::Char('y') => self.start_yank_field(),
+            KeyCode::Char('C') => self.clear_all_filters(),
+            _ => {}
+        }
+        true
+    }
+
+    pub fn on_text_selection_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Down | KeyCode::Char('j') => self.next_log_entry(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous_log_entry(),
+            KeyCode::PageUp => self.page_up(self.page_height()),
+            KeyCode::PageDown => self.page_down(self.page_height()),
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                // Yはフィールドもkey=value形式で付けてコピーする
+                let copied = if key == KeyCode::Char('Y') {
+                    self.copy_selected_logs_with_fields()
+                } else {
+                    self.copy_selected_logs()
+                };
+                if let Ok(selected_text) = copied
+                    && !selected_text.is_empty()
+                {
+                    self.clipboard_request = Some(selected_text);
+                }
+                self.clear_selection();
+            }
+            KeyCode::Char('A') => self.capture_compare_window(0),
+            KeyCode::Char('B') => self.capture_compare_window(1),
+            KeyCode::Esc => self.clear_selection(),
+            KeyCode::Char('c') => self.clear_status_message(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn on_log_level_filter_key(&mut self, key: KeyCode) -> bool {
+        if self.on_level_shortcut(key) {
+            return true;
+        }
+        match key {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Tab => self.switch_to_log_mode(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                // ログレベルリストの最下部にいる場合、入力元一覧（なければモジュール選択）の最上部に移動
+                let at_bottom = !self.available_log_levels.is_empty()
+                    && self.selected_log_level_index == self.available_log_levels.len().saturating_sub(1);
+                if at_bottom && self.has_source_panel() {
+                    self.switch_to_source_mode();
+                    self.selected_source_index = 0;
+                } else if at_bottom {
+                    self.switch_to_module_mode();
+                    self.module_list_state.select(Some(0));
+                } else {
+                    self.next_log_level();
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                // ログレベルリストの最上部にいる場合、モジュール選択の最下部に移動
+                if self.selected_log_level_index == 0 {
+                    self.switch_to_module_mode();
+                    if !self.module_items.is_empty() {
+                        self.module_list_state.select(Some(self.module_items.len() - 1));
+                    }
+                } else {
+                    self.previous_log_level();
+                }
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_selected_log_level(),
+            KeyCode::Char('C') => self.clear_all_filters(),
+            KeyCode::Char('b') => self.toggle_filter_panel(),
+            KeyCode::Char(',') => self.decrease_panel_width(),
+            KeyCode::Char('.') => self.increase_panel_width(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn on_source_filter_key(&mut self, key: KeyCode) -> bool {
+        if self.on_level_shortcut(key) {
+            return true;
+        }
+        match key {
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Tab => self.switch_to_log_mode(),
+            KeyCode::Down | KeyCode::Char('j') => {
+                // 入力元一覧の最下部にいる場合、モジュール選択の最上部に移動
+                if self.selected_source_index + 1 >= self.sources.len() {
+                    self.switch_to_module_mode();
+                    self.module_list_state.select(Some(0));
+                } else {
+                    self.next_source();
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                // 入力元一覧の最上部にいる場合、ログレベル選択の最下部に移動
+                if self.selected_source_index == 0 {
+                    self.switch_to_log_level_mode();
+                    self.selected_log_level_index = self.available_log_levels.len().saturating_sub(1);
+                } else {
+                    self.previous_source();
+                }
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => self.toggle_selected_source(),
+            KeyCode::Char('C') => self.clear_all_filters(),
+            KeyCode::Char('b') => self.toggle_filter_panel(),
+            KeyCode::Char(',') => self.decrease_panel_width(),
+            KeyCode::Char('.') => self.increase_panel_width(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_key_binding;
+    use crate::launcher::RecentFiles;
+    use crate::log_parser::{SeverityOrder, SeverityPreset};
+    use crate::palette::Command;
+    use crate::log_parser::LogEntry;
+    use crate::ui::{App, AppMode, InputKind};
+    use crossterm::event::KeyCode;
+
+    fn app_with_modules(targets: &[&str]) -> App {
+        let mut app = App::new();
+        let logs = targets
+            .iter()
+            .map(|target| LogEntry {
+                level: "INFO".to_string(),
+                target: target.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        app.update_logs(logs);
+        app
+    }
+
+    #[test]
+    fn test_panel_navigation_wraps_between_modules_and_levels() {
+        let mut app = app_with_modules(&["a", "b"]);
+        assert_eq!(app.mode, AppMode::ModuleSelection);
+
+        // モジュールリストの先頭から上に移動するとレベル一覧の末尾へ
+        app.on_module_selection_key(KeyCode::Up);
+        assert_eq!(app.mode, AppMode::LogLevelFilter);
+        assert_eq!(app.selected_log_level_index, app.available_log_levels.len() - 1);
+
+        app.on_log_level_filter_key(KeyCode::Down);
+        assert_eq!(app.mode, AppMode::ModuleSelection);
+        assert_eq!(app.module_list_state.selected(), Some(0));
+
+        app.on_module_selection_key(KeyCode::Char('j'));
+        app.on_module_selection_key(KeyCode::Char('j'));
+        assert_eq!(app.mode, AppMode::LogLevelFilter);
+    }
+
+    #[test]
+    fn test_filter_panel_toggle_survives_mode_switches() {
+        let mut app = app_with_modules(&["a", "b"]);
+        app.on_key(KeyCode::Tab);
+        assert!(!app.show_filter_panel);
+
+        // ログ表示のままパネルを表示し、フォーカスを行き来しても表示が続く
+        app.on_key(KeyCode::Char('b'));
+        assert_eq!((&app.mode, app.show_filter_panel), (&AppMode::LogNavigation, true));
+        app.on_key(KeyCode::Tab);
+        app.on_key(KeyCode::Tab);
+        assert_eq!((&app.mode, app.show_filter_panel), (&AppMode::LogNavigation, true));
+
+        // パネルにフォーカスがある状態で隠すとログ表示に戻る
+        app.on_key(KeyCode::Tab);
+        assert_eq!(app.mode, AppMode::ModuleSelection);
+        app.on_key(KeyCode::Char('b'));
+        assert_eq!((&app.mode, app.show_filter_panel), (&AppMode::LogNavigation, false));
+    }
+
+    #[test]
+    fn test_source_panel_sits_between_levels_and_modules() {
+        let mut app = App::new();
+        let entry = |source: &str| LogEntry {
+            level: "INFO".to_string(),
+            target: "app".to_string(),
+            source: source.to_string(),
+            ..Default::default()
+        };
+        app.update_logs(vec![entry("a.log"), entry("b.log"), entry("a.log")]);
+        assert!(app.has_source_panel());
+
+        app.on_key(KeyCode::Up);
+        assert_eq!(app.mode, AppMode::SourceFilter);
+        assert_eq!(app.selected_source_index, 1);
+
+        app.on_key(KeyCode::Char(' '));
+        assert_eq!(app.filtered_logs.len(), 2);
+        assert!(app.active_filter_labels().contains(&"[src:1 hidden]".to_string()));
+
+        app.on_key(KeyCode::Char('k'));
+        app.on_key(KeyCode::Char('k'));
+        assert_eq!(app.mode, AppMode::LogLevelFilter);
+        app.on_key(KeyCode::Char('j'));
+        assert_eq!(app.mode, AppMode::SourceFilter);
+        assert_eq!(app.selected_source_index, 0);
+
+        app.on_key(KeyCode::Char('C'));
+        assert_eq!(app.filtered_logs.len(), 3);
+    }
+
+    #[test]
+    fn test_on_key_routes_prompt_and_popups_first() {
+        let mut app = app_with_modules(&["a", "b"]);
+        app.on_key(KeyCode::Tab);
+        assert_eq!(app.mode, AppMode::LogNavigation);
+
+        // プロンプト表示中は q も入力として扱う
+        app.on_key(KeyCode::Char('/'));
+        for c in "qa".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        assert!(!app.should_quit);
+        app.on_key(KeyCode::Esc);
+        assert!(app.input.is_none());
+
+        app.on_key(KeyCode::Enter);
+        assert!(app.show_details);
+        // 詳細表示中は移動キーを受け付けない
+        let focused = app.current_log_line;
+        app.on_key(KeyCode::Char('k'));
+        assert_eq!(app.current_log_line, focused);
+        app.on_key(KeyCode::Esc);
+        assert!(!app.show_details);
+
+        app.show_error("boom".to_string());
+        app.on_key(KeyCode::Char('x'));
+        assert!(app.error_banner.is_none());
+
+        // フォーマットの警告は q でも閉じるだけ
+        app.show_format_warning(20);
+        app.on_key(KeyCode::Char('q'));
+        assert!(app.format_warning.is_none() && !app.should_quit);
+
+        // 比較ビューは移動キーを受け付けないが、qでは終了する
+        app.on_key(KeyCode::Char('D'));
+        assert!(app.show_compare);
+        app.on_key(KeyCode::Char('k'));
+        assert!(app.show_compare);
+        app.on_key(KeyCode::Char('q'));
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_confirm_quit_needs_double_press() {
+        let mut app = app_with_modules(&["a"]);
+        app.confirm_quit = true;
+
+        app.on_key(KeyCode::Char('q'));
+        assert!(!app.should_quit);
+        app.on_key(KeyCode::Char('j'));
+        app.on_key(KeyCode::Char('q'));
+        assert!(!app.should_quit);
+        app.on_key(KeyCode::Char('q'));
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_pattern_tester_edits_and_applies() {
+        let mut app = app_with_modules(&["a"]);
+        app.on_key(KeyCode::Tab);
+        app.on_key(KeyCode::Char('p'));
+        assert!(app.pattern_tester.is_none());
+
+        app.record_raw_samples(["a b", "", "c d"]);
+        assert_eq!(app.raw_samples, ["a b", "c d"]);
+        app.on_key(KeyCode::Char('p'));
+        app.pattern_tester.as_mut().unwrap().pattern.clear();
+        for c in "(?P<timestamp>)(?P<level>)(?P<target>)(?P<message>.*".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        // 不正なパターンでは確定せず、テスターを開いたままにする
+        app.on_key(KeyCode::Enter);
+        assert!(app.pattern_tester.is_some());
+        assert!(app.pattern_request.is_none());
+
+        app.on_key(KeyCode::Char(')'));
+        assert_eq!(app.pattern_tester.as_ref().unwrap().matched_count(), Some(2));
+        app.on_key(KeyCode::Enter);
+        assert!(app.pattern_tester.is_none());
+        assert_eq!(app.pattern_request.as_deref(), Some(app.parse_pattern.as_str()));
+    }
+
+    #[test]
+    fn test_yank_field_from_details() {
+        let mut app = app_with_modules(&["a"]);
+        app.on_key(KeyCode::Tab);
+        app.on_key(KeyCode::Char('y'));
+        assert!(app.input.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("The focused entry has no fields"));
+
+        app.filtered_logs[0].fields.insert("span_id".to_string(), "7".to_string());
+        app.filtered_logs[0].fields.insert("trace_id".to_string(), "abc123".to_string());
+        app.on_key(KeyCode::Enter);
+        app.on_key(KeyCode::Char('y'));
+        assert_eq!(app.input.as_ref().map(|input| input.kind), Some(InputKind::YankField));
+        app.on_key(KeyCode::Char('t'));
+        app.on_key(KeyCode::Tab);
+        assert_eq!(app.input.as_ref().unwrap().buffer, "trace_id");
+        app.on_key(KeyCode::Enter);
+        assert_eq!(app.clipboard_request.as_deref(), Some("abc123"));
+        assert!(app.show_details);
+
+        app.clipboard_request = None;
+        app.on_key(KeyCode::Char('y'));
+        for c in "user".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        app.on_key(KeyCode::Enter);
+        assert!(app.clipboard_request.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("No field `user` on the focused entry"));
+    }
+
+    #[test]
+    fn test_field_filter_toggle() {
+        let mut app = app_with_modules(&["a", "b", "c"]);
+        app.logs[1].fields.insert("req_id".to_string(), "7".to_string());
+        app.logs[2].fields.insert("span".to_string(), "handler".to_string());
+        app.update_logs(app.logs.clone());
+        app.switch_to_log_mode();
+        app.current_log_line = 1;
+
+        app.on_key(KeyCode::Char('F'));
+        assert_eq!(app.input.as_ref().map(|input| input.kind), Some(InputKind::FieldFilter));
+        app.on_key(KeyCode::Char('r'));
+        app.on_key(KeyCode::Tab);
+        app.on_key(KeyCode::Enter);
+        assert_eq!(app.required_field.as_deref(), Some("req_id"));
+        assert_eq!(app.filtered_logs.len(), 1);
+        assert!(app.active_filter_labels().contains(&"[has:req_id]".to_string()));
+
+        // もう一度Fで解除
+        app.on_key(KeyCode::Char('F'));
+        assert!(app.input.is_none());
+        assert_eq!(app.required_field, None);
+        assert_eq!(app.filtered_logs.len(), 3);
+    }
+
+    #[test]
+    fn test_palette_runs_commands() {
+        let mut app = app_with_modules(&["a", "b"]);
+        app.switch_to_log_mode();
+        app.on_key(KeyCode::Char(':'));
+        assert!(app.palette.is_some());
+        for c in "msgonly".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        // パレット表示中のキーはすべて入力として扱われる
+        assert!(!app.message_only);
+        app.on_key(KeyCode::Enter);
+        assert!(app.palette.is_none());
+        assert!(app.message_only);
+
+        app.on_key(KeyCode::Char(':'));
+        for c in "hide".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        app.on_key(KeyCode::Enter);
+        assert!(app.filtered_logs.is_empty());
+
+        app.on_key(KeyCode::Char(':'));
+        app.on_key(KeyCode::Char('z'));
+        app.on_key(KeyCode::Enter);
+        app.on_key(KeyCode::Char(':'));
+        app.on_key(KeyCode::Esc);
+        assert!(app.palette.is_none());
+        assert!(!app.should_quit);
+
+        // 書き出しはファイルに触れず、メインループへの要求として残す
+        app.run_command(Command::ExportHtml);
+        let export = app.export_request.take().unwrap();
+        assert!(export.path.ends_with(".html") && export.content.contains("<html"), "{}", export.path);
+        app.run_command(Command::ExportText);
+        assert!(app.export_request.as_ref().is_some_and(|export| export.path.ends_with(".txt")));
+    }
+
+    #[test]
+    fn test_level_keys_follow_severity_order() {
+        let mut app = app_with_modules(&["a"]);
+        app.switch_to_log_mode();
+        app.on_key(KeyCode::Char('1'));
+        assert!(!app.log_level_filter.contains("ERROR"));
+        app.on_key(KeyCode::Char('1'));
+
+        app.set_severity_order(SeverityOrder::preset(SeverityPreset::Syslog));
+        app.on_key(KeyCode::Char('1'));
+        assert!(!app.log_level_filter.contains("EMERG") && app.log_level_filter.contains("ERROR"));
+        app.on_key(KeyCode::Char('9'));
+        assert!(!app.log_level_filter.contains("TRACE"));
+        // 段階の数より大きい数字には何も割り当てない
+        app.set_severity_order(SeverityOrder::preset(SeverityPreset::Bunyan));
+        let shown = app.log_level_filter.clone();
+        app.on_key(KeyCode::Char('7'));
+        assert_eq!(app.log_level_filter, shown);
+    }
+
+    #[test]
+    fn test_control_keys_scroll_half_pages() {
+        let targets: Vec<String> = (0..40).map(|index| format!("t{}", index)).collect();
+        let mut app = app_with_modules(&targets.iter().map(String::as_str).collect::<Vec<_>>());
+        app.switch_to_log_mode();
+        app.log_scroll_position = 0;
+        app.last_terminal_size = (80, 23);
+        app.on_control_key(KeyCode::Char('d'));
+        assert_eq!(app.log_scroll_position, 10);
+        app.on_control_key(KeyCode::Char('u'));
+        assert_eq!(app.log_scroll_position, 0);
+
+        // ポップアップの表示中や他のキーは単独で押した場合と同じに扱う
+        app.on_control_key(KeyCode::Char('r'));
+        assert!(app.raw_view);
+        app.toggle_details();
+        app.on_control_key(KeyCode::Char('d'));
+        assert_eq!(app.log_scroll_position, 0);
+    }
+
+    #[test]
+    fn test_configured_keys_run_commands() {
+        assert_eq!(parse_key_binding("PageDown", "goto-bottom"), Ok((KeyCode::PageDown, Command::GotoBottom)));
+        assert!(parse_key_binding("f13", "quit").is_err());
+        assert!(parse_key_binding("x", "no-such-command").is_err());
+
+        let mut app = app_with_modules(&["a"]);
+        app.switch_to_log_mode();
+        app.key_bindings = vec![parse_key_binding("x", "toggle-raw").unwrap(), parse_key_binding("m", "toggle-message-only").unwrap()];
+        app.on_key(KeyCode::Char('x'));
+        assert!(app.raw_view);
+        // 組み込みのm（ミニマップ）より設定ファイルの割り当てが優先される
+        let minimap = app.show_minimap;
+        app.on_key(KeyCode::Char('m'));
+        assert!(app.message_only);
+        assert_eq!(app.show_minimap, minimap);
+    }
+
+    #[test]
+    fn test_log_and_text_selection_transitions() {
+        let mut app = app_with_modules(&["a", "a", "a"]);
+        app.on_module_selection_key(KeyCode::Tab);
+        assert_eq!(app.mode, AppMode::LogNavigation);
+
+        app.on_log_navigation_key(KeyCode::Char('k'));
+        app.on_log_navigation_key(KeyCode::Char('v'));
+        assert_eq!(app.mode, AppMode::TextSelection);
+        app.on_text_selection_key(KeyCode::Char('j'));
+        app.on_text_selection_key(KeyCode::Char('y'));
+        assert_eq!(app.mode, AppMode::LogNavigation);
+        assert_eq!(app.clipboard_request.as_deref().map(|text| text.lines().count()), Some(2));
+
+        // Yはフィールド付きでコピーする
+        for entry in &mut app.filtered_logs {
+            entry.fields.insert("request_id".to_string(), "r-1".to_string());
+        }
+        app.on_log_navigation_key(KeyCode::Char('v'));
+        app.on_text_selection_key(KeyCode::Char('Y'));
+        assert!(app.clipboard_request.as_deref().unwrap().ends_with(" request_id=r-1"));
+
+        assert!(!app.on_text_selection_key(KeyCode::F(1)));
+        app.on_log_navigation_key(KeyCode::Tab);
+        assert_eq!(app.mode, AppMode::ModuleSelection);
+        app.on_module_selection_key(KeyCode::Char('q'));
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn test_recent_files_picker_keys() {
+        let mut app = App::new();
+        app.recent_files = Some(RecentFiles { files: vec!["/var/log/a.log".to_string(), "/var/log/b.log".to_string()], selected: 0 });
+        app.on_key(KeyCode::Char('j'));
+        app.on_key(KeyCode::Enter);
+        assert_eq!(app.open_file_request.as_deref(), Some("/var/log/b.log"));
+        assert!(app.recent_files.is_none());
+
+        // 何も読み込んでいないので、閉じると終了する
+        app.recent_files = Some(RecentFiles::default());
+        app.on_key(KeyCode::Enter);
+        assert!(app.recent_files.is_some());
+        app.on_key(KeyCode::Esc);
+        assert!(app.should_quit);
+    }
+}
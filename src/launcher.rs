@@ -0,0 +1,65 @@
+//! Picker of recently viewed files, shown when the viewer is started bare on an
+//! interactive terminal (no `--input`, no other source, nothing piped in) instead of
+//! waiting on an empty standard input.
+
+/// `InputHistory` key under which opened input files are remembered
+pub const RECENT_FILES_KEY: &str = "recent-files";
+
+/// State of the open picker: the files to choose from, newest first, and the highlighted one
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecentFiles {
+    pub files: Vec<String>,
+    pub selected: usize,
+}
+
+impl RecentFiles {
+    /// Picker over remembered files as stored in the history (oldest first), leaving out
+    /// files that no longer exist
+    pub fn new(history: &[String]) -> Self {
+        let files = history.iter().rev().filter(|path| std::path::Path::new(path).is_file()).cloned().collect();
+        Self { files, selected: 0 }
+    }
+
+    pub fn selected_file(&self) -> Option<&str> {
+        self.files.get(self.selected).map(String::as_str)
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.files.is_empty() {
+            self.selected = (self.selected + 1) % self.files.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.files.is_empty() {
+            self.selected = (self.selected + self.files.len() - 1) % self.files.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files_newest_first_and_existing_only() {
+        let dir = std::env::temp_dir().join(format!("tracing-viewer-recent-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let older = dir.join("older.log");
+        let newer = dir.join("newer.log");
+        std::fs::write(&older, "").unwrap();
+        std::fs::write(&newer, "").unwrap();
+        let history = [older.display().to_string(), dir.join("deleted.log").display().to_string(), newer.display().to_string()];
+
+        let mut picker = RecentFiles::new(&history);
+        assert_eq!(picker.files, [history[2].clone(), history[0].clone()]);
+        assert_eq!(picker.selected_file(), Some(history[2].as_str()));
+        picker.select_previous();
+        assert_eq!(picker.selected_file(), Some(history[0].as_str()));
+        picker.select_next();
+        assert_eq!(picker.selected, 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(RecentFiles::new(&[]).selected_file(), None);
+    }
+}
@@ -0,0 +1,20 @@
+pub mod clipboard;
+pub mod config;
+pub mod export;
+pub mod history;
+pub mod journald;
+pub mod keys;
+pub mod launcher;
+pub mod log_parser;
+pub mod mouse;
+pub mod notes;
+pub mod palette;
+pub mod pattern_tester;
+pub mod state;
+pub mod stats;
+pub mod status;
+pub mod template;
+pub mod theme;
+pub mod timestamp;
+pub mod ui;
+pub mod watch;
@@ -1,15 +1,102 @@
+use indexmap::IndexMap;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LogEntry {
     pub timestamp: String,
     pub level: String,
     pub target: String,
     pub message: String,
-    pub fields: HashMap<String, String>,
+    /// Structured fields in the order the parser discovered them
+    pub fields: IndexMap<String, String>,
+    /// Input the entry was read from (file path or `stdin`)
+    #[serde(default)]
+    pub source: String,
+    /// 1-based line number of the entry's first line within its source
+    #[serde(default)]
+    pub line_number: Option<usize>,
+    /// Byte offset of the entry's first line within its source
+    #[serde(default)]
+    pub byte_offset: Option<u64>,
+    /// Order in which the viewer received the entry; assigned by `App`, 0 until then
+    #[serde(default)]
+    pub id: u64,
+    /// Input lines the entry was parsed from, verbatim apart from line endings; continuation
+    /// lines are joined with `\n`. Left out of serialized entries when empty.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub raw: String,
+}
+
+/// Built-in severity ladder, most severe first
+const LEVELS: [&str; 5] = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+
+/// Built-in severity orderings selectable with `--level-preset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SeverityPreset {
+    /// ERROR, WARN, INFO, DEBUG, TRACE
+    #[default]
+    Tracing,
+    /// Syslog severities: EMERG, ALERT, CRITICAL, ERROR, WARN, NOTICE, INFO, DEBUG, plus TRACE
+    Syslog,
+    /// Bunyan/pino levels: FATAL above the tracing ones
+    Bunyan,
+}
+
+/// Severity ladder used for level thresholds and severity sorting, most severe first.
+/// Levels missing from the ladder rank below every level on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeverityOrder {
+    levels: Vec<String>,
+}
+
+impl Default for SeverityOrder {
+    fn default() -> Self {
+        Self::new(LEVELS)
+    }
+}
+
+impl SeverityOrder {
+    /// Ladder of `levels`, most severe first; names are uppercased like parsed levels
+    /// and later duplicates are ignored
+    pub fn new<S: AsRef<str>>(levels: impl IntoIterator<Item = S>) -> Self {
+        let mut ladder: Vec<String> = Vec::new();
+        for level in levels {
+            let level = level.as_ref().trim().to_ascii_uppercase();
+            if !level.is_empty() && !ladder.contains(&level) {
+                ladder.push(level);
+            }
+        }
+        Self { levels: ladder }
+    }
+
+    pub fn preset(preset: SeverityPreset) -> Self {
+        match preset {
+            SeverityPreset::Tracing => Self::default(),
+            SeverityPreset::Syslog => Self::new(["EMERG", "ALERT", "CRITICAL", "ERROR", "WARN", "NOTICE", "INFO", "DEBUG", "TRACE"]),
+            SeverityPreset::Bunyan => Self::new(["FATAL", "ERROR", "WARN", "INFO", "DEBUG", "TRACE"]),
+        }
+    }
+
+    /// Every level on the ladder, most severe first
+    pub fn levels(&self) -> &[String] {
+        &self.levels
+    }
+
+    /// Position of `level` on the ladder (0 = most severe); unknown levels rank last
+    pub fn rank(&self, level: &str) -> usize {
+        self.levels.iter().position(|known| known == level).unwrap_or(self.levels.len())
+    }
+
+    /// `level` if it is on the ladder, otherwise `fallback`. Numeric level mappings use this
+    /// to produce the finer names (`FATAL`, `NOTICE`, …) only for ladders that have them.
+    pub fn level_or<'a>(&self, level: &'a str, fallback: &'a str) -> &'a str {
+        if self.levels.iter().any(|known| known == level) { level } else { fallback }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -17,12 +104,19 @@ pub struct ModuleTree {
     pub name: String,
     pub children: HashMap<String, ModuleTree>,
     pub is_selected: bool,
+    /// Per-module level threshold overriding the global level filter for this subtree
+    pub min_level: Option<String>,
+    /// Levels kept below the root, set on the root only; deeper path segments stay
+    /// joined in the last level's name
+    pub max_depth: Option<usize>,
 }
 
 impl Hash for ModuleTree {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
         self.is_selected.hash(state);
+        self.min_level.hash(state);
+        self.max_depth.hash(state);
         
         // HashMapの順序は不定なので、キーをソートしてからハッシュ化
         let mut keys: Vec<_> = self.children.keys().collect();
@@ -40,11 +134,28 @@ impl ModuleTree {
             name,
             children: HashMap::new(),
             is_selected: true,
+            min_level: None,
+            max_depth: None,
+        }
+    }
+
+    /// Cap the tree at `max_depth` levels, e.g. with 4 `a::b::c::d::e` becomes
+    /// `a` › `b` › `c` › `d::e`
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Split `module_path` into the names of the nodes along it, honoring `max_depth`
+    fn path_parts<'a>(&self, module_path: &'a str) -> Vec<&'a str> {
+        match self.max_depth {
+            Some(max_depth) => module_path.splitn(max_depth.max(1), "::").collect(),
+            None => module_path.split("::").collect(),
         }
     }
 
     pub fn insert_module(&mut self, module_path: &str) {
-        let parts: Vec<&str> = module_path.split("::").collect();
+        let parts = self.path_parts(module_path);
         let mut current = self;
 
         for part in parts {
@@ -57,8 +168,20 @@ impl ModuleTree {
         }
     }
 
+    /// Whether `module_path` has been inserted
+    pub fn contains(&self, module_path: &str) -> bool {
+        let mut current = self;
+        for part in self.path_parts(module_path) {
+            match current.children.get(part) {
+                Some(child) => current = child,
+                None => return false,
+            }
+        }
+        true
+    }
+
     pub fn is_module_selected(&self, module_path: &str) -> bool {
-        let parts: Vec<&str> = module_path.split("::").collect();
+        let parts = self.path_parts(module_path);
         let mut current = self;
 
         for part in parts {
@@ -71,11 +194,66 @@ impl ModuleTree {
         current.is_selected
     }
 
+    /// The threshold in effect for `module_path`: its own override or the nearest ancestor's
+    pub fn module_min_level(&self, module_path: &str) -> Option<&str> {
+        let mut current = self;
+        let mut min_level = current.min_level.as_deref();
+
+        for part in self.path_parts(module_path) {
+            match current.children.get(part) {
+                Some(child) => current = child,
+                None => break,
+            }
+            if let Some(level) = current.min_level.as_deref() {
+                min_level = Some(level);
+            }
+        }
+        min_level
+    }
+
+    pub fn set_min_level(&mut self, module_path: &str, min_level: Option<String>) {
+        let parts = self.path_parts(module_path);
+        let mut current = self;
+        for part in parts {
+            match current.children.get_mut(part) {
+                Some(child) => current = child,
+                None => return,
+            }
+        }
+        current.min_level = min_level;
+    }
+
+    /// Whether any module in this subtree has a level threshold
+    pub fn has_min_levels(&self) -> bool {
+        self.min_level.is_some() || self.children.values().any(ModuleTree::has_min_levels)
+    }
+
+    /// Remove every per-module level threshold in this subtree
+    pub fn clear_min_levels(&mut self) {
+        self.min_level = None;
+        for child in self.children.values_mut() {
+            child.clear_min_levels();
+        }
+    }
+
     pub fn toggle_selection(&mut self, module_path: &str) {
-        let parts: Vec<&str> = module_path.split("::").collect();
+        let parts = self.path_parts(module_path);
         self.toggle_selection_recursive(&parts, 0);
     }
 
+    /// Set `module_path` and all of its submodules to `selected`
+    pub fn set_selection(&mut self, module_path: &str, selected: bool) {
+        let parts = self.path_parts(module_path);
+        let mut current = self;
+        for part in parts {
+            match current.children.get_mut(part) {
+                Some(child) => current = child,
+                None => return,
+            }
+        }
+        current.propagate_selection_to_children(selected);
+    }
+
     fn toggle_selection_recursive(&mut self, parts: &[&str], index: usize) {
         if index >= parts.len() {
             self.is_selected = !self.is_selected;
@@ -104,68 +282,645 @@ impl ModuleTree {
     }
 }
 
+/// Input format selected with `--format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// JSON objects when a line looks like one, `tracing` fmt lines otherwise
+    #[default]
+    Auto,
+    /// `tracing_subscriber::fmt` text lines only
+    Tracing,
+    /// JSON objects only (tracing json, OpenTelemetry-style field names, numeric levels)
+    Json,
+    /// Bunyan/pino JSON: `time`, numeric `level`, `name`, `msg`
+    Bunyan,
+    /// Serialized `LogEntry` records, one per line or (in files) as a JSON array
+    EntriesJson,
+}
+
+/// Map a Bunyan/pino numeric level (10 = trace … 60 = fatal) onto `LEVELS`, or onto
+/// `FATAL` when `severity` has it
+pub fn bunyan_level(level: u64, severity: &SeverityOrder) -> &'static str {
+    match level {
+        0..=10 => "TRACE",
+        11..=20 => "DEBUG",
+        21..=30 => "INFO",
+        31..=40 => "WARN",
+        41..=50 => "ERROR",
+        _ => severity.level_or("FATAL", "ERROR"),
+    }
+}
+
+/// Map an OpenTelemetry `SeverityNumber` (1..=24) onto `LEVELS`, or onto `FATAL` when
+/// `severity` has it
+pub fn otel_severity_level(severity_number: u64, severity: &SeverityOrder) -> &'static str {
+    match severity_number {
+        0..=4 => "TRACE",
+        5..=8 => "DEBUG",
+        9..=12 => "INFO",
+        13..=16 => "WARN",
+        17..=20 => "ERROR",
+        _ => severity.level_or("FATAL", "ERROR"),
+    }
+}
+
+/// Characters allowed in a target by the default pattern: module paths plus hyphenated
+/// crate names, dotted names and bracketed decorations such as `worker[3]`
+pub const DEFAULT_TARGET_CHARS: &str = r"\w:.\-\[\]";
+
+/// Line pattern for `tracing_subscriber::fmt` output, i.e. `tracing_pattern(DEFAULT_TARGET_CHARS, false)`
+pub static DEFAULT_PATTERN: LazyLock<String> = LazyLock::new(|| tracing_pattern(DEFAULT_TARGET_CHARS, false));
+
+/// The line pattern for `tracing_subscriber::fmt` output with `target_chars` (the inside of
+/// a regex character class) as the characters allowed in a target. Fractional seconds and
+/// the offset (`Z`, `+09:00` or `+0900`) are both optional; so is the level when
+/// `optional_level` is set, for use with `LogParser::with_optional_level`.
+pub fn tracing_pattern(target_chars: &str, optional_level: bool) -> String {
+    let level = if optional_level { r"(?:\s+(?P<level>\w+))?" } else { r"\s+(?P<level>\w+)" };
+    format!(
+        r"(?P<timestamp>\d{{4}}-\d{{2}}-\d{{2}}T\d{{2}}:\d{{2}}:\d{{2}}(?:\.\d+)?(?:Z|[+-]\d{{2}}:?\d{{2}})?){}\s+(?P<target>[{}]+):\s*(?P<message>.*)",
+        level, target_chars
+    )
+}
+
+/// Level given to text lines whose pattern matched without a level in optional-level mode
+pub const DEFAULT_LEVEL: &str = "INFO";
+
+/// Level of entries made from lines that don't parse when multi-line merging is off
+pub const RAW_LEVEL: &str = "RAW";
+
+/// Named groups a line pattern has to capture
+pub const PATTERN_GROUPS: [&str; 4] = ["timestamp", "level", "target", "message"];
+
+/// Compile a line pattern, rejecting it unless it has every group in `PATTERN_GROUPS`
+pub fn compile_pattern(pattern: &str) -> anyhow::Result<Regex> {
+    let regex = Regex::new(pattern)?;
+    let missing: Vec<&str> = PATTERN_GROUPS
+        .iter()
+        .copied()
+        .filter(|group| !regex.capture_names().flatten().any(|name| name == *group))
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!("pattern is missing named groups: {}", missing.join(", "));
+    }
+    Ok(regex)
+}
+
+/// Non-blank input lines sampled to judge whether the selected format fits the input
+pub const FORMAT_CHECK_LINES: usize = 20;
+
+/// Keys looked up, in order, for the human-readable message of structured entries
+pub const DEFAULT_MESSAGE_FIELDS: [&str; 4] = ["message", "msg", "body", "event"];
+
 pub struct LogParser {
-    tracing_regex: Regex,
+    /// Line patterns tried in order; the first one that matches wins
+    patterns: Vec<Regex>,
+    message_fields: Vec<String>,
+    format: LogFormat,
+    keep_blank_lines: bool,
+    optional_level: bool,
+    multiline: bool,
+    split_fields: bool,
+    /// Non-blank lines `parse_multiline_logs` couldn't parse or attach to an entry
+    dropped_lines: AtomicUsize,
+    /// The first `FORMAT_CHECK_LINES` non-blank lines seen, and how many of them parsed
+    sampled_lines: AtomicUsize,
+    matched_sample_lines: AtomicUsize,
+    /// Records `--format entries-json` skipped, and why the first one was skipped
+    malformed_records: AtomicUsize,
+    first_malformed: Mutex<Option<String>>,
+    /// Ladder numeric JSON levels are named for
+    severity: SeverityOrder,
 }
 
 impl LogParser {
     pub fn new() -> anyhow::Result<Self> {
-        let tracing_regex = Regex::new(
-            r"(?P<timestamp>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\.\d+Z?)\s+(?P<level>\w+)\s+(?P<target>[\w:]+):\s*(?P<message>.*)"
-        )?;
+        Ok(Self {
+            patterns: vec![compile_pattern(&DEFAULT_PATTERN)?],
+            message_fields: DEFAULT_MESSAGE_FIELDS.iter().map(|field| field.to_string()).collect(),
+            format: LogFormat::Auto,
+            keep_blank_lines: false,
+            optional_level: false,
+            multiline: true,
+            split_fields: false,
+            dropped_lines: AtomicUsize::new(0),
+            sampled_lines: AtomicUsize::new(0),
+            matched_sample_lines: AtomicUsize::new(0),
+            malformed_records: AtomicUsize::new(0),
+            first_malformed: Mutex::new(None),
+            severity: SeverityOrder::default(),
+        })
+    }
 
-        Ok(Self { tracing_regex })
+    /// Name numeric JSON levels (Bunyan, OpenTelemetry) with the levels of `severity`, so
+    /// e.g. Bunyan's 60 becomes `FATAL` under the Bunyan preset instead of `ERROR`
+    pub fn with_severity_order(mut self, severity: SeverityOrder) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Accept text lines whose pattern matched without capturing a level, giving them
+    /// `DEFAULT_LEVEL`. The level group must be optional in the pattern for this to apply
+    /// (see `tracing_pattern`); off by default so malformed lines aren't masked.
+    pub fn with_optional_level(mut self, optional_level: bool) -> Self {
+        self.optional_level = optional_level;
+        self
+    }
+
+    /// Keep blank lines inside a multi-line message (e.g. a pasted Python traceback)
+    /// instead of dropping them. Blank lines after an entry's last text are still dropped.
+    pub fn with_keep_blank_lines(mut self, keep_blank_lines: bool) -> Self {
+        self.keep_blank_lines = keep_blank_lines;
+        self
+    }
+
+    /// Merge lines that don't parse into the preceding entry (the default). When off, each
+    /// such line becomes its own `RAW_LEVEL` entry with the line as its message.
+    pub fn with_multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    /// Move `key=value` pairs trailing the message of text lines into `LogEntry::fields`
+    /// (see `split_trailing_fields`); off by default so messages are shown as written
+    pub fn with_split_fields(mut self, split_fields: bool) -> Self {
+        self.split_fields = split_fields;
+        self
+    }
+
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Replace the line patterns, tried in order for every line; an empty list keeps the
+    /// default. See `compile_pattern` for what each pattern must capture.
+    pub fn with_patterns(mut self, patterns: &[String]) -> anyhow::Result<Self> {
+        if !patterns.is_empty() {
+            self.patterns = patterns.iter().map(|pattern| compile_pattern(pattern)).collect::<anyhow::Result<_>>()?;
+        }
+        Ok(self)
+    }
+
+    /// Replace the first (highest priority) line pattern, keeping any others
+    pub fn set_pattern(&mut self, pattern: &str) -> anyhow::Result<()> {
+        self.patterns[0] = compile_pattern(pattern)?;
+        Ok(())
+    }
+
+    /// The first (highest priority) line pattern
+    pub fn pattern(&self) -> &str {
+        self.patterns[0].as_str()
+    }
+
+    /// Lines discarded so far by `parse_multiline_logs`: non-blank lines that didn't parse
+    /// and had no preceding entry to continue
+    pub fn dropped_lines(&self) -> usize {
+        self.dropped_lines.load(Ordering::Relaxed)
+    }
+
+    /// Records skipped so far by `--format entries-json` (also counted as dropped lines),
+    /// with the location and reason of the first one
+    pub fn malformed_records(&self) -> (usize, Option<String>) {
+        let first = self.first_malformed.lock().map(|first| first.clone()).unwrap_or_default();
+        (self.malformed_records.load(Ordering::Relaxed), first)
+    }
+
+    fn record_malformed(&self, reason: String) {
+        self.malformed_records.fetch_add(1, Ordering::Relaxed);
+        self.dropped_lines.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut first) = self.first_malformed.lock() {
+            first.get_or_insert(reason);
+        }
+    }
+
+    /// Whether `content` is a JSON array of entries for `--format entries-json`, which has
+    /// to be parsed in one piece
+    pub fn is_entry_array(&self, content: &str) -> bool {
+        self.format == LogFormat::EntriesJson && content.trim_start().starts_with('[')
+    }
+
+    /// Number of sampled lines when none of them parsed, suggesting the wrong format or
+    /// pattern: once `FORMAT_CHECK_LINES` lines were sampled, or earlier when `complete`
+    /// says the input has been read in full. Never reported without multi-line merging,
+    /// where unparsed lines still show up as `RAW_LEVEL` entries.
+    pub fn format_mismatch(&self, complete: bool) -> Option<usize> {
+        let sampled = self.sampled_lines.load(Ordering::Relaxed);
+        let enough = sampled >= FORMAT_CHECK_LINES || (complete && sampled > 0);
+        (self.multiline && enough && self.matched_sample_lines.load(Ordering::Relaxed) == 0).then_some(sampled)
+    }
+
+    /// Count a non-blank line towards the format check
+    fn sample_line(&self, parsed: bool) {
+        if self.sampled_lines.load(Ordering::Relaxed) < FORMAT_CHECK_LINES {
+            self.sampled_lines.fetch_add(1, Ordering::Relaxed);
+            if parsed {
+                self.matched_sample_lines.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Override the prioritized list of message keys; an empty list keeps the defaults
+    pub fn with_message_fields(mut self, message_fields: Vec<String>) -> Self {
+        if !message_fields.is_empty() {
+            self.message_fields = message_fields;
+        }
+        self
     }
 
     pub fn parse_line(&self, line: &str) -> Option<LogEntry> {
-        if let Some(captures) = self.tracing_regex.captures(line) {
+        // Windows形式の改行（CRLF）の末尾のCRを除去
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        match self.format {
+            LogFormat::Json | LogFormat::Bunyan => return self.parse_json_line(line),
+            LogFormat::EntriesJson => return serde_json::from_str(line.trim()).map_err(|e| e.to_string()).and_then(|entry| validate_entry(entry, line.trim())).ok(),
+            LogFormat::Tracing => {}
+            // NDJSON（tracing-subscriberのjson形式など）の行
+            LogFormat::Auto => {
+                if line.trim_start().starts_with('{')
+                    && let Some(entry) = self.parse_json_line(line)
+                {
+                    return Some(entry);
+                }
+            }
+        }
+
+        if let Some(captures) = self.patterns.iter().find_map(|pattern| pattern.captures(line)) {
             let timestamp = captures.name("timestamp")?.as_str().to_string();
-            let level = captures.name("level")?.as_str().to_string();
+            // フィルタや色分けは大文字のレベル名で比較するのでそろえておく
+            let level = match captures.name("level") {
+                Some(level) => level.as_str().to_ascii_uppercase(),
+                None if self.optional_level => DEFAULT_LEVEL.to_string(),
+                None => return None,
+            };
             let target = captures.name("target")?.as_str().to_string();
-            let message = captures.name("message")?.as_str().to_string();
+            let message = captures.name("message")?.as_str();
+            let (message, fields) = if self.split_fields { split_trailing_fields(message) } else { (message, IndexMap::new()) };
 
             Some(LogEntry {
                 timestamp,
                 level,
                 target,
-                message,
-                fields: HashMap::new(),
+                message: message.to_string(),
+                fields,
+                source: String::new(),
+                line_number: None,
+                byte_offset: None,
+                id: 0,
+                raw: line.to_string(),
             })
         } else {
             None
         }
     }
 
+    /// Parse a JSON object line such as `tracing_subscriber::fmt().json()`, Bunyan or
+    /// OpenTelemetry-style output. Numeric levels and epoch timestamps are normalized.
+    /// Nested `fields` and any other top-level scalars end up in `LogEntry::fields`.
+    /// When none of the message keys is present, the message is synthesized from those fields.
+    pub fn parse_json_line(&self, line: &str) -> Option<LogEntry> {
+        let serde_json::Value::Object(mut object) = serde_json::from_str(line.trim()).ok()? else {
+            return None;
+        };
+
+        // shift_removeで残りのキーの出現順を保つ
+        let mut nested_fields = match object.shift_remove("fields") {
+            Some(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+
+        let timestamp = take_json_timestamp(&mut object)?;
+        let level = take_json_level(&mut object, &self.severity).unwrap_or_else(|| "INFO".to_string());
+        let target = take_json_string(&mut object, &["target", "logger", "name"]).unwrap_or_default();
+        if self.format == LogFormat::Bunyan {
+            // Bunyanのフォーマットバージョンは表示に不要
+            object.shift_remove("v");
+        }
+        let message_fields: Vec<&str> = self.message_fields.iter().map(String::as_str).collect();
+        let message = take_json_string(&mut nested_fields, &message_fields)
+            .or_else(|| take_json_string(&mut object, &message_fields));
+
+        let mut fields = IndexMap::new();
+        let mut synthesized = Vec::new();
+        for (key, value) in nested_fields.into_iter().chain(object) {
+            if let Some(value) = json_scalar_to_string(&value) {
+                if message.is_none() {
+                    synthesized.push(format!("{}={}", key, value));
+                }
+                fields.insert(key, value);
+            }
+        }
+        let message = message.unwrap_or_else(|| synthesized.join(" "));
+
+        Some(LogEntry {
+            timestamp,
+            level,
+            target,
+            message,
+            fields,
+            raw: line.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Parse `content` into entries, merging continuation lines into the preceding entry.
+    /// `line_number` and `byte_offset` are filled relative to the start of `content`.
     pub fn parse_multiline_logs(&self, content: &str) -> Vec<LogEntry> {
+        let (mut entries, last) = self.continue_multiline_logs(None, content);
+        entries.extend(last);
+        entries
+    }
+
+    /// Like `parse_multiline_logs`, but lines before the first new entry continue `pending`
+    /// (an entry from earlier input of the same source), and the last entry is returned
+    /// separately since later input may still continue it
+    pub fn continue_multiline_logs(&self, pending: Option<LogEntry>, content: &str) -> (Vec<LogEntry>, Option<LogEntry>) {
+        if self.format == LogFormat::EntriesJson {
+            // 直列化されたエントリは1件ずつ完結しているので、続きの行を待つ必要がない
+            return (pending.into_iter().chain(self.parse_entry_records(content)).collect(), None);
+        }
         let mut entries = Vec::new();
-        let lines: Vec<&str> = content.lines().collect();
-        let mut current_entry: Option<LogEntry> = None;
+        let mut current_entry = pending;
+        let mut byte_offset = 0u64;
+        // 続きの行が来るまで保留している空行の数
+        let mut pending_blank_lines = 0;
         
-        for line in lines {
-            if let Some(new_entry) = self.parse_line(line) {
+        for (line_index, raw_line) in content.split_inclusive('\n').enumerate() {
+            let line_start = byte_offset;
+            byte_offset += raw_line.len() as u64;
+            let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+
+            let parsed = self.parse_line(line);
+            if !line.trim().is_empty() {
+                self.sample_line(parsed.is_some());
+            }
+            if let Some(mut new_entry) = parsed {
+                new_entry.line_number = Some(line_index + 1);
+                new_entry.byte_offset = Some(line_start);
                 // 新しいエントリが見つかった場合、前のエントリを保存
                 if let Some(entry) = current_entry.take() {
                     entries.push(entry);
                 }
                 current_entry = Some(new_entry);
+                pending_blank_lines = 0;
+            } else if !self.multiline {
+                // 結合しない場合は解析できない行もそれぞれ1つのエントリにする
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Some(entry) = current_entry.replace(LogEntry {
+                    level: RAW_LEVEL.to_string(),
+                    message: line.to_string(),
+                    raw: line.to_string(),
+                    line_number: Some(line_index + 1),
+                    byte_offset: Some(line_start),
+                    ..Default::default()
+                }) {
+                    entries.push(entry);
+                }
             } else if let Some(ref mut entry) = current_entry {
                 // 既存のエントリの続きの行として追加
-                if !line.trim().is_empty() {
+                if line.trim().is_empty() {
+                    pending_blank_lines += 1;
+                } else {
+                    if self.keep_blank_lines {
+                        entry.message.push_str(&"\n".repeat(pending_blank_lines));
+                    }
+                    // 元の行は空行も含めてそのまま残す
+                    entry.raw.push_str(&"\n".repeat(pending_blank_lines));
+                    pending_blank_lines = 0;
                     entry.message.push('\n');
                     entry.message.push_str(line);
+                    entry.raw.push('\n');
+                    entry.raw.push_str(line);
                 }
+            } else if !line.trim().is_empty() {
+                self.dropped_lines.fetch_add(1, Ordering::Relaxed);
             }
         }
         
-        // 最後のエントリを保存
-        if let Some(entry) = current_entry {
-            entries.push(entry);
+        (entries, current_entry)
+    }
+
+    /// `--format entries-json` input: a JSON array of serialized entries, or one per line.
+    /// Malformed records are skipped and counted rather than failing the whole input.
+    fn parse_entry_records(&self, content: &str) -> Vec<LogEntry> {
+        if self.is_entry_array(content) {
+            return match serde_json::from_str::<Vec<serde_json::Value>>(content) {
+                Ok(values) => values
+                    .into_iter()
+                    .map(|value| {
+                        let record = value.to_string();
+                        serde_json::from_value(value).map_err(|e| e.to_string()).and_then(|entry| validate_entry(entry, &record))
+                    })
+                    .enumerate()
+                    .filter_map(|(index, result)| match result {
+                        Ok(entry) => Some(entry),
+                        Err(e) => {
+                            self.record_malformed(format!("record {}: {}", index + 1, e));
+                            None
+                        }
+                    })
+                    .collect(),
+                Err(e) => {
+                    self.record_malformed(format!("not a JSON array of entries: {}", e));
+                    Vec::new()
+                }
+            };
+        }
+        let mut entries = Vec::new();
+        let mut byte_offset = 0u64;
+        for (line_index, raw_line) in content.split_inclusive('\n').enumerate() {
+            let line_start = byte_offset;
+            byte_offset += raw_line.len() as u64;
+            if raw_line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(raw_line.trim()).map_err(|e| e.to_string()).and_then(|entry| validate_entry(entry, raw_line.trim())) {
+                Ok(mut entry) => {
+                    entry.line_number = Some(line_index + 1);
+                    entry.byte_offset = Some(line_start);
+                    entries.push(entry);
+                }
+                Err(e) => self.record_malformed(format!("line {}: {}", line_index + 1, e)),
+            }
         }
-        
         entries
     }
 }
 
+/// Check a deserialized `--format entries-json` record and normalize it like parsed entries.
+/// A record without raw lines of its own gets `record`, the JSON it was read from.
+fn validate_entry(mut entry: LogEntry, record: &str) -> Result<LogEntry, String> {
+    if entry.level.trim().is_empty() {
+        return Err("`level` is empty".to_string());
+    }
+    entry.level = entry.level.to_ascii_uppercase();
+    // IDは受け取った側で振り直す
+    entry.id = 0;
+    if entry.raw.is_empty() {
+        entry.raw = record.to_string();
+    }
+    Ok(entry)
+}
+
+/// Number of sample lines one candidate parser recognized, as reported by `detect_formats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatMatch {
+    /// Command-line arguments selecting the candidate, e.g. `--format json`
+    pub args: String,
+    pub matched: usize,
+}
+
+/// Count how many of `lines` each known format recognizes, followed by one candidate per
+/// entry of `patterns` (custom `--log-regex` values). Candidates are ordered from general to
+/// specific, so on a tie the later one is the better pick.
+pub fn detect_formats(lines: &[&str], patterns: &[String]) -> anyhow::Result<Vec<FormatMatch>> {
+    let count = |parser: &LogParser| lines.iter().filter(|line| parser.parse_line(line).is_some()).count();
+    let mut matches = Vec::new();
+    for (name, format) in [("tracing", LogFormat::Tracing), ("json", LogFormat::Json)] {
+        let parser = LogParser::new()?.with_format(format);
+        matches.push(FormatMatch { args: format!("--format {}", name), matched: count(&parser) });
+    }
+    // Bunyanの行はJSONとしても読めるので、フォーマットバージョンの"v"があるものだけ数える
+    let json = LogParser::new()?.with_format(LogFormat::Bunyan);
+    let bunyan = lines
+        .iter()
+        .filter(|line| json.parse_line(line).is_some())
+        .filter(|line| {
+            serde_json::from_str::<serde_json::Value>(line.trim()).is_ok_and(|value| value.get("v").is_some_and(|v| v.is_u64()))
+        })
+        .count();
+    matches.push(FormatMatch { args: "--format bunyan".to_string(), matched: bunyan });
+    for pattern in patterns {
+        let parser = LogParser::new()?.with_format(LogFormat::Tracing).with_patterns(std::slice::from_ref(pattern))?;
+        matches.push(FormatMatch { args: format!("--log-regex '{}'", pattern), matched: count(&parser) });
+    }
+    Ok(matches)
+}
+
+/// Remove and return the first of `keys` in `object` holding a scalar, as a string
+fn take_json_string(object: &mut serde_json::Map<String, serde_json::Value>, keys: &[&str]) -> Option<String> {
+    // オブジェクトや配列の値は飛ばし、次のキーを探す
+    let key = keys.iter().find(|key| object.get(**key).and_then(json_scalar_to_string).is_some())?;
+    object.shift_remove(*key).as_ref().and_then(json_scalar_to_string)
+}
+
+/// Remove and return the entry timestamp. Numeric `time`/`ts` values are taken as epoch
+/// milliseconds and OpenTelemetry `timeUnixNano` as epoch nanoseconds.
+fn take_json_timestamp(object: &mut serde_json::Map<String, serde_json::Value>) -> Option<String> {
+    let format = |time: chrono::DateTime<chrono::Utc>| time.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+    if let Some(nanos) = ["timeUnixNano", "time_unix_nano", "observedTimeUnixNano"]
+        .iter()
+        .find_map(|key| object.shift_remove(*key))
+    {
+        let nanos = json_scalar_to_string(&nanos)?.parse::<i64>().ok()?;
+        return Some(format(chrono::DateTime::from_timestamp_nanos(nanos)));
+    }
+    match ["timestamp", "time", "ts", "Timestamp"].iter().find_map(|key| object.shift_remove(*key))? {
+        serde_json::Value::Number(millis) => millis
+            .as_i64()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(format),
+        value => json_scalar_to_string(&value),
+    }
+}
+
+/// Remove and return the entry level, mapping numeric Bunyan levels and OpenTelemetry
+/// severity numbers onto the levels of `severity`
+fn take_json_level(object: &mut serde_json::Map<String, serde_json::Value>, severity: &SeverityOrder) -> Option<String> {
+    let severity_number = ["severityNumber", "SeverityNumber", "severity_number"]
+        .iter()
+        .find_map(|key| object.shift_remove(*key))
+        .and_then(|value| value.as_u64());
+    let level = match ["level", "severity", "severityText", "SeverityText", "severity_text"]
+        .iter()
+        .find_map(|key| object.shift_remove(*key))
+    {
+        Some(serde_json::Value::Number(level)) => level.as_u64().map(|level| bunyan_level(level, severity).to_string()),
+        Some(value) => json_scalar_to_string(&value).map(|level| level.to_uppercase()),
+        None => None,
+    };
+    level.or_else(|| severity_number.map(|number| otel_severity_level(number, severity).to_string()))
+}
+
+/// Split the `key=value` pairs tracing's fmt output appends to a message off the message,
+/// e.g. `request done status=200 path="/a b"`. Values may be double-quoted with `\"` and
+/// `\\` escapes. Only an unbroken run of pairs at the end is taken, so a message with `=`
+/// elsewhere keeps it, and a message made up of nothing but pairs is left whole.
+pub fn split_trailing_fields(message: &str) -> (&str, IndexMap<String, String>) {
+    // 先頭から空白区切りの語に分ける。key=valueとして読めた語は値も取っておく
+    let mut tokens: Vec<(usize, Option<(String, String)>)> = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = message[position..].find(|c: char| !c.is_whitespace()) {
+        let start = position + offset;
+        match parse_field_pair(&message[start..]) {
+            Some((key, value, length)) => {
+                tokens.push((start, Some((key, value))));
+                position = start + length;
+            }
+            None => {
+                tokens.push((start, None));
+                position = message[start..].find(char::is_whitespace).map_or(message.len(), |end| start + end);
+            }
+        }
+    }
+    let first_pair = tokens.iter().rposition(|(_, pair)| pair.is_none()).map_or(0, |index| index + 1);
+    if first_pair == 0 || first_pair == tokens.len() {
+        return (message, IndexMap::new());
+    }
+    let message_end = tokens[first_pair].0;
+    let fields = tokens.drain(first_pair..).filter_map(|(_, pair)| pair).collect();
+    (message[..message_end].trim_end(), fields)
+}
+
+/// Read one `key=value` or `key="quoted value"` pair from the start of `text`, returning the
+/// key, the unescaped value and the length it took. It must end at whitespace or the end.
+fn parse_field_pair(text: &str) -> Option<(String, String, usize)> {
+    let key_end = text.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')))?;
+    let key = &text[..key_end];
+    if !key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') || !text[key_end..].starts_with('=') {
+        return None;
+    }
+    let rest = &text[key_end + 1..];
+    let (value, length) = if let Some(quoted) = rest.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        let closing = loop {
+            match chars.next()? {
+                (_, '\\') => value.push(chars.next()?.1),
+                (index, '"') => break index,
+                (_, c) => value.push(c),
+            }
+        };
+        (value, key_end + 1 + 1 + closing + 1)
+    } else {
+        // 引用符を含む語は引用の途中の可能性があるので値とみなさない
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let value = &rest[..end];
+        if value.is_empty() || value.contains('"') {
+            return None;
+        }
+        (value.to_string(), key_end + 1 + end)
+    };
+    if !text[length..].is_empty() && !text[length..].starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some((key.to_string(), value, length))
+}
+
+/// Render scalar JSON values as plain text; objects and arrays are skipped
+fn json_scalar_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(flag) => Some(flag.to_string()),
+        serde_json::Value::Null | serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,6 +936,225 @@ mod tests {
         assert_eq!(entry.message, "This is a test message");
     }
 
+    #[test]
+    fn test_timestamp_variants() {
+        let parser = LogParser::new().unwrap();
+        for timestamp in [
+            "2024-01-01T12:00:00.123Z",
+            "2024-01-01T12:00:00.123456789",
+            "2024-01-01T12:00:00Z",
+            "2024-01-01T12:00:00",
+            "2024-01-01T12:00:00+09:00",
+            "2024-01-01T12:00:00.5-0530",
+        ] {
+            let entry = parser.parse_line(&format!("{} INFO myapp::db: connected", timestamp)).unwrap();
+            assert_eq!(entry.timestamp, timestamp);
+            assert_eq!(entry.target, "myapp::db");
+            assert_eq!(entry.message, "connected");
+        }
+    }
+
+    #[test]
+    fn test_parse_json_log() {
+        let parser = LogParser::new().unwrap();
+        let line = r#"{"timestamp":"2024-01-01T12:00:00.123Z","level":"WARN","fields":{"message":"slow query","elapsed_ms":250},"target":"myapp::db","span":{"name":"request"}}"#;
+
+        let entry = parser.parse_line(line).unwrap();
+        assert_eq!(entry.timestamp, "2024-01-01T12:00:00.123Z");
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.target, "myapp::db");
+        assert_eq!(entry.message, "slow query");
+        assert_eq!(entry.fields.get("elapsed_ms").map(String::as_str), Some("250"));
+        assert!(!entry.fields.contains_key("span"));
+        assert_eq!(entry.raw, line);
+
+        assert!(parser.parse_line(r#"{"no":"timestamp"}"#).is_none());
+    }
+
+    #[test]
+    fn test_json_message_fields() {
+        let parser = LogParser::new().unwrap();
+        let line = r#"{"time":"2024-01-01T12:00:00Z","level":"INFO","body":"from body","event":"ignored"}"#;
+        assert_eq!(parser.parse_line(line).unwrap().message, "from body");
+
+        let parser = parser.with_message_fields(vec!["event".to_string()]);
+        assert_eq!(parser.parse_line(line).unwrap().message, "ignored");
+
+        let line = r#"{"time":"2024-01-01T12:00:00Z","level":"INFO","user":"alice","status":200}"#;
+        let entry = parser.parse_line(line).unwrap();
+        assert_eq!(entry.message, "user=alice status=200");
+
+        // 構造化された値のキーは飛ばして次の候補を使う
+        let parser = parser.with_message_fields(vec!["message".to_string(), "msg".to_string()]);
+        let line = r#"{"time":"2024-01-01T12:00:00Z","level":"INFO","message":{"text":"nested"},"msg":"plain"}"#;
+        assert_eq!(parser.parse_line(line).unwrap().message, "plain");
+        assert_eq!(entry.fields["user"], "alice");
+    }
+
+    #[test]
+    fn test_json_fields_keep_source_order() {
+        let parser = LogParser::new().unwrap();
+        let line = r#"{"timestamp":"2024-01-01T12:00:00Z","zeta":1,"fields":{"message":"m","request_id":"r1","attempt":2},"alpha":true}"#;
+        let entry = parser.parse_line(line).unwrap();
+        let keys: Vec<&str> = entry.fields.keys().map(String::as_str).collect();
+        assert_eq!(keys, ["request_id", "attempt", "zeta", "alpha"]);
+
+        let roundtrip: LogEntry = serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+        assert!(roundtrip.fields.keys().eq(entry.fields.keys()));
+    }
+
+    #[test]
+    fn test_parse_bunyan_and_otel_json() {
+        let parser = LogParser::new().unwrap().with_format(LogFormat::Bunyan);
+        let line = r#"{"name":"api","hostname":"web-1","pid":42,"level":50,"msg":"request failed","time":"2024-01-01T12:00:00.123Z","v":0}"#;
+        let entry = parser.parse_line(line).unwrap();
+        assert_eq!(entry.level, "ERROR");
+        assert_eq!(entry.target, "api");
+        assert_eq!(entry.message, "request failed");
+        assert_eq!(entry.timestamp, "2024-01-01T12:00:00.123Z");
+        assert!(!entry.fields.contains_key("v"));
+        assert!(parser.parse_line("2024-01-01T12:00:00.123Z INFO app: plain").is_none());
+
+        let parser = LogParser::new().unwrap();
+        let line = r#"{"timeUnixNano":"1704110400000000000","severityNumber":13,"body":"disk almost full"}"#;
+        let entry = parser.parse_line(line).unwrap();
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.timestamp, "2024-01-01T12:00:00Z");
+        assert_eq!(entry.message, "disk almost full");
+
+        let pino = r#"{"level":30,"time":1704110400500,"msg":"listening"}"#;
+        let entry = parser.parse_line(pino).unwrap();
+        assert_eq!((entry.level.as_str(), entry.timestamp.as_str()), ("INFO", "2024-01-01T12:00:00.500Z"));
+
+        // FATALのある段階では、60やOpenTelemetryの21以上をFATALにする
+        let fatal = r#"{"name":"api","level":60,"msg":"out of memory","time":"2024-01-01T12:00:00Z","v":0}"#;
+        assert_eq!(parser.parse_line(fatal).unwrap().level, "ERROR");
+        let bunyan = SeverityOrder::preset(SeverityPreset::Bunyan);
+        let parser = LogParser::new().unwrap().with_format(LogFormat::Bunyan).with_severity_order(bunyan.clone());
+        let entry = parser.parse_line(fatal).unwrap();
+        assert_eq!((entry.level.as_str(), bunyan.rank(&entry.level)), ("FATAL", 0));
+        assert_eq!(otel_severity_level(21, &bunyan), "FATAL");
+        assert_eq!(otel_severity_level(17, &bunyan), "ERROR");
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let parser = LogParser::new().unwrap();
+        let content = "2024-01-01T12:00:00.123Z INFO myapp::module: First message\r\n\
+                       continuation line\r\n\
+                       2024-01-01T12:00:01.456Z WARN myapp::other: Second message\r";
+
+        let entries = parser.parse_multiline_logs(content);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "First message\ncontinuation line");
+        assert_eq!(entries[1].message, "Second message");
+
+        let entry = parser.parse_line("2024-01-01T12:00:00.123Z ERROR myapp: boom\r").unwrap();
+        assert_eq!(entry.message, "boom");
+    }
+
+    #[test]
+    fn test_keep_blank_lines_in_traceback() {
+        let content = "2024-01-01T12:00:00.123Z ERROR app::worker: task failed\n\
+                       Traceback (most recent call last):\n\
+                       \n\
+                       \n\
+                       ValueError: bad input\n\
+                       \n\
+                       2024-01-01T12:00:01.000Z INFO app::worker: retrying\n";
+        let parser = LogParser::new().unwrap();
+        let entries = parser.parse_multiline_logs(content);
+        assert_eq!(entries[0].message, "task failed\nTraceback (most recent call last):\nValueError: bad input");
+        // 元の行は空行を省かない
+        assert_eq!(entries[0].raw, "2024-01-01T12:00:00.123Z ERROR app::worker: task failed\nTraceback (most recent call last):\n\n\nValueError: bad input");
+
+        let parser = parser.with_keep_blank_lines(true);
+        let entries = parser.parse_multiline_logs(content);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "task failed\nTraceback (most recent call last):\n\n\nValueError: bad input");
+        assert_eq!(entries[1].message, "retrying");
+    }
+
+    #[test]
+    fn test_multiple_patterns_tried_in_order() {
+        // アプリのログとアクセスログが交互に出力されるファイル
+        let access = r#"(?P<timestamp>\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}) \[(?P<level>\w+)\] (?P<target>access): (?P<message>.*)"#;
+        let content = "2024-01-01T12:00:00Z INFO app::http: listening\n\
+                       2024-01-01 12:00:01 [INFO] access: GET /health 200\n\
+                       2024-01-01T12:00:02Z WARN app::db: slow query\n\
+                       \x20   select * from users\n\
+                       2024-01-01 12:00:03 [WARN] access: GET /users 503\n";
+        let parser = LogParser::new()
+            .unwrap()
+            .with_patterns(&[DEFAULT_PATTERN.to_string(), access.to_string()])
+            .unwrap();
+        let entries = parser.parse_multiline_logs(content);
+        let summary: Vec<(&str, &str)> = entries.iter().map(|entry| (entry.target.as_str(), entry.level.as_str())).collect();
+        assert_eq!(summary, [("app::http", "INFO"), ("access", "INFO"), ("app::db", "WARN"), ("access", "WARN")]);
+        assert_eq!(entries[1].timestamp, "2024-01-01 12:00:01");
+        // どのパターンにも一致しない行は直前のエントリの続きになる
+        assert_eq!(entries[2].message, "slow query\n    select * from users");
+
+        // 既定のパターンだけではアクセスログは続きの行として扱われる
+        assert_eq!(LogParser::new().unwrap().parse_multiline_logs(content).len(), 2);
+        assert!(LogParser::new().unwrap().with_patterns(&["(?P<level>\\w+)".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_targets_with_hyphens_dots_and_brackets() {
+        assert!(DEFAULT_PATTERN.contains(r"\s+(?P<level>\w+)\s+(?P<target>[\w:.\-\[\]]+):"));
+        let parser = LogParser::new().unwrap();
+        for (line, target) in [
+            ("2024-01-01T12:00:00Z INFO my-crate::module: started", "my-crate::module"),
+            ("2024-01-01T12:00:00Z INFO com.example.Service: started", "com.example.Service"),
+            ("2024-01-01T12:00:00Z WARN app::worker[3]: busy: retrying", "app::worker[3]"),
+        ] {
+            let entry = parser.parse_line(line).unwrap();
+            assert_eq!(entry.target, target);
+        }
+        assert_eq!(parser.parse_line("2024-01-01T12:00:00Z WARN app::worker[3]: busy: retrying").unwrap().message, "busy: retrying");
+
+        // 文字クラスを変えると空白を含むターゲットも受け付けられる
+        let spaced = LogParser::new().unwrap().with_patterns(&[tracing_pattern(r"\w: ", false)]).unwrap();
+        assert_eq!(spaced.parse_line("2024-01-01T12:00:00Z INFO Http Server: up").unwrap().target, "Http Server");
+        assert!(parser.parse_line("2024-01-01T12:00:00Z INFO Http Server: up").is_none());
+    }
+
+    #[test]
+    fn test_optional_level() {
+        let pattern = tracing_pattern(DEFAULT_TARGET_CHARS, true);
+        let strict = LogParser::new().unwrap().with_patterns(std::slice::from_ref(&pattern)).unwrap();
+        let lenient = LogParser::new().unwrap().with_patterns(&[pattern]).unwrap().with_optional_level(true);
+
+        let bare = "2024-01-01T12:00:00Z nginx: GET /health 200";
+        assert!(strict.parse_line(bare).is_none());
+        let entry = lenient.parse_line(bare).unwrap();
+        assert_eq!((entry.level.as_str(), entry.target.as_str()), ("INFO", "nginx"));
+        assert_eq!(entry.message, "GET /health 200");
+
+        let leveled = lenient.parse_line("2024-01-01T12:00:00Z warn app::db: slow").unwrap();
+        assert_eq!((leveled.level.as_str(), leveled.target.as_str()), ("WARN", "app::db"));
+        // 既定のパターンではレベルが必須のまま
+        let default = LogParser::new().unwrap().with_optional_level(true);
+        assert!(default.parse_line(bare).is_none());
+    }
+
+    #[test]
+    fn test_detect_formats() {
+        let lines = [
+            r#"{"v":0,"level":30,"time":"2024-01-01T12:00:00Z","name":"api","msg":"up"}"#,
+            r#"{"v":0,"level":50,"time":"2024-01-01T12:00:01Z","name":"api","msg":"down"}"#,
+            "2024-01-01T12:00:02Z INFO app: plain",
+            "    at frame",
+        ];
+        let custom = r"(?P<timestamp>\S+) (?P<level>\w+) (?P<target>\w+): (?P<message>.*)".to_string();
+        let matches = detect_formats(&lines, &[custom]).unwrap();
+        let counts: Vec<usize> = matches.iter().map(|candidate| candidate.matched).collect();
+        assert_eq!(counts, [1, 2, 2, 1]);
+        assert_eq!(matches[2].args, "--format bunyan");
+        assert!(matches[3].args.starts_with("--log-regex '"));
+    }
+
     #[test]
     fn test_module_tree_insertion() {
         let mut tree = ModuleTree::new("root".to_string());
@@ -191,6 +1165,24 @@ mod tests {
         assert!(tree.children["myapp"].children["module"].children.contains_key("submodule"));
     }
 
+    #[test]
+    fn test_max_depth_flattens_deep_paths() {
+        let mut tree = ModuleTree::new("root".to_string()).with_max_depth(Some(3));
+        tree.insert_module("a::b::c::d::e");
+        tree.insert_module("a::b::x");
+        tree.insert_module("a");
+
+        let b = &tree.children["a"].children["b"];
+        assert_eq!(b.children.keys().len(), 2);
+        assert!(b.children["c::d::e"].children.is_empty());
+
+        tree.toggle_selection("a::b::c::d::e");
+        assert!(!tree.is_module_selected("a::b::c::d::e"));
+        assert!(tree.is_module_selected("a::b::x"));
+        tree.set_min_level("a::b::c::d::e", Some("WARN".to_string()));
+        assert_eq!(tree.module_min_level("a::b::c::d::e"), Some("WARN"));
+    }
+
     #[test]
     fn test_module_selection() {
         let mut tree = ModuleTree::new("root".to_string());
@@ -201,6 +1193,37 @@ mod tests {
         assert!(!tree.is_module_selected("myapp::module"));
     }
 
+    #[test]
+    fn test_module_min_level_inherits_from_ancestors() {
+        let mut tree = ModuleTree::new("root".to_string());
+        tree.insert_module("myapp::db::pool");
+        tree.insert_module("myapp::http");
+
+        assert_eq!(tree.module_min_level("myapp::db::pool"), None);
+        assert!(!tree.has_min_levels());
+        tree.set_min_level("myapp::db", Some("WARN".to_string()));
+        assert!(tree.has_min_levels());
+        assert_eq!(tree.module_min_level("myapp::db"), Some("WARN"));
+        assert_eq!(tree.module_min_level("myapp::db::pool"), Some("WARN"));
+        assert_eq!(tree.module_min_level("myapp::http"), None);
+
+        tree.set_min_level("myapp::db::pool", Some("DEBUG".to_string()));
+        assert_eq!(tree.module_min_level("myapp::db::pool"), Some("DEBUG"));
+    }
+
+    #[test]
+    fn test_severity_order() {
+        let syslog = SeverityOrder::preset(SeverityPreset::Syslog);
+        assert!(syslog.rank("CRITICAL") < syslog.rank("ERROR"));
+        assert!(syslog.rank("WARN") < syslog.rank("NOTICE") && syslog.rank("NOTICE") < syslog.rank("INFO"));
+        assert_eq!(syslog.rank("RAW"), syslog.levels().len());
+
+        let custom = SeverityOrder::new(["fatal", " error", "", "FATAL", "info"]);
+        assert_eq!(custom.levels(), ["FATAL", "ERROR", "INFO"]);
+        assert_eq!(SeverityOrder::preset(SeverityPreset::Bunyan).rank("FATAL"), 0);
+        assert_eq!(SeverityOrder::default().levels(), LEVELS);
+    }
+
     #[test]
     fn test_multiline_log_parsing() {
         let parser = LogParser::new().unwrap();
@@ -223,5 +1246,146 @@ Error details on next line
         
         assert_eq!(entries[2].level, "WARN");
         assert_eq!(entries[2].message, "Third message");
+
+        assert_eq!(entries[1].line_number, Some(4));
+        assert_eq!(entries[2].line_number, Some(7));
+        assert_eq!(entries[0].byte_offset, Some(0));
+        assert_eq!(entries[1].byte_offset, Some(content.find("2024-01-01T12:00:01").unwrap() as u64));
+
+        // 先頭のエントリより前の行は捨てられ、数えられる
+        assert_eq!(parser.dropped_lines(), 0);
+        parser.parse_multiline_logs("orphan line\n\n  another\n2024-01-01T12:00:03Z INFO app: ok");
+        assert_eq!(parser.dropped_lines(), 2);
+    }
+
+    #[test]
+    fn test_continue_multiline_logs() {
+        let parser = LogParser::new().unwrap();
+        let (entries, pending) = parser.continue_multiline_logs(None, "2024-01-01T12:00:00Z ERROR app: failed\n  at a.rs:1");
+        assert!(entries.is_empty());
+
+        let (entries, pending) = parser.continue_multiline_logs(pending, "  at b.rs:2\n2024-01-01T12:00:01Z INFO app: ok");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].message, "failed\n  at a.rs:1\n  at b.rs:2");
+        assert_eq!(entries[0].line_number, Some(1));
+        assert_eq!(pending.unwrap().line_number, Some(2));
+        assert_eq!(parser.dropped_lines(), 0);
+    }
+
+    #[test]
+    fn test_no_multiline_keeps_raw_lines() {
+        let parser = LogParser::new().unwrap().with_multiline(false);
+        let content = "banner\n2024-01-01T12:00:00Z ERROR app: failed\n  at main.rs:3\n\n2024-01-01T12:00:01Z INFO app: ok";
+        let entries = parser.parse_multiline_logs(content);
+        let summary: Vec<_> = entries.iter().map(|entry| (entry.level.as_str(), entry.message.as_str(), entry.line_number)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (RAW_LEVEL, "banner", Some(1)),
+                ("ERROR", "failed", Some(2)),
+                (RAW_LEVEL, "  at main.rs:3", Some(3)),
+                ("INFO", "ok", Some(5)),
+            ]
+        );
+        assert_eq!(entries[2].byte_offset, Some(content.find("  at").unwrap() as u64));
+        assert_eq!(parser.dropped_lines(), 0);
+    }
+
+    #[test]
+    fn test_entries_json_records() {
+        let parser = LogParser::new().unwrap().with_format(LogFormat::EntriesJson);
+        let entry = LogEntry {
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            level: "WARN".to_string(),
+            target: "app".to_string(),
+            message: "line one\nline two".to_string(),
+            fields: IndexMap::from([("user".to_string(), "42".to_string())]),
+            source: "original.log".to_string(),
+            id: 7,
+            ..Default::default()
+        };
+        let record = serde_json::to_string(&entry).unwrap();
+
+        // 1行に1件、壊れたレコードは飛ばして数える
+        let content = format!("{}\n{{\"level\": \"INFO\"}}\n\n{}\n", record, record);
+        let entries = parser.parse_multiline_logs(&content);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].message.as_str(), entries[0].id, entries[0].fields["user"].as_str()), ("line one\nline two", 0, "42"));
+        assert_eq!(entries[1].line_number, Some(4));
+        // 元の行を持たないレコードはレコード自体を元の行とする
+        assert!(!record.contains("\"raw\""));
+        assert_eq!(entries[0].raw, record);
+        let with_raw = serde_json::to_string(&LogEntry { raw: "2024-01-01T00:00:00Z WARN app: line one".to_string(), ..entry.clone() }).unwrap();
+        assert_eq!(parser.parse_line(&with_raw).unwrap().raw, "2024-01-01T00:00:00Z WARN app: line one");
+        let (count, first) = parser.malformed_records();
+        assert_eq!(count, 1);
+        assert!(first.unwrap().starts_with("line 2: missing field `timestamp`"));
+        assert_eq!(parser.dropped_lines(), 1);
+
+        // JSON配列
+        let array = format!("[\n  {},\n  {{\"timestamp\": \"\", \"level\": \"\", \"target\": \"\", \"message\": \"\", \"fields\": {{}}}},\n  {}\n]", record, record);
+        assert!(parser.is_entry_array(&array));
+        assert_eq!(parser.parse_multiline_logs(&array).len(), 2);
+        assert_eq!(parser.malformed_records().0, 2);
+        assert!(parser.parse_multiline_logs("[1, ").is_empty());
+        assert_eq!(parser.malformed_records().0, 3);
+    }
+
+    #[test]
+    fn test_split_trailing_fields() {
+        let parser = LogParser::new().unwrap().with_split_fields(true);
+        let entry = parser
+            .parse_line(r#"2024-01-01T12:00:00Z INFO app::http: request done status=200 path="/a b" note="say \"hi\"" http.method=GET"#)
+            .unwrap();
+        assert_eq!(entry.message, "request done");
+        let fields: Vec<(&str, &str)> = entry.fields.iter().map(|(key, value)| (key.as_str(), value.as_str())).collect();
+        assert_eq!(fields, [("status", "200"), ("path", "/a b"), ("note", r#"say "hi""#), ("http.method", "GET")]);
+        assert!(entry.raw.ends_with("http.method=GET"));
+
+        // 既定では分けない
+        let entry = LogParser::new().unwrap().parse_line("2024-01-01T12:00:00Z INFO app: done status=200").unwrap();
+        assert_eq!(entry.message, "done status=200");
+    }
+
+    #[test]
+    fn test_split_trailing_fields_keeps_equals_inside_messages() {
+        let unchanged = [
+            // 後ろにkey=value以外の語がある
+            "set x=1 before retrying",
+            "a = b",
+            // 引用の中の=
+            r#"failed to parse "a=b""#,
+            r#"failed to parse "a b=c""#,
+            // key=valueだけのメッセージ
+            "status=200 path=/",
+            // 閉じていない引用
+            r#"done path="/a b"#,
+            "done 1x=2",
+        ];
+        for message in unchanged {
+            assert_eq!(split_trailing_fields(message), (message, IndexMap::new()), "{}", message);
+        }
+        let (message, fields) = split_trailing_fields("query failed: WHERE id = 5 retries=3");
+        assert_eq!((message, fields.len()), ("query failed: WHERE id = 5", 1));
+        assert_eq!(split_trailing_fields(r#"empty value=""  "#).1["value"], "");
+    }
+
+    #[test]
+    fn test_format_mismatch() {
+        let json_lines = r#"{"level":"info","msg":"started"}"#.to_string() + "\n\n";
+        let parser = LogParser::new().unwrap().with_format(LogFormat::Tracing);
+        parser.parse_multiline_logs(&json_lines.repeat(3));
+        // 読み終えた入力なら少ない行数でも判定し、続きがありうるならサンプルが揃うまで待つ
+        assert_eq!(parser.format_mismatch(true), Some(3));
+        assert_eq!(parser.format_mismatch(false), None);
+        parser.parse_multiline_logs(&json_lines.repeat(FORMAT_CHECK_LINES));
+        assert_eq!(parser.format_mismatch(false), Some(FORMAT_CHECK_LINES));
+
+        let parser = LogParser::new().unwrap().with_format(LogFormat::Tracing);
+        parser.parse_multiline_logs(&format!("{}2024-01-01T12:00:00Z INFO app: ok\n", json_lines.repeat(FORMAT_CHECK_LINES - 1)));
+        assert_eq!(parser.format_mismatch(true), None);
+        let raw = LogParser::new().unwrap().with_format(LogFormat::Tracing).with_multiline(false);
+        raw.parse_multiline_logs(&json_lines);
+        assert_eq!(raw.format_mismatch(true), None);
     }
 }
\ No newline at end of file
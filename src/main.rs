@@ -1,22 +1,27 @@
-mod log_parser;
-mod ui;
-
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyEventKind, MouseEventKind},
+    cursor::Show,
+    event::{DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use log_parser::{LogEntry, LogParser};
+use tracing_viewer::clipboard::{self, SystemClipboard};
+use tracing_viewer::config;
+use tracing_viewer::export::EntryAnchor;
+use tracing_viewer::history::InputHistory;
+use tracing_viewer::journald;
+use tracing_viewer::notes::SavedNotes;
+use tracing_viewer::mouse::{self, MouseAction, MouseBindings, MouseTrigger};
+use tracing_viewer::log_parser::{
+    tracing_pattern, LogEntry, LogFormat, LogParser, SeverityOrder, SeverityPreset, DEFAULT_TARGET_CHARS,
+};
 use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
 use std::{
-    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    io::{self, BufRead, BufReader, IsTerminal},
     time::Duration,
-    path::Path,
-    fs::File,
 };
 use futures::StreamExt;
 use tokio::{
@@ -24,32 +29,219 @@ use tokio::{
     time::interval,
 };
 use tokio_util::sync::CancellationToken;
-use notify::{Watcher, RecursiveMode, RecommendedWatcher, Event as NotifyEvent, EventKind, Config};
-use ui::{App, AppMode};
-use arboard::Clipboard;
-use std::sync::{Arc, Mutex};
-use std::process::Command;
+use tracing_viewer::stats::session_summary;
+use tracing_viewer::status::StatusSegment;
+use tracing_viewer::template::{EntryTemplate, DEFAULT_COPY_FORMAT};
+use tracing_viewer::timestamp::merge_by_timestamp;
+use tracing_viewer::ui::{self, App, EntrySeparator, LineNumberMode};
+use tracing_viewer::watch::{
+    parse_logs_from_content, watch_file, EntryAssembler, FilePosition, PollingFallback, SourceLine, LOAD_CHUNK_BYTES,
+};
+use std::sync::Arc;
 use log::{debug, error};
 
 
+/// Source name used for lines read from standard input
+const STDIN_SOURCE: &str = "stdin";
+
 #[derive(Parser)]
 #[command(name = "tracing-viewer")]
 #[command(about = "A TUI application for filtering and viewing tracing logs")]
 #[command(version)]
 struct Cli {
-    #[arg(short, long, help = "Input file path (default: stdin)")]
-    input: Option<String>,
+    #[arg(long, value_name = "PATH", help = "TOML config file with defaults for any long option (`option = value`) plus [keys], [theme] and [[highlight]] tables (default: tracing-viewer/config.toml in the config directory, if it exists)")]
+    config: Option<String>,
+
+    #[arg(short, long, help = "Input file path; repeat to follow several files (default: stdin)")]
+    input: Vec<String>,
+
+    #[arg(long, help = "Stream logs from an HTTP(S) endpoint (plain lines or NDJSON), reconnecting on drop")]
+    url: Option<String>,
+
+    #[arg(long, help = "Accept newline-delimited logs over TCP on this address (e.g. 127.0.0.1:5170)")]
+    listen: Option<String>,
+
+    #[arg(long, value_name = "UNIT", help = "Follow a systemd unit through `journalctl -o json`, mapping priorities to levels and the syslog identifier to the target")]
+    journald: Option<String>,
     
     #[arg(short, long, default_value = "300", help = "Refresh interval in milliseconds")]
     refresh: u64,
 
+    #[arg(long, help = "Show a followed entry after its source has been quiet this many milliseconds, even if no next entry has started yet (default: the refresh interval)")]
+    multiline_flush: Option<u64>,
+
     #[arg(long, help = "Enable logging to the specified file")]
     log_file: Option<String>,
+
+    #[arg(long, help = "Insert a header line between entries whose date changes")]
+    day_separators: bool,
+
+    #[arg(long, default_value = DEFAULT_COPY_FORMAT, help = "Format used when copying entries. Placeholders: {timestamp}, {level}, {target}, {message}, {source}, {fields.<name>}")]
+    copy_format: String,
+
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=9), help = "Fractional-second digits shown in the timestamp column (0-9), padding or truncating so the column lines up [default: as written]")]
+    timestamp_precision: Option<u8>,
+
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u16).range(1..), help = "Lines scrolled per mouse wheel notch")]
+    scroll_step: u16,
+
+    #[arg(long, value_name = "BUTTON=ACTION", value_parser = mouse::parse_binding, help = "Change what a mouse button does; repeat for several. Buttons: left, middle, right, wheel-up, wheel-down. Actions: click (jump via the minimap), scroll-up, scroll-down, details, palette, none. Defaults: left=click, wheel-up=scroll-up, wheel-down=scroll-down; with every button set to none the mouse isn't captured, keeping the terminal's own selection and paste")]
+    mouse: Vec<(MouseTrigger, MouseAction)>,
+
+    #[arg(long, default_value = "60", value_parser = clap::value_parser!(u16).range(1..=240), help = "Redraw at most this many times per second (1-240). Lower it on slow or high-latency terminals (e.g. SSH) to save bandwidth; raise it for snappier updates on fast local ones")]
+    max_fps: u16,
+
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..), help = "Check input files for appended lines every MS milliseconds instead of relying on file-change events (for network mounts and editors that don't trigger them)")]
+    poll_interval: Option<u64>,
+
+    #[arg(long, value_name = "LINES", help = "Sticky follow: only jump to new entries while the view is within this many lines of the bottom")]
+    sticky_follow: Option<usize>,
+
+    #[arg(long, help = "Start with the severity pane open, showing entries at or above this level (toggle with S)")]
+    split_level: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = SeverityPreset::Tracing, help = "Severity ordering used by level thresholds and sorting; also names numeric Bunyan levels and journald priorities (e.g. 60 as FATAL, priority 5 as NOTICE) when the ladder has those levels")]
+    level_preset: SeverityPreset,
+
+    #[arg(long, value_delimiter = ',', help = "Comma-separated level names, most severe first, replacing --level-preset (e.g. FATAL,ERROR,WARN,NOTICE,INFO,DEBUG); unlisted levels rank lowest")]
+    levels: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = LineNumberMode::Off, help = "Line-number gutter shown at startup (cycle with #)")]
+    line_numbers: LineNumberMode,
+
+    #[arg(long, value_enum, default_value_t = EntrySeparator::None, help = "Set consecutive entries apart with a faint rule or alternating row backgrounds")]
+    entry_separator: EntrySeparator,
+
+    #[arg(long, default_value = "8", value_parser = clap::value_parser!(u8).range(1..=16), help = "Columns between tab stops when tabs in messages are expanded for display (1-16)")]
+    tab_width: u8,
+
+    #[arg(long, default_value = "", help = "Marker drawn dimmed at the start of continuation lines of multi-line messages, e.g. '│' (display only)")]
+    continuation_marker: String,
+
+    #[arg(long, default_value = "blue", help = "Background color of the focused entry, as a color name (e.g. blue, darkgray), an index 0-255 or #rrggbb")]
+    focus_color: ratatui::style::Color,
+
+    #[arg(long, value_enum, value_delimiter = ',', help = "Comma-separated status-bar segments shown before the help text (filters, counts, rate, follow, clock)")]
+    status_left: Vec<StatusSegment>,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "filters", help = "Comma-separated status-bar segments shown at the right end (filters, counts, rate, follow, clock)")]
+    status_right: Vec<StatusSegment>,
+
+    #[arg(long, help = "Require pressing q twice within a second to quit")]
+    confirm_quit: bool,
+
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..), help = "Show at most this many levels in the module tree, keeping deeper path segments together in the last level")]
+    max_module_depth: Option<u16>,
+
+    #[arg(long, help = "Shorten targets longer than this many characters with a middle ellipsis")]
+    max_target_width: Option<usize>,
+
+    #[arg(long, value_enum, default_value_t = LogFormat::Auto, help = "Input format (entries-json reads serialized entries, one per line or as a JSON array in a file, skipping and reporting malformed records)")]
+    format: LogFormat,
+
+    #[arg(long, help = "Keep blank lines inside multi-line messages such as tracebacks")]
+    keep_blank_lines: bool,
+
+    #[arg(long, help = "Move key=value pairs trailing the message of text lines (values may be double-quoted) into the entry's fields")]
+    split_fields: bool,
+
+    #[arg(long, help = "Show every line that doesn't parse as its own RAW entry instead of merging it into the previous message")]
+    no_multiline: bool,
+
+    #[arg(long, help = "Regex with named groups timestamp, level, target and message used to parse text lines; repeat to try several patterns in order (default: tracing fmt output)")]
+    log_regex: Vec<String>,
+
+    #[arg(long, default_value = DEFAULT_TARGET_CHARS, help = "Characters allowed in targets by the default pattern, as the inside of a regex character class (ignored with --log-regex)")]
+    target_chars: String,
+
+    #[arg(long, help = "Start with only entries whose target or message contains this text (ignoring ASCII case; toggle with &)")]
+    grep: Option<String>,
+
+    #[arg(long, help = "Make --grep and the & filter case-sensitive")]
+    grep_case_sensitive: bool,
+
+    #[arg(long, help = "Start with this search query highlighted and the first match focused (as with /)")]
+    search: Option<String>,
+
+    #[arg(long, value_name = "ANCHOR", value_parser = parse_goto, help = "Start focused on this entry, as shown in the details popup and in HTML report links: FILE:LINE for entries read from files (app.log:120 or report.html#app.log:120), e42 for others")]
+    goto: Option<EntryAnchor>,
+
+    #[arg(long, value_name = "TERM", help = "Color this term wherever it appears in messages, ignoring ASCII case (as with h); repeat for several, each in its own color")]
+    highlight: Vec<String>,
+
+    #[arg(long, value_name = "KEY", help = "Start with only entries that have this field (as with F)")]
+    has_field: Option<String>,
+
+    #[arg(long, value_delimiter = ',', help = "Comma-separated levels shown at startup; the others start hidden")]
+    show_levels: Vec<String>,
+
+    #[arg(long, value_name = "MODULE", help = "Start with this module and its submodules hidden; repeat for several")]
+    hide_module: Vec<String>,
+
+    #[arg(long, help = "Accept text lines without a level and show them as INFO (custom --log-regex patterns must make the level group optional)")]
+    level_optional: bool,
+
+    #[arg(long, help = "Report which input format matches a sample of the input best, then exit without starting the viewer")]
+    detect: bool,
+
+    #[arg(long, value_name = "PATH", help = "Write the entries that pass the startup filters (--show-levels, --hide-module, --has-field, --grep) to PATH as a self-contained HTML report, then exit without starting the viewer")]
+    export_html: Option<String>,
+
+    #[arg(long, value_name = "PATH", conflicts_with = "export_html", help = "Like --export-html, but write the entries as text lines in the --copy-format")]
+    export_text: Option<String>,
+
+    #[arg(long, help = "Include notes on entries in exported reports and copied lines")]
+    export_notes: bool,
+
+    #[arg(long, help = "Show a header line with the inputs, format and follow state (toggle with H)")]
+    header: bool,
+
+    #[arg(long, value_delimiter = ',', help = "Comma-separated keys tried in order for the message of JSON entries (default: message,msg,body,event)")]
+    message_field: Vec<String>,
+
+    #[arg(long, hide = true, value_name = "PATH", help = "Write the view state (counts, focus, scroll position, filters) as JSON to PATH whenever it changes, for tests and scripts")]
+    dump_state: Option<String>,
+
+    #[arg(long, value_delimiter = ',', help = "Comma-separated field keys listed first in the details popup (e.g. trace_id,error); other fields follow in their original order")]
+    pinned_fields: Vec<String>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let (mut cli, config) = parse_cli_with_config()?;
+    // テンプレートとパターンは端末を初期化する前に検証する
+    let copy_template = EntryTemplate::parse(&cli.copy_format)?;
+    let severity = if cli.levels.is_empty() {
+        SeverityOrder::preset(cli.level_preset)
+    } else {
+        SeverityOrder::new(&cli.levels)
+    };
+    let split_level = cli.split_level.as_ref().map(|level| level.to_ascii_uppercase());
+    if let Some(level) = &split_level
+        && !severity.levels().contains(level)
+    {
+        anyhow::bail!("--split-level {} is not one of the levels: {}", level, severity.levels().join(", "));
+    }
+    if cli.detect {
+        return detect_format(&cli);
+    }
+    let patterns = if !cli.log_regex.is_empty() {
+        cli.log_regex.clone()
+    } else {
+        vec![tracing_pattern(&cli.target_chars, cli.level_optional)]
+    };
+    let mut parser = LogParser::new()?
+        .with_patterns(&patterns)?
+        .with_format(cli.format)
+        .with_message_fields(cli.message_field.clone())
+        .with_keep_blank_lines(cli.keep_blank_lines)
+        .with_multiline(!cli.no_multiline)
+        .with_split_fields(cli.split_fields)
+        .with_optional_level(cli.level_optional)
+        .with_severity_order(severity.clone());
+    if let Some(path) = cli.export_html.as_deref().or(cli.export_text.as_deref()) {
+        return export_entries(&cli, &parser, severity, path);
+    }
 
     if let Some(log_path) = &cli.log_file {
         let log_file = std::fs::File::create(log_path)?;
@@ -58,85 +250,200 @@ async fn main() -> anyhow::Result<()> {
             .init();
     }
 
+    let mouse_bindings = MouseBindings::with_overrides(&cli.mouse);
     enable_raw_mode()?;
+    // 以降は`?`で抜けた場合も端末を元に戻す
+    let terminal_guard = TerminalGuard;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen)?;
+    // マウスを使わない設定なら端末の選択や中クリックの貼り付けを妨げない
+    if mouse_bindings.captures_mouse() {
+        execute!(stdout, EnableMouseCapture)?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Terminal cleanup on panic or early exit
-    let cleanup = || {
-        let _ = disable_raw_mode();
-        let _ = execute!(
-            io::stdout(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        );
-    };
-    
-    // Register panic hook for proper cleanup
-    std::panic::set_hook(Box::new(move |_| {
-        cleanup();
+    // Register panic hook for proper cleanup, keeping the default panic message
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
     }));
 
     let (log_sender, mut log_receiver) = mpsc::unbounded_channel();
+    // 処理を続けられるエラーは画面上のバナーで知らせる
+    let (error_sender, mut error_receiver) = mpsc::unbounded_channel::<String>();
+    // ポーリングへの切り替えはエラーではないので状態として表示する
+    let (fallback_sender, mut fallback_receiver) = mpsc::unbounded_channel::<PollingFallback>();
 
-    let parser = LogParser::new()?;
     let mut app = App::new();
+    app.editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    app.show_day_separators = cli.day_separators;
+    app.copy_template = copy_template;
+    app.export_notes = cli.export_notes;
+    app.max_target_width = cli.max_target_width;
+    app.set_max_module_depth(cli.max_module_depth.map(usize::from));
+    app.timestamp_precision = cli.timestamp_precision.map(usize::from);
+    app.scroll_step = cli.scroll_step as usize;
+    app.mouse_bindings = mouse_bindings;
+    app.follow_stickiness = cli.sticky_follow;
+    app.confirm_quit = cli.confirm_quit;
+    app.line_number_mode = cli.line_numbers;
+    app.entry_separator = cli.entry_separator;
+    app.show_header = cli.header;
+    app.pinned_fields = cli.pinned_fields.clone();
+    app.format_label = format_label(&cli);
+    app.continuation_marker = cli.continuation_marker.clone();
+    app.tab_width = cli.tab_width as usize;
+    app.focus_color = cli.focus_color;
+    app.key_bindings = config.keys;
+    app.theme = config.theme;
+    app.status_left = cli.status_left.clone();
+    app.status_right = cli.status_right.clone();
+    app.parse_pattern = parser.pattern().to_string();
+    app.set_severity_order(severity);
+    apply_startup_filters(&mut app, &cli);
+    app.launch_args = launch_args(&cli);
+    if let Some(level) = split_level {
+        app.show_split = true;
+        app.split_min_level = level;
+    }
+    app.history_path = InputHistory::default_path();
+    if let Some(path) = &app.history_path {
+        app.input_history = InputHistory::load(path);
+    }
+    app.notes_path = SavedNotes::default_path();
+    if let Some(path) = &app.notes_path {
+        app.saved_notes = SavedNotes::load(path);
+    }
     
-    // クリップボードオブジェクトを長期間保持するためのコンテナ
-    let clipboard_holder: Arc<Mutex<Option<Clipboard>>> = Arc::new(Mutex::new(None));
+    let mut system_clipboard = SystemClipboard::default();
 
     // Create cancellation token for background tasks
     let cancellation_token = CancellationToken::new();
     let mut background_tasks = Vec::new();
 
-    if let Some(input_file) = cli.input {
-        // 初期ファイル読み込み
-        let file_content = std::fs::read_to_string(&input_file)?;
-        let logs = parse_logs_from_content(&parser, &file_content);
-        app.update_logs(logs);
-        
-        // ファイル監視を開始
-        let input_file_clone = input_file.clone();
+    // 入力ファイルごとの監視タスク（cli.inputと同じ順。再読み込みに成功したら差し替える）
+    let mut watch_tasks: Vec<(CancellationToken, tokio::task::JoinHandle<()>)> = Vec::new();
+    let poll_interval = cli.poll_interval.map(Duration::from_millis);
+    if !cli.input.is_empty() {
+        // 初期ファイル読み込み（全ファイルを時刻順にまとめて一度に反映する）
+        let mut initial_logs = Vec::new();
+        for input_file in &cli.input {
+            let (logs, start) = load_input_file(&parser, input_file, &mut terminal, &mut app)?;
+            initial_logs.push(logs);
+            watch_tasks.push(spawn_watch(input_file, start, poll_interval, &log_sender, &error_sender, &fallback_sender, &cancellation_token));
+        }
+        app.update_logs(merge_by_timestamp(initial_logs));
+        for input_file in &cli.input {
+            app.remember_file(input_file);
+        }
+    }
+    // ファイルは読み終えているので、サンプルが揃っていなくても判定する
+    let mut format_checked = false;
+    if !cli.input.is_empty()
+        && let Some(sampled) = parser.format_mismatch(true)
+    {
+        app.show_format_warning(sampled);
+        format_checked = true;
+    }
+    let mut reported_malformed = 0;
+    report_malformed_records(&parser, &mut app, &mut reported_malformed);
+    if let Some(query) = cli.search.clone() {
+        app.apply_search(query);
+    }
+    for rule in &config.highlight {
+        app.add_highlight_rule(&rule.term, rule.color);
+    }
+    for term in &cli.highlight {
+        app.add_highlight_term(term);
+    }
+    if let Some(anchor) = &cli.goto {
+        app.goto_entry(anchor);
+    }
+
+    if let Some(url) = cli.url.clone() {
+        let log_sender_clone = log_sender.clone();
+        let token_clone = cancellation_token.clone();
+        let url_handle = tokio::spawn(async move {
+            debug!("stream_url task started: {}", url);
+            stream_url(&url, log_sender_clone, token_clone).await;
+            debug!("stream_url task ended");
+        });
+        background_tasks.push(url_handle);
+    }
+
+    if let Some(address) = cli.listen.clone() {
+        let listener = tokio::net::TcpListener::bind(&address)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to listen on {}: {}", address, e))?;
+        let log_sender_clone = log_sender.clone();
+        let token_clone = cancellation_token.clone();
+        let listen_handle = tokio::spawn(async move {
+            debug!("listen task started: {}", address);
+            accept_tcp(listener, log_sender_clone, token_clone).await;
+            debug!("listen task ended");
+        });
+        background_tasks.push(listen_handle);
+    }
+
+    if let Some(unit) = cli.journald.clone() {
         let log_sender_clone = log_sender.clone();
+        let error_sender_clone = error_sender.clone();
         let token_clone = cancellation_token.clone();
-        let watch_handle = tokio::spawn(async move {
-            debug!("watch_file task started");
-            if let Err(e) = watch_file(&input_file_clone, log_sender_clone, token_clone).await {
-                error!("ファイル監視エラー: {}", e);
+        let severity = app.severity.clone();
+        let journald_handle = tokio::spawn(async move {
+            debug!("journald task started: {}", unit);
+            if let Err(e) = stream_journald(&unit, &severity, log_sender_clone, token_clone).await {
+                error!("journalctlの読み込みエラー: {}", e);
+                let _ = error_sender_clone.send(format!("Stopped reading the journal for {}: {}", unit, e));
             }
-            debug!("watch_file task ended");
+            debug!("journald task ended");
         });
-        background_tasks.push(watch_handle);
-        
-        // タスクが正常に開始されたことを確認
-        debug!("watch_file task spawned successfully");
-    } else {
+        background_tasks.push(journald_handle);
+    }
+
+    // 他の入力元の指定がない場合、またはパイプで標準入力が渡された場合は標準入力も読み込む
+    let has_other_source = !cli.input.is_empty() || cli.url.is_some() || cli.listen.is_some() || cli.journald.is_some();
+    let reads_stdin = !io::stdin().is_terminal();
+    // 入力が何もない状態で端末から起動された場合は、空の標準入力を待たずに最近のファイルを選ばせる
+    if !has_other_source && !reads_stdin {
+        app.open_recent_files();
+    }
+    app.input_label = input_label(&cli, reads_stdin);
+    if reads_stdin {
         let token_clone = cancellation_token.clone();
-        let stdin_handle = tokio::spawn(async move {
+        let log_sender_clone = log_sender.clone();
+        // 行の読み込みはブロックするので専用スレッドで行う。ランタイムのワーカーを塞がず、
+        // 終了時にランタイムが読み込みの完了を待つこともない（キー入力は標準入力ではなく端末から読まれる）
+        std::thread::spawn(move || {
             let stdin = io::stdin();
             let reader = BufReader::new(stdin);
+            let source: Arc<str> = Arc::from(STDIN_SOURCE);
             
             for line in reader.lines() {
                 if token_clone.is_cancelled() {
                     break;
                 }
-                if let Ok(line) = line {
-                    if log_sender.send(line).is_err() {
-                        break;
-                    }
+                if let Ok(line) = line
+                    && log_sender_clone.send(SourceLine { source: source.clone(), line, position: None }).is_err()
+                {
+                    break;
                 }
             }
+            // 標準入力がEOFに達してもファイル監視は継続する
+            debug!("標準入力がクローズされました");
         });
-        background_tasks.push(stdin_handle);
     }
 
     let mut refresh_interval = interval(Duration::from_millis(cli.refresh));
     let mut pending_logs = Vec::new();
+    let mut assembler = EntryAssembler::default().with_flush_timeout(Duration::from_millis(cli.multiline_flush.unwrap_or(cli.refresh)));
     let mut should_redraw = true;
     let mut last_redraw_time = std::time::Instant::now();
-    let min_redraw_interval = Duration::from_millis(16); // 約60fps
+    let min_redraw_interval = Duration::from_secs(1) / u32::from(cli.max_fps);
     let mut event_stream = EventStream::new();
 
     debug!("メインループ開始前の準備完了");
@@ -145,16 +452,36 @@ async fn main() -> anyhow::Result<()> {
     debug!("初期画面描画開始");
     terminal.draw(|f| ui::render(f, &mut app))?;
     debug!("初期画面描画完了");
+    let mut state_dump = cli.dump_state.clone().map(|path| (path, String::new()));
+    if let Some((path, last_written)) = &mut state_dump {
+        dump_state(path, &app, last_written);
+    }
 
     debug!("メインループに入ります");
     let result = async {
         loop {
             tokio::select! {
                 _ = refresh_interval.tick() => {
-                    if !pending_logs.is_empty() {
-                        let logs = parse_logs_from_lines(&parser, &pending_logs);
+                    // 時計や取り込み速度は入力がなくても変わるので定期的に描き直す
+                    if app.has_live_status() {
+                        should_redraw = true;
+                    }
+                    app.record_raw_samples(pending_logs.iter().map(|line: &SourceLine| line.line.as_str()));
+                    // 行が届かなかったティックでも、保留中のエントリを解放するために呼ぶ
+                    let logs = assembler.push(&parser, &pending_logs, std::time::Instant::now());
+                    pending_logs.clear();
+                    if !logs.is_empty() {
                         app.add_logs(logs);
-                        pending_logs.clear();
+                        should_redraw = true;
+                    }
+                    if !format_checked
+                        && let Some(sampled) = parser.format_mismatch(false)
+                    {
+                        app.show_format_warning(sampled);
+                        format_checked = true;
+                        should_redraw = true;
+                    }
+                    if report_malformed_records(&parser, &mut app, &mut reported_malformed) {
                         should_redraw = true;
                     }
                 }
@@ -164,13 +491,101 @@ async fn main() -> anyhow::Result<()> {
                         pending_logs.push(line);
                     }
                 }
+
+                message = error_receiver.recv() => {
+                    if let Some(message) = message {
+                        app.show_error(message);
+                        should_redraw = true;
+                    }
+                }
+
+                fallback = fallback_receiver.recv() => {
+                    if let Some(fallback) = fallback {
+                        app.note_polling_fallback(fallback.path, &fallback.reason);
+                        should_redraw = true;
+                    }
+                }
                 
                 event = event_stream.next() => {
                     if let Some(Ok(event)) = event {
-                        let needs_redraw = handle_events(&event, &mut app, &clipboard_holder, terminal.size()?)?;
+                        let needs_redraw = handle_events(&event, &mut app, &mut system_clipboard, &error_sender)?;
                         if needs_redraw {
                             should_redraw = true;
                         }
+
+                        if let Some(pattern) = app.pattern_request.take() {
+                            match parser.set_pattern(&pattern) {
+                                Ok(()) => {
+                                    app.status_message = Some("Applied the new pattern to incoming lines".to_string());
+                                    // ファイル入力は新しいパターンで読み直す
+                                    app.reload_requested = !cli.input.is_empty();
+                                }
+                                Err(e) => app.show_error(format!("Failed to apply pattern: {}", e)),
+                            }
+                        }
+
+                        if let Some(input_file) = app.open_file_request.take() {
+                            match load_input_file(&parser, &input_file, &mut terminal, &mut app) {
+                                Ok((logs, start)) => {
+                                    app.update_logs(logs);
+                                    app.remember_file(&input_file);
+                                    watch_tasks.push(spawn_watch(&input_file, start, poll_interval, &log_sender, &error_sender, &fallback_sender, &cancellation_token));
+                                    cli.input.push(input_file);
+                                    app.input_label = input_label(&cli, reads_stdin);
+                                    app.launch_args = launch_args(&cli);
+                                    if !format_checked
+                                        && let Some(sampled) = parser.format_mismatch(true)
+                                    {
+                                        app.show_format_warning(sampled);
+                                        format_checked = true;
+                                    }
+                                    report_malformed_records(&parser, &mut app, &mut reported_malformed);
+                                }
+                                Err(e) => {
+                                    app.loading = None;
+                                    app.show_error(format!("Failed to open {}: {}", input_file, e));
+                                    app.open_recent_files();
+                                }
+                            }
+                            should_redraw = true;
+                        }
+
+                        if std::mem::take(&mut app.reload_requested) {
+                            if cli.input.is_empty() {
+                                app.status_message = Some("Nothing to reload (no --input file)".to_string());
+                            }
+                            let mut reloaded = 0;
+                            for (index, input_file) in cli.input.iter().enumerate() {
+                                match load_input_file(&parser, input_file, &mut terminal, &mut app) {
+                                    Ok((logs, start)) => {
+                                        // 監視は読み直せたファイルだけ差し替え、古い監視タスクから届いた未処理の行は破棄する
+                                        let (token, handle) = &watch_tasks[index];
+                                        token.cancel();
+                                        handle.abort();
+                                        pending_logs.retain(|line: &SourceLine| &*line.source != input_file.as_str());
+                                        assembler.forget(input_file);
+                                        app.reload_source(input_file, logs);
+                                        watch_tasks[index] = spawn_watch(input_file, start, poll_interval, &log_sender, &error_sender, &fallback_sender, &cancellation_token);
+                                        reloaded += 1;
+                                    }
+                                    Err(e) => {
+                                        app.loading = None;
+                                        app.show_error(format!("Failed to reload {} (still following it): {}", input_file, e));
+                                    }
+                                }
+                            }
+                            if reloaded > 0 {
+                                let mut message = match cli.input.as_slice() {
+                                    [input_file] => format!("Reloaded {}", input_file),
+                                    _ => format!("Reloaded {} of {} files", reloaded, cli.input.len()),
+                                };
+                                if app.frozen {
+                                    message.push_str(" (shown when thawed)");
+                                }
+                                app.status_message = Some(message);
+                            }
+                            should_redraw = true;
+                        }
                         
                         // 画面サイズ変更イベントを検出（即座に再描画）
                         if matches!(event, Event::Resize(_, _)) {
@@ -195,6 +610,10 @@ async fn main() -> anyhow::Result<()> {
                 }
                 should_redraw = false;
                 last_redraw_time = std::time::Instant::now();
+                // 描画した内容と同じ状態を書き出す
+                if let Some((path, last_written)) = &mut state_dump {
+                    dump_state(path, &app, last_written);
+                }
             }
 
             if app.should_quit {
@@ -206,18 +625,18 @@ async fn main() -> anyhow::Result<()> {
 
     // Cancel all background tasks
     cancellation_token.cancel();
+    for (_, handle) in watch_tasks {
+        handle.abort();
+    }
     for task in background_tasks {
         task.abort();
     }
 
     // Always perform cleanup, regardless of how we exited
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    drop(terminal_guard);
+
+    // 端末を元に戻した後にセッションの概要を標準エラー出力に残す
+    eprintln!("tracing-viewer: {}", session_summary(&app.logs, parser.dropped_lines(), &app.severity));
 
     // Force exit to ensure process terminates
     match result {
@@ -229,291 +648,285 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
-fn handle_events(event: &Event, app: &mut App, clipboard_holder: &Arc<Mutex<Option<Clipboard>>>, terminal_size: ratatui::layout::Size) -> anyhow::Result<bool> {
+/// Every input being followed, as named in the header
+fn input_label(cli: &Cli, reads_stdin: bool) -> String {
+    let mut inputs = cli.input.clone();
+    inputs.extend(cli.url.clone());
+    inputs.extend(cli.listen.as_ref().map(|address| format!("tcp:{}", address)));
+    inputs.extend(cli.journald.as_ref().map(|unit| format!("journald:{}", unit)));
+    if reads_stdin {
+        inputs.push(STDIN_SOURCE.to_string());
+    }
+    inputs.join(", ")
+}
+
+fn parse_goto(text: &str) -> Result<EntryAnchor, String> {
+    tracing_viewer::export::parse_entry_anchor(text).ok_or_else(|| format!("`{}` isn't an entry anchor such as app.log:120 or e42", text))
+}
+
+/// Show an error banner when `--format entries-json` skipped records since the last
+/// report. Returns whether it did.
+fn report_malformed_records(parser: &LogParser, app: &mut App, reported: &mut usize) -> bool {
+    let (count, first) = parser.malformed_records();
+    if count <= *reported {
+        return false;
+    }
+    *reported = count;
+    app.show_error(format!("Skipped {} malformed entry records (first at {})", count, first.unwrap_or_default()));
+    true
+}
+
+/// Apply the filters given on the command line (levels, modules, field, grep) to `app`
+fn apply_startup_filters(app: &mut App, cli: &Cli) {
+    app.grep_query = cli.grep.clone().filter(|query| !query.is_empty());
+    app.grep_case_sensitive = cli.grep_case_sensitive;
+    if !cli.show_levels.is_empty() {
+        app.set_shown_levels(&cli.show_levels);
+    }
+    app.pending_hidden_modules = cli.hide_module.clone();
+    app.set_required_field(cli.has_field.as_deref().unwrap_or_default());
+}
+
+/// `--export-html`/`--export-text`: read the inputs to the end, filter them as the viewer
+/// would at startup and write the report
+fn export_entries(cli: &Cli, parser: &LogParser, severity: SeverityOrder, path: &str) -> anyhow::Result<()> {
+    let html = cli.export_html.is_some();
+    let mut logs = Vec::new();
+    let mut read = |source: &str, content: &str| {
+        let mut entries = parse_logs_from_content(parser, content, LOAD_CHUNK_BYTES, |_, _| {});
+        for entry in &mut entries {
+            entry.source = source.to_string();
+        }
+        logs.push(entries);
+    };
+    if cli.input.is_empty() {
+        if io::stdin().is_terminal() {
+            anyhow::bail!("--export-{} needs --input files or logs piped to standard input", if html { "html" } else { "text" });
+        }
+        read(STDIN_SOURCE, &io::read_to_string(io::stdin())?);
+    } else {
+        for input in &cli.input {
+            read(input, &std::fs::read_to_string(input)?);
+        }
+    }
+
+    let mut app = App::new();
+    app.input_label = input_label(cli, cli.input.is_empty());
+    app.copy_template = EntryTemplate::parse(&cli.copy_format)?;
+    app.export_notes = cli.export_notes;
+    if let Some(path) = SavedNotes::default_path() {
+        app.saved_notes = SavedNotes::load(&path);
+    }
+    app.set_severity_order(severity);
+    apply_startup_filters(&mut app, cli);
+    app.update_logs(merge_by_timestamp(logs));
+    let report = if html {
+        tracing_viewer::export::html_report(&app.filtered_logs, &app.report_title(), app.exported_notes())
+    } else {
+        tracing_viewer::export::text_report(&app.filtered_logs, &app.copy_template, app.exported_notes())
+    };
+    std::fs::write(path, report)?;
+    if let (count @ 1.., Some(first)) = parser.malformed_records() {
+        eprintln!("Skipped {} malformed entry records (first at {})", count, first);
+    }
+    println!("Wrote {} of {} entries to {}", app.filtered_logs.len(), app.logs.len(), path);
+    Ok(())
+}
+
+/// Parse the command line, then fill in options it left out from the config file, which
+/// is returned for its other settings. The default config path may be missing; a
+/// `--config` path must exist.
+fn parse_cli_with_config() -> anyhow::Result<(Cli, config::Config)> {
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let matches = Cli::command().get_matches_from(&args);
+    let explicit_path = matches.get_one::<String>("config").map(std::path::PathBuf::from);
+    let Some(path) = explicit_path.clone().or_else(config::default_path) else {
+        return Ok((Cli::from_arg_matches(&matches)?, config::Config::default()));
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if explicit_path.is_none() && e.kind() == io::ErrorKind::NotFound => {
+            return Ok((Cli::from_arg_matches(&matches)?, config::Config::default()));
+        }
+        Err(e) => anyhow::bail!("Failed to read config {}: {}", path.display(), e),
+    };
+    let config_error = |e: anyhow::Error| anyhow::anyhow!("Invalid config {}: {}", path.display(), e);
+    let config = config::parse(&text).map_err(config_error)?;
+    let config_args = config::to_args(&config.options, &Cli::command(), &matches).map_err(config_error)?;
+
+    // 設定ファイルの値を先に並べ、コマンドラインで指定されたものと重ならないようにしてある
+    let mut merged = args[..1].to_vec();
+    merged.extend(config_args.into_iter().map(std::ffi::OsString::from));
+    merged.extend(args[1..].iter().cloned());
+    let cli = Cli::try_parse_from(merged).map_err(|e| {
+        // clapのエラーは使い方の案内まで含むので、最初の行だけを使う
+        let message = e.to_string();
+        config_error(anyhow::anyhow!("{}", message.lines().next().unwrap_or_default().trim_start_matches("error: ")))
+    })?;
+    Ok((cli, config))
+}
+
+/// Arguments that reopen the same inputs parsed the same way, for `App::view_command`.
+/// Display options are left out; `--format` is only given when it isn't auto-detected.
+fn launch_args(cli: &Cli) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut flag = |name: &str, value: &str| args.extend([name.to_string(), value.to_string()]);
+    for input in &cli.input {
+        flag("--input", input);
+    }
+    if let Some(url) = &cli.url {
+        flag("--url", url);
+    }
+    if let Some(address) = &cli.listen {
+        flag("--listen", address);
+    }
+    if let Some(unit) = &cli.journald {
+        flag("--journald", unit);
+    }
+    if cli.format != LogFormat::Auto
+        && let Some(value) = cli.format.to_possible_value()
+    {
+        flag("--format", value.get_name());
+    }
+    for pattern in &cli.log_regex {
+        flag("--log-regex", pattern);
+    }
+    if cli.target_chars != DEFAULT_TARGET_CHARS {
+        flag("--target-chars", &cli.target_chars);
+    }
+    if !cli.message_field.is_empty() {
+        flag("--message-field", &cli.message_field.join(","));
+    }
+    if cli.levels.is_empty() {
+        if cli.level_preset != SeverityPreset::Tracing
+            && let Some(value) = cli.level_preset.to_possible_value()
+        {
+            flag("--level-preset", value.get_name());
+        }
+    } else {
+        flag("--levels", &cli.levels.join(","));
+    }
+    for (enabled, name) in [
+        (cli.level_optional, "--level-optional"),
+        (cli.keep_blank_lines, "--keep-blank-lines"),
+        (cli.no_multiline, "--no-multiline"),
+        (cli.split_fields, "--split-fields"),
+    ] {
+        if enabled {
+            args.push(name.to_string());
+        }
+    }
+    args
+}
+
+/// Write `app.view_state_json()` to `path` if it changed since the last write, through a
+/// temporary file so readers never see a partial snapshot
+fn dump_state(path: &str, app: &App, last_written: &mut String) {
+    let state = app.view_state_json();
+    if state == *last_written {
+        return;
+    }
+    let temporary = format!("{}.tmp", path);
+    match std::fs::write(&temporary, &state).and_then(|_| std::fs::rename(&temporary, path)) {
+        Ok(()) => *last_written = state,
+        Err(e) => error!("状態の書き出しエラー: {}: {}", path, e),
+    }
+}
+
+/// The `--format` value, noting custom `--log-regex` patterns, as named in the header
+fn format_label(cli: &Cli) -> String {
+    let format = cli.format.to_possible_value().map_or_else(String::new, |value| value.get_name().to_string());
+    match cli.log_regex.len() {
+        0 => format,
+        1 => format!("{} + custom regex", format),
+        count => format!("{} + {} custom regexes", format, count),
+    }
+}
+
+/// Leave raw mode, the alternate screen and mouse capture, ignoring errors so it can run
+/// from the panic hook and on early returns alike
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show);
+}
+
+/// Restores the terminal when dropped, so errors returned with `?` after raw mode was
+/// enabled don't leave the shell in raw mode with mouse capture on
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Lines sampled by `--detect`
+const DETECT_SAMPLE_LINES: usize = 200;
+
+/// Print how many sampled input lines each candidate format parses and the best pick
+fn detect_format(cli: &Cli) -> anyhow::Result<()> {
+    let (name, content) = match cli.input.first() {
+        Some(path) => (path.clone(), std::fs::read_to_string(path)?),
+        None => {
+            let mut content = String::new();
+            for line in io::stdin().lock().lines().take(DETECT_SAMPLE_LINES) {
+                content.push_str(&line?);
+                content.push('\n');
+            }
+            (STDIN_SOURCE.to_string(), content)
+        }
+    };
+    let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).take(DETECT_SAMPLE_LINES).collect();
+    if lines.is_empty() {
+        anyhow::bail!("No input lines to detect the format from");
+    }
+
+    let matches = tracing_viewer::log_parser::detect_formats(&lines, &cli.log_regex)?;
+    println!("Sampled {} lines from {}", lines.len(), name);
+    for candidate in &matches {
+        println!("  {:>5}/{}  {}", candidate.matched, lines.len(), candidate.args);
+    }
+    // 同数の場合は後ろの（より限定的な）候補を選ぶ
+    match matches.iter().filter(|candidate| candidate.matched > 0).max_by_key(|candidate| candidate.matched) {
+        Some(best) => println!("Best match: {}", best.args),
+        None => println!("No known format matched; try --log-regex with a custom pattern"),
+    }
+    Ok(())
+}
+
+fn handle_events(
+    event: &Event,
+    app: &mut App,
+    system_clipboard: &mut SystemClipboard,
+    error_sender: &mpsc::UnboundedSender<String>,
+) -> anyhow::Result<bool> {
     match event {
         Event::Key(key) => {
-            if key.kind == KeyEventKind::Press {
-                match app.mode {
-                    AppMode::ModuleSelection => {
-                        match key.code {
-                            KeyCode::Char('q') => {
-                                app.quit();
-                            }
-                            KeyCode::Char(' ') | KeyCode::Enter => {
-                                app.toggle_selected_module();
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                // モジュールリストの最下部にいる場合、ログレベル選択に移動
-                                if !app.module_items.is_empty() && app.module_list_state.selected().unwrap_or(0) == app.module_items.len().saturating_sub(1) {
-                                    app.switch_to_log_level_mode();
-                                } else {
-                                    app.next_module();
-                                }
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                // モジュールリストの最上部にいる場合、ログレベル選択の最下部に移動
-                                if app.module_list_state.selected().unwrap_or(0) == 0 {
-                                    app.switch_to_log_level_mode();
-                                    // ログレベルリストの最下部に移動
-                                    if !app.available_log_levels.is_empty() {
-                                        app.selected_log_level_index = app.available_log_levels.len() - 1;
-                                    }
-                                } else {
-                                    app.previous_module();
-                                }
-                            }
-                            KeyCode::Tab => {
-                                app.switch_to_log_mode();
-                            }
-                            KeyCode::Char('r') => {
-                                app.filter_logs();
-                            }
-                            KeyCode::Char('1') => {
-                                app.toggle_log_level("ERROR");
-                            }
-                            KeyCode::Char('2') => {
-                                app.toggle_log_level("WARN");
-                            }
-                            KeyCode::Char('3') => {
-                                app.toggle_log_level("INFO");
-                            }
-                            KeyCode::Char('4') => {
-                                app.toggle_log_level("DEBUG");
-                            }
-                            KeyCode::Char('5') => {
-                                app.toggle_log_level("TRACE");
-                            }
-                            KeyCode::Char('a') => {
-                                app.select_all_modules();
-                            }
-                            KeyCode::Char('n') => {
-                                app.deselect_all_modules();
-                            }
-                            KeyCode::Char(',') => {
-                                app.decrease_panel_width();
-                            }
-                            KeyCode::Char('.') => {
-                                app.increase_panel_width();
-                            }
-                            _ => {}
-                        }
-                    }
-                    AppMode::LogNavigation => {
-                        match key.code {
-                            KeyCode::Char('q') => {
-                                app.quit();
-                            }
-                            KeyCode::Tab => {
-                                if app.show_filter_panel {
-                                    app.switch_to_module_mode();
-                                } else {
-                                    app.show_filter_panel = true;
-                                    app.switch_to_module_mode();
-                                }
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                app.next_log_entry();
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                app.previous_log_entry();
-                            }
-                            KeyCode::PageUp => {
-                                let visible_lines = terminal_size.height.saturating_sub(3) as usize; // Account for status bars
-                                app.page_up(visible_lines);
-                            }
-                            KeyCode::PageDown => {
-                                let visible_lines = terminal_size.height.saturating_sub(3) as usize; // Account for status bars
-                                app.page_down(visible_lines);
-                            }
-                            KeyCode::Char('v') => {
-                                app.start_text_selection();
-                            }
-                            KeyCode::Esc => {
-                                app.scroll_to_bottom();
-                                app.switch_to_module_mode();
-                            }
-                            KeyCode::Char('c') => {
-                                app.clear_copy_message();
-                            }
-                            KeyCode::Char('1') => {
-                                app.toggle_log_level("ERROR");
-                            }
-                            KeyCode::Char('2') => {
-                                app.toggle_log_level("WARN");
-                            }
-                            KeyCode::Char('3') => {
-                                app.toggle_log_level("INFO");
-                            }
-                            KeyCode::Char('4') => {
-                                app.toggle_log_level("DEBUG");
-                            }
-                            KeyCode::Char('5') => {
-                                app.toggle_log_level("TRACE");
-                            }
-                            _ => {}
-                        }
-                    }
-                    AppMode::TextSelection => {
-                        match key.code {
-                            KeyCode::Char('q') => {
-                                app.quit();
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                app.next_log_entry();
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                app.previous_log_entry();
-                            }
-                            KeyCode::PageUp => {
-                                let visible_lines = terminal_size.height.saturating_sub(3) as usize; // Account for status bars
-                                app.page_up(visible_lines);
-                            }
-                            KeyCode::PageDown => {
-                                let visible_lines = terminal_size.height.saturating_sub(3) as usize; // Account for status bars
-                                app.page_down(visible_lines);
-                            }
-                            KeyCode::Char('y') => {
-                                let selected_text = app.copy_selected_logs()?;
-                                if !selected_text.is_empty() {
-                                    // まずarboardで試行
-                                    let mut arboard_success = false;
-                                    if let Ok(mut clipboard) = Clipboard::new() {
-                                        if clipboard.set_text(&selected_text).is_ok() {
-                                            arboard_success = true;
-                                            // クリップボードオブジェクトを保持
-                                            let holder_clone = clipboard_holder.clone();
-                                            let _text_clone = selected_text.clone();
-                                            tokio::spawn(async move {
-                                                if let Ok(mut holder) = holder_clone.lock() {
-                                                    *holder = Some(clipboard);
-                                                }
-                                                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                                            });
-                                        }
-                                    }
-                                
-                                    // arboardが失敗した場合やLinux環境での代替手段
-                                    if !arboard_success {
-                                        // xclipまたはwl-clipboardを試行
-                                        let text_clone = selected_text.clone();
-                                        tokio::spawn(async move {
-                                            // xclip (X11) を試行
-                                            if let Ok(mut child) = Command::new("xclip")
-                                                .arg("-selection")
-                                                .arg("clipboard")
-                                                .stdin(std::process::Stdio::piped())
-                                                .spawn() {
-                                                if let Some(stdin) = child.stdin.as_mut() {
-                                                    use std::io::Write;
-                                                    let _ = stdin.write_all(text_clone.as_bytes());
-                                                }
-                                                let _ = child.wait();
-                                            }
-                                            // wl-clipboard (Wayland) も試行
-                                            else if let Ok(mut child) = Command::new("wl-copy")
-                                                .stdin(std::process::Stdio::piped())
-                                                .spawn() {
-                                                if let Some(stdin) = child.stdin.as_mut() {
-                                                    use std::io::Write;
-                                                    let _ = stdin.write_all(text_clone.as_bytes());
-                                                }
-                                                let _ = child.wait();
-                                            }
-                                        });
-                                    }
-                                }
-                                app.clear_selection();
-                            }
-                            KeyCode::Esc => {
-                                app.clear_selection();
-                            }
-                            KeyCode::Char('c') => {
-                                app.clear_copy_message();
-                                return Ok(true);
-                            }
-                            _ => return Ok(false),
-                        }
-                    }
-                    AppMode::LogLevelFilter => {
-                        match key.code {
-                            KeyCode::Char('q') => {
-                                app.quit();
-                            }
-                            KeyCode::Tab => {
-                                app.switch_to_log_mode();
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                // ログレベルリストの最下部にいる場合、モジュール選択の最上部に移動
-                                if !app.available_log_levels.is_empty() && app.selected_log_level_index == app.available_log_levels.len().saturating_sub(1) {
-                                    app.switch_to_module_mode();
-                                    // モジュールリストの最上部に移動
-                                    app.module_list_state.select(Some(0));
-                                } else {
-                                    app.next_log_level();
-                                }
-                            }
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                // ログレベルリストの最上部にいる場合、モジュール選択に移動
-                                if app.selected_log_level_index == 0 {
-                                    app.switch_to_module_mode();
-                                    // モジュールリストの最下部に移動
-                                    if !app.module_items.is_empty() {
-                                        app.module_list_state.select(Some(app.module_items.len() - 1));
-                                    }
-                                } else {
-                                    app.previous_log_level();
-                                }
-                            }
-                            KeyCode::Char(' ') | KeyCode::Enter => {
-                                app.toggle_selected_log_level();
-                            }
-                            KeyCode::Char('1') => {
-                                app.toggle_log_level("ERROR");
-                            }
-                            KeyCode::Char('2') => {
-                                app.toggle_log_level("WARN");
-                            }
-                            KeyCode::Char('3') => {
-                                app.toggle_log_level("INFO");
-                            }
-                            KeyCode::Char('4') => {
-                                app.toggle_log_level("DEBUG");
-                            }
-                            KeyCode::Char('5') => {
-                                app.toggle_log_level("TRACE");
-                            }
-                            KeyCode::Char(',') => {
-                                app.decrease_panel_width();
-                            }
-                            KeyCode::Char('.') => {
-                                app.increase_panel_width();
-                            }
-                            _ => return Ok(false),
-                        }
-                    }
-                }
+            // Windowsでは押下と解放の両方が届くので押下だけを扱う
+            if key.kind != KeyEventKind::Press {
+                return Ok(false);
             }
-            Ok(true)
-        }
-        Event::Mouse(mouse) => {
-            match mouse.kind {
-                MouseEventKind::ScrollUp => {
-                    // マウススクロールアップ（上に3行スクロール）
-                    if app.mode == AppMode::LogNavigation || app.mode == AppMode::TextSelection {
-                        app.scroll_up(3);
-                    }
-                    Ok(true)
-                }
-                MouseEventKind::ScrollDown => {
-                    // マウススクロールダウン（下に3行スクロール）
-                    if app.mode == AppMode::LogNavigation || app.mode == AppMode::TextSelection {
-                        app.scroll_down(3);
-                    }
-                    Ok(true)
-                }
-                _ => Ok(false),
+            // rawモードではCtrl-Cはシグナルにならずキー入力として届く（Windowsも同様）
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                app.should_quit = true;
+                return Ok(true);
+            }
+            let handled = if key.modifiers.contains(KeyModifiers::CONTROL) {
+                app.on_control_key(key.code)
+            } else {
+                app.on_key(key.code)
+            };
+            // クリップボードやファイルへの書き込みはI/Oを伴うのでここで行う
+            if let Some(text) = app.clipboard_request.take() {
+                copy_to_clipboard(text, system_clipboard, error_sender);
             }
+            if let Some(export) = app.export_request.take() {
+                let result = std::fs::write(&export.path, &export.content);
+                app.finish_export(&export, result);
+            }
+            Ok(handled)
         }
+        Event::Mouse(mouse) => Ok(app.on_mouse(mouse.kind, mouse.column, mouse.row)),
         Event::Resize(_width, _height) => {
             // 画面サイズ変更時は常に再描画
             Ok(true)
@@ -522,89 +935,243 @@ fn handle_events(event: &Event, app: &mut App, clipboard_holder: &Arc<Mutex<Opti
     }
 }
 
-fn parse_logs_from_content(parser: &LogParser, content: &str) -> Vec<LogEntry> {
-    parser.parse_multiline_logs(content)
+/// Copy through arboard, falling back to the platform's clipboard commands in the
+/// background and reporting through `error_sender` when nothing worked
+fn copy_to_clipboard(text: String, system_clipboard: &mut SystemClipboard, error_sender: &mpsc::UnboundedSender<String>) {
+    let arboard_error = match system_clipboard.set_text(&text) {
+        Ok(()) => return,
+        Err(e) => e,
+    };
+    debug!("arboardでのコピーに失敗: {}", arboard_error);
+    let error_sender = error_sender.clone();
+    // 外部コマンドの終了を待つのでブロッキング用のスレッドで実行する
+    tokio::task::spawn_blocking(move || match clipboard::copy_with_fallbacks(&text, std::env::consts::OS) {
+        Ok(program) => debug!("{}でコピーしました", program),
+        Err(e) => {
+            let _ = error_sender.send(format!("Copy failed: {}; {}", arboard_error, e));
+        }
+    });
 }
 
-fn parse_logs_from_lines(parser: &LogParser, lines: &[String]) -> Vec<LogEntry> {
-    let content = lines.join("\n");
-    parser.parse_multiline_logs(&content)
+/// Read and parse `path` from scratch, showing progress over the current screen.
+/// Returns the entries and the position the watcher should continue from.
+fn load_input_file(
+    parser: &LogParser,
+    path: &str,
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut App,
+) -> anyhow::Result<(Vec<LogEntry>, FilePosition)> {
+    // 大きなファイルでも固まって見えないよう進捗を表示する
+    app.loading = Some(format!("Loading {}…", path));
+    terminal.draw(|f| ui::render(f, app))?;
+    let file_content = std::fs::read_to_string(path)?;
+    app.record_raw_samples(file_content.lines());
+    let mut logs = parse_logs_from_content(parser, &file_content, LOAD_CHUNK_BYTES, |parsed, entries| {
+        let percent = parsed * 100 / file_content.len().max(1);
+        app.loading = Some(format!("Loading {}… {}% ({} entries)", path, percent, entries));
+        let _ = terminal.draw(|f| ui::render(f, app));
+    });
+    app.loading = None;
+    for log in &mut logs {
+        log.source = path.to_string();
+    }
+    // 読み込んだ位置から監視を続ける
+    let start = FilePosition {
+        offset: file_content.len() as u64,
+        line: file_content.lines().count(),
+    };
+    Ok((logs, start))
 }
 
-async fn watch_file(file_path: &str, log_sender: mpsc::UnboundedSender<String>, cancellation_token: CancellationToken) -> anyhow::Result<()> {
-    let path = Path::new(file_path);
-    if !path.exists() {
-        return Err(anyhow::anyhow!("ファイルが存在しません: {}", file_path));
-    }
+/// Spawn a watcher for `path` starting at `start`, cancellable through the returned child token
+fn spawn_watch(
+    path: &str,
+    start: FilePosition,
+    poll_interval: Option<Duration>,
+    log_sender: &mpsc::UnboundedSender<SourceLine>,
+    error_sender: &mpsc::UnboundedSender<String>,
+    fallback_sender: &mpsc::UnboundedSender<PollingFallback>,
+    cancellation_token: &CancellationToken,
+) -> (CancellationToken, tokio::task::JoinHandle<()>) {
+    let path = path.to_string();
+    let log_sender = log_sender.clone();
+    let error_sender = error_sender.clone();
+    let fallback_sender = fallback_sender.clone();
+    let token = cancellation_token.child_token();
+    let task_token = token.clone();
+    let handle = tokio::spawn(async move {
+        debug!("watch_file task started");
+        if let Err(e) = watch_file(&path, start, poll_interval, log_sender, error_sender.clone(), fallback_sender, task_token).await {
+            error!("ファイル監視エラー: {}", e);
+            let _ = error_sender.send(format!("Stopped watching {}: {}", path, e));
+        }
+        debug!("watch_file task ended");
+    });
+    (token, handle)
+}
 
-    debug!("ファイル監視を開始: {}", file_path);
+/// Follow `unit` with `journalctl`, sending each record as a normalized JSON line
+async fn stream_journald(
+    unit: &str,
+    severity: &SeverityOrder,
+    log_sender: mpsc::UnboundedSender<SourceLine>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    use tokio::io::AsyncBufReadExt;
 
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-    let mut watcher = RecommendedWatcher::new(
-        move |res: Result<NotifyEvent, notify::Error>| {
-            if let Ok(event) = res {
-                debug!("ファイルイベント受信: {:?}", event);
-                let _ = tx.send(event);
-            } else if let Err(e) = res {
-                error!("ファイル監視エラー: {}", e);
-            }
-        },
-        Config::default(),
-    )?;
-    
-    watcher.watch(path, RecursiveMode::NonRecursive)?;
-    
-    let mut file = File::open(path)?;
-    let mut last_size = file.metadata()?.len();
-    file.seek(SeekFrom::End(0))?;
-    debug!("初期ファイルサイズ: {} bytes", last_size);
+    let mut child = tokio::process::Command::new("journalctl")
+        .args(journald::journalctl_args(unit))
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("failed to run journalctl: {}", e))?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("journalctl has no stdout"))?;
+    let mut lines = tokio::io::BufReader::new(stdout).lines();
+    let source: Arc<str> = Arc::from(format!("journald:{}", unit));
 
     loop {
         tokio::select! {
-            _ = cancellation_token.cancelled() => {
-                debug!("ファイル監視がキャンセルされました");
-                break;
+            _ = cancellation_token.cancelled() => return Ok(()),
+            line = lines.next_line() => match line? {
+                Some(line) => {
+                    // レコードでない行（"-- No entries --"など）は読み飛ばす
+                    if let Some(line) = journald::normalize_record(&line, severity)
+                        && log_sender.send(SourceLine { source: source.clone(), line, position: None }).is_err()
+                    {
+                        return Ok(());
+                    }
+                }
+                None => {
+                    let status = child.wait().await?;
+                    anyhow::bail!("journalctl exited ({})", status);
+                }
             }
-            event = rx.recv() => {
-                match event {
-                    Some(event) => {
-                        debug!("イベント処理中: {:?}", event.kind);
-                        if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
-                            let mut file = File::open(path)?;
-                            let current_size = file.metadata()?.len();
-                            debug!("現在のファイルサイズ: {} bytes (前回: {} bytes)", current_size, last_size);
-                            
-                            if current_size > last_size {
-                                file.seek(SeekFrom::Start(last_size))?;
-                                let mut new_content = String::new();
-                                file.read_to_string(&mut new_content)?;
-                                debug!("新しいコンテンツ読み込み: {} bytes", new_content.len());
-                                
-                                for line in new_content.lines() {
-                                    if !line.trim().is_empty() {
-                                        if log_sender.send(line.to_string()).is_err() {
-                                            debug!("ログ送信失敗、監視を終了");
-                                            return Ok(());
-                                        }
+        }
+    }
+}
+
+/// Stream lines from an HTTP endpoint into `log_sender`, reconnecting with backoff
+/// whenever the connection fails or the body ends.
+async fn stream_url(url: &str, log_sender: mpsc::UnboundedSender<SourceLine>, cancellation_token: CancellationToken) {
+    const MAX_BACKOFF: Duration = Duration::from_secs(30);
+    let client = reqwest::Client::new();
+    let source: Arc<str> = Arc::from(url);
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        match client.get(url).send().await.and_then(|response| response.error_for_status()) {
+            Ok(response) => {
+                debug!("HTTPストリームに接続しました: {}", url);
+                backoff = Duration::from_secs(1);
+                let mut body = response.bytes_stream();
+                let mut buffer: Vec<u8> = Vec::new();
+
+                loop {
+                    tokio::select! {
+                        _ = cancellation_token.cancelled() => return,
+                        chunk = body.next() => match chunk {
+                            Some(Ok(bytes)) => {
+                                buffer.extend_from_slice(&bytes);
+                                // 改行までそろった行だけを送信し、残りは次のチャンクを待つ
+                                while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+                                    let raw: Vec<u8> = buffer.drain(..=newline).collect();
+                                    let line = String::from_utf8_lossy(&raw).trim_end_matches(['\n', '\r']).to_string();
+                                    // 空行も送り、続きの行として扱うかどうかはパーサーに任せる
+                                    if log_sender.send(SourceLine { source: source.clone(), line, position: None }).is_err() {
+                                        return;
                                     }
                                 }
-                                last_size = current_size;
-                            } else if current_size < last_size {
-                                // ファイルが縮小された場合（ローテーションなど）
-                                debug!("ファイルが縮小されました。リセット中...");
-                                last_size = 0;
-                                file.seek(SeekFrom::Start(0))?;
+                            }
+                            Some(Err(e)) => {
+                                error!("HTTPストリームの読み込みエラー: {}", e);
+                                break;
+                            }
+                            None => {
+                                debug!("HTTPストリームが終了しました");
+                                break;
                             }
                         }
                     }
-                    None => {
-                        debug!("ファイル監視チャンネルがクローズされました");
-                        break;
-                    }
+                }
+
+                // 改行で終わっていない最後の行
+                let line = String::from_utf8_lossy(&buffer).trim_end_matches(['\n', '\r']).to_string();
+                if !buffer.is_empty() && log_sender.send(SourceLine { source: source.clone(), line, position: None }).is_err() {
+                    return;
                 }
             }
+            Err(e) => {
+                error!("HTTPストリームへの接続エラー: {}", e);
+            }
+        }
+
+        debug!("{:?} 後に再接続します", backoff);
+        tokio::select! {
+            _ = cancellation_token.cancelled() => return,
+            _ = tokio::time::sleep(backoff) => {}
         }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Accept TCP connections on `listener` and stream each one's lines into `log_sender`,
+/// tagging them with the peer address as the source.
+async fn accept_tcp(listener: tokio::net::TcpListener, log_sender: mpsc::UnboundedSender<SourceLine>, cancellation_token: CancellationToken) {
+    use tokio::io::AsyncBufReadExt;
+    const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+    const MAX_BACKOFF: Duration = Duration::from_secs(5);
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = cancellation_token.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => {
+                    backoff = INITIAL_BACKOFF;
+                    accepted
+                }
+                Err(e) => {
+                    // ファイル記述子の枯渇などは続けて失敗するので、間隔を空けてから再試行する
+                    error!("TCP接続の受け付けエラー: {}", e);
+                    tokio::select! {
+                        _ = cancellation_token.cancelled() => return,
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+            }
+        };
+        debug!("TCP接続を受け付けました: {}", peer);
+        let log_sender = log_sender.clone();
+        let cancellation_token = cancellation_token.clone();
+        // 接続ごとにタスクを分け、複数のクライアントから同時に受け取れるようにする
+        tokio::spawn(async move {
+            let source: Arc<str> = Arc::from(format!("tcp:{}", peer));
+            let mut reader = tokio::io::BufReader::new(stream);
+            let mut buffer: Vec<u8> = Vec::new();
+            loop {
+                buffer.clear();
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => return,
+                    read = reader.read_until(b'\n', &mut buffer) => match read {
+                        Ok(0) => break,
+                        Ok(_) => {
+                            // 不正なUTF-8で接続を切らないよう、置換文字にして送る
+                            let line = String::from_utf8_lossy(&buffer).trim_end_matches(['\n', '\r']).to_string();
+                            if log_sender.send(SourceLine { source: source.clone(), line, position: None }).is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            error!("TCP接続の読み込みエラー: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+            debug!("TCP接続が終了しました: {}", peer);
+        });
     }
-    
-    Ok(())
 }
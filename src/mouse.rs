@@ -0,0 +1,197 @@
+//! Mouse handling for `App`: which action each button and wheel direction triggers,
+//! configurable with `--mouse BUTTON=ACTION`. Like key handling it stays free of terminal
+//! I/O; `main` passes the crossterm event kind to `App::on_mouse`.
+
+use crate::ui::{App, AppMode};
+use crossterm::event::{MouseButton, MouseEventKind};
+
+/// A mouse button press or wheel direction that can be bound to an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseTrigger {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+impl MouseTrigger {
+    const ALL: [MouseTrigger; 5] = [MouseTrigger::Left, MouseTrigger::Middle, MouseTrigger::Right, MouseTrigger::WheelUp, MouseTrigger::WheelDown];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MouseTrigger::Left => "left",
+            MouseTrigger::Middle => "middle",
+            MouseTrigger::Right => "right",
+            MouseTrigger::WheelUp => "wheel-up",
+            MouseTrigger::WheelDown => "wheel-down",
+        }
+    }
+
+    fn from_event(kind: MouseEventKind) -> Option<Self> {
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => Some(MouseTrigger::Left),
+            MouseEventKind::Down(MouseButton::Middle) => Some(MouseTrigger::Middle),
+            MouseEventKind::Down(MouseButton::Right) => Some(MouseTrigger::Right),
+            MouseEventKind::ScrollUp => Some(MouseTrigger::WheelUp),
+            MouseEventKind::ScrollDown => Some(MouseTrigger::WheelDown),
+            _ => None,
+        }
+    }
+}
+
+/// What a mouse trigger does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    /// Ignore the event
+    None,
+    /// Jump to the clicked spot of the minimap
+    Click,
+    /// Scroll the view by `--scroll-step` lines, leaving focus alone
+    ScrollUp,
+    ScrollDown,
+    /// Open or close the details popup of the focused entry
+    Details,
+    /// Open the command palette
+    Palette,
+}
+
+impl MouseAction {
+    const ALL: [MouseAction; 6] =
+        [MouseAction::None, MouseAction::Click, MouseAction::ScrollUp, MouseAction::ScrollDown, MouseAction::Details, MouseAction::Palette];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MouseAction::None => "none",
+            MouseAction::Click => "click",
+            MouseAction::ScrollUp => "scroll-up",
+            MouseAction::ScrollDown => "scroll-down",
+            MouseAction::Details => "details",
+            MouseAction::Palette => "palette",
+        }
+    }
+}
+
+/// Parse a `BUTTON=ACTION` binding such as `middle=none` or `right=palette`
+pub fn parse_binding(text: &str) -> Result<(MouseTrigger, MouseAction), String> {
+    let (trigger, action) = text.split_once('=').ok_or_else(|| format!("`{}` isn't of the form BUTTON=ACTION", text))?;
+    let (trigger, action) = (trigger.trim(), action.trim());
+    let Some(trigger) = MouseTrigger::ALL.into_iter().find(|candidate| candidate.name() == trigger) else {
+        let names: Vec<&str> = MouseTrigger::ALL.iter().map(|candidate| candidate.name()).collect();
+        return Err(format!("unknown mouse button `{}` (expected one of {})", trigger, names.join(", ")));
+    };
+    let Some(action) = MouseAction::ALL.into_iter().find(|candidate| candidate.name() == action) else {
+        let names: Vec<&str> = MouseAction::ALL.iter().map(|candidate| candidate.name()).collect();
+        return Err(format!("unknown mouse action `{}` (expected one of {})", action, names.join(", ")));
+    };
+    Ok((trigger, action))
+}
+
+/// The action bound to each trigger
+#[derive(Debug, Clone, PartialEq)]
+pub struct MouseBindings {
+    bindings: [(MouseTrigger, MouseAction); 5],
+}
+
+impl Default for MouseBindings {
+    fn default() -> Self {
+        Self {
+            bindings: [
+                (MouseTrigger::Left, MouseAction::Click),
+                (MouseTrigger::Middle, MouseAction::None),
+                (MouseTrigger::Right, MouseAction::None),
+                (MouseTrigger::WheelUp, MouseAction::ScrollUp),
+                (MouseTrigger::WheelDown, MouseAction::ScrollDown),
+            ],
+        }
+    }
+}
+
+impl MouseBindings {
+    /// The defaults with `overrides` applied in order
+    pub fn with_overrides(overrides: &[(MouseTrigger, MouseAction)]) -> Self {
+        let mut bindings = Self::default();
+        for &(trigger, action) in overrides {
+            if let Some(binding) = bindings.bindings.iter_mut().find(|(bound, _)| *bound == trigger) {
+                binding.1 = action;
+            }
+        }
+        bindings
+    }
+
+    pub fn action(&self, trigger: MouseTrigger) -> MouseAction {
+        self.bindings.iter().find(|(bound, _)| *bound == trigger).map_or(MouseAction::None, |(_, action)| *action)
+    }
+
+    /// Whether any trigger does something. Without one the viewer leaves mouse capture
+    /// off, so the terminal keeps its own selection and middle-click paste.
+    pub fn captures_mouse(&self) -> bool {
+        self.bindings.iter().any(|(_, action)| *action != MouseAction::None)
+    }
+}
+
+impl App {
+    /// Handle a mouse event at `column`, `row` through `mouse_bindings`. Returns whether
+    /// anything changed.
+    pub fn on_mouse(&mut self, kind: MouseEventKind, column: u16, row: u16) -> bool {
+        let Some(trigger) = MouseTrigger::from_event(kind) else {
+            return false;
+        };
+        let viewing_logs = matches!(self.mode, AppMode::LogNavigation | AppMode::TextSelection);
+        match self.mouse_bindings.action(trigger) {
+            MouseAction::None => false,
+            MouseAction::Click => self.on_click(column, row),
+            // ホイールは元々再描画を要求していたので、何もしない場合もtrueを返す
+            MouseAction::ScrollUp => {
+                if viewing_logs {
+                    self.wheel_up();
+                }
+                true
+            }
+            MouseAction::ScrollDown => {
+                if viewing_logs {
+                    self.wheel_down();
+                }
+                true
+            }
+            MouseAction::Details if self.mode == AppMode::LogNavigation && self.input.is_none() => {
+                self.toggle_details();
+                true
+            }
+            MouseAction::Palette if self.palette.is_none() && self.input.is_none() => {
+                self.open_palette();
+                true
+            }
+            MouseAction::Details | MouseAction::Palette => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_parser::LogEntry;
+
+    #[test]
+    fn test_mouse_bindings_override_defaults() {
+        assert_eq!(parse_binding("middle=none"), Ok((MouseTrigger::Middle, MouseAction::None)));
+        assert_eq!(parse_binding("right = palette"), Ok((MouseTrigger::Right, MouseAction::Palette)));
+        assert!(parse_binding("thumb=none").unwrap_err().contains("left, middle, right, wheel-up, wheel-down"));
+        assert!(parse_binding("left").is_err());
+
+        let mut app = App::new();
+        app.update_logs(vec![LogEntry { level: "INFO".to_string(), ..Default::default() }]);
+        app.switch_to_log_mode();
+        assert!(!app.on_mouse(MouseEventKind::Down(MouseButton::Right), 0, 0));
+
+        app.mouse_bindings = MouseBindings::with_overrides(&[(MouseTrigger::Right, MouseAction::Details), (MouseTrigger::WheelUp, MouseAction::None)]);
+        assert!(app.on_mouse(MouseEventKind::Down(MouseButton::Right), 0, 0));
+        assert!(app.show_details);
+        assert!(!app.on_mouse(MouseEventKind::ScrollUp, 0, 0));
+        assert_eq!(app.mouse_bindings.action(MouseTrigger::WheelDown), MouseAction::ScrollDown);
+
+        let none = [MouseTrigger::Left, MouseTrigger::WheelUp, MouseTrigger::WheelDown].map(|trigger| (trigger, MouseAction::None));
+        assert!(!MouseBindings::with_overrides(&none).captures_mouse());
+        assert!(MouseBindings::default().captures_mouse());
+    }
+}
@@ -0,0 +1,115 @@
+//! Notes attached to entries during an investigation. In the viewer they're kept by entry
+//! id; notes on entries read from files are also saved by file and byte offset, so they
+//! come back when the same file is opened in a later session. A hash of the entry's
+//! message is saved with each note, so a file that was rewritten in the meantime doesn't
+//! put notes on whatever now starts at the old offsets.
+
+use crate::history::config_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Saved notes by canonical file path, then by byte offset of the entry within the file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SavedNotes {
+    #[serde(default)]
+    files: HashMap<String, BTreeMap<u64, SavedNote>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SavedNote {
+    /// `message_hash` of the entry the note was written on
+    message_hash: u64,
+    text: String,
+}
+
+/// FNV-1a hash of `message`, which unlike `DefaultHasher` stays the same across builds
+pub fn message_hash(message: &str) -> u64 {
+    message.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+}
+
+impl SavedNotes {
+    pub fn default_path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("notes.json"))
+    }
+
+    /// Load notes from `path`; a missing or unreadable file yields no notes
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The note on the entry at `byte_offset` of `file`, if it was written on an entry with
+    /// the same `message`
+    pub fn get(&self, file: &str, byte_offset: u64, message: &str) -> Option<&str> {
+        let note = self.files.get(file)?.get(&byte_offset)?;
+        (note.message_hash == message_hash(message)).then_some(note.text.as_str())
+    }
+
+    /// Save `note` for the entry with `message` at `byte_offset` of `file`; an empty note
+    /// removes it
+    pub fn set(&mut self, file: &str, byte_offset: u64, message: &str, note: &str) {
+        if note.is_empty() {
+            if let Some(notes) = self.files.get_mut(file) {
+                notes.remove(&byte_offset);
+                if notes.is_empty() {
+                    self.files.remove(file);
+                }
+            }
+            return;
+        }
+        let note = SavedNote { message_hash: message_hash(message), text: note.to_string() };
+        self.files.entry(file.to_string()).or_default().insert(byte_offset, note);
+    }
+}
+
+/// Canonical path notes on entries of `source` are saved under, when it's a file
+pub fn note_file(source: &str) -> Option<String> {
+    let path = Path::new(source);
+    if !path.is_file() {
+        return None;
+    }
+    Some(std::fs::canonicalize(path).ok()?.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_notes_roundtrip() {
+        let path = std::env::temp_dir().join(format!("tracing-viewer-notes-{}.json", std::process::id()));
+        let mut notes = SavedNotes::default();
+        notes.set("/var/log/app.log", 120, "connection reset", "this is where it broke");
+        notes.set("/var/log/app.log", 0, "starting", "startup");
+        notes.set("/var/log/other.log", 5, "tick", "unrelated");
+        notes.set("/var/log/other.log", 5, "tick", "");
+        assert_eq!(notes.get("/var/log/app.log", 120, "connection reset"), Some("this is where it broke"));
+        assert_eq!(notes.get("/var/log/other.log", 5, "tick"), None);
+        // 同じ位置でもメッセージが変わっていれば別のエントリとみなす
+        assert_eq!(notes.get("/var/log/app.log", 120, "connection refused"), None);
+
+        notes.save(&path).unwrap();
+        let loaded = SavedNotes::load(&path);
+        assert_eq!(loaded, notes);
+        assert_eq!(loaded.files.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+        assert!(SavedNotes::load(&path).is_empty());
+
+        assert_eq!(note_file("stdin"), None);
+    }
+}
@@ -0,0 +1,250 @@
+//! Command palette: named actions found by fuzzy search, as an alternative to
+//! remembering every single-key binding.
+
+/// An action that can be run from the palette; each one maps to the same `App` method
+/// as its key binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    ClearFilters,
+    SelectAllModules,
+    DeselectAllModules,
+    CycleMinLevel,
+    LowerMinLevel,
+    Search,
+    Grep,
+    Highlight,
+    ClearHighlights,
+    FieldFilter,
+    TraceFilter,
+    CopyField,
+    CopyViewCommand,
+    ExportHtml,
+    ExportText,
+    Reload,
+    TestPattern,
+    ToggleSplit,
+    ToggleFilterPanel,
+    ToggleHeader,
+    ToggleMinimap,
+    ToggleMessageOnly,
+    ToggleRawView,
+    ToggleFreeze,
+    ToggleCompare,
+    MarkA,
+    MarkB,
+    Note,
+    ToggleDetails,
+    CycleTimestamps,
+    CycleLineNumbers,
+    GotoBottom,
+    Quit,
+}
+
+impl Command {
+    /// Every command, in the order listed for an empty query
+    pub const ALL: [Command; 33] = [
+        Command::ClearFilters,
+        Command::SelectAllModules,
+        Command::DeselectAllModules,
+        Command::CycleMinLevel,
+        Command::LowerMinLevel,
+        Command::Search,
+        Command::Grep,
+        Command::Highlight,
+        Command::ClearHighlights,
+        Command::FieldFilter,
+        Command::TraceFilter,
+        Command::CopyField,
+        Command::CopyViewCommand,
+        Command::ExportHtml,
+        Command::ExportText,
+        Command::Reload,
+        Command::TestPattern,
+        Command::ToggleSplit,
+        Command::ToggleFilterPanel,
+        Command::ToggleHeader,
+        Command::ToggleMinimap,
+        Command::ToggleMessageOnly,
+        Command::ToggleRawView,
+        Command::ToggleFreeze,
+        Command::ToggleCompare,
+        Command::MarkA,
+        Command::MarkB,
+        Command::Note,
+        Command::ToggleDetails,
+        Command::CycleTimestamps,
+        Command::CycleLineNumbers,
+        Command::GotoBottom,
+        Command::Quit,
+    ];
+
+    /// Name matched against the query
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::ClearFilters => "filter-clear",
+            Command::SelectAllModules => "modules-show-all",
+            Command::DeselectAllModules => "modules-hide-all",
+            Command::CycleMinLevel => "set-level",
+            Command::LowerMinLevel => "set-level-lower",
+            Command::Search => "search",
+            Command::Grep => "grep",
+            Command::Highlight => "highlight",
+            Command::ClearHighlights => "highlight-clear",
+            Command::FieldFilter => "filter-has-field",
+            Command::TraceFilter => "filter-trace",
+            Command::CopyField => "copy-field",
+            Command::CopyViewCommand => "copy-view-command",
+            Command::ExportHtml => "export-html",
+            Command::ExportText => "export-text",
+            Command::Reload => "reload",
+            Command::TestPattern => "test-pattern",
+            Command::ToggleSplit => "toggle-split",
+            Command::ToggleFilterPanel => "toggle-filter-panel",
+            Command::ToggleHeader => "toggle-header",
+            Command::ToggleMinimap => "toggle-minimap",
+            Command::ToggleMessageOnly => "toggle-message-only",
+            Command::ToggleRawView => "toggle-raw",
+            Command::ToggleFreeze => "toggle-freeze",
+            Command::ToggleCompare => "toggle-compare",
+            Command::MarkA => "mark-a",
+            Command::MarkB => "mark-b",
+            Command::Note => "note",
+            Command::ToggleDetails => "toggle-details",
+            Command::CycleTimestamps => "cycle-timestamps",
+            Command::CycleLineNumbers => "cycle-line-numbers",
+            Command::GotoBottom => "goto-bottom",
+            Command::Quit => "quit",
+        }
+    }
+
+    /// The command called `name` in the palette
+    pub fn from_name(name: &str) -> Option<Command> {
+        Command::ALL.into_iter().find(|command| command.name() == name)
+    }
+
+    /// Key that runs the same action outside the palette (empty when there is none)
+    pub fn key_hint(self) -> &'static str {
+        match self {
+            Command::ClearFilters => "C",
+            Command::SelectAllModules => "a",
+            Command::DeselectAllModules => "n",
+            Command::CycleMinLevel => "L",
+            Command::LowerMinLevel => "<",
+            Command::Search => "/",
+            Command::Grep => "&",
+            Command::Highlight => "h",
+            Command::ClearHighlights => "",
+            Command::FieldFilter => "F",
+            Command::TraceFilter => "T",
+            Command::CopyField => "y",
+            Command::CopyViewCommand => "E",
+            Command::ExportHtml => "",
+            Command::ExportText => "",
+            Command::Reload => "R",
+            Command::TestPattern => "p",
+            Command::ToggleSplit => "S",
+            Command::ToggleFilterPanel => "b",
+            Command::ToggleHeader => "H",
+            Command::ToggleMinimap => "m",
+            Command::ToggleMessageOnly => "M",
+            Command::ToggleRawView => "r",
+            Command::ToggleFreeze => "P",
+            Command::ToggleCompare => "D",
+            Command::MarkA => "A",
+            Command::MarkB => "B",
+            Command::Note => "a",
+            Command::ToggleDetails => "Enter",
+            Command::CycleTimestamps => "t",
+            Command::CycleLineNumbers => "#",
+            Command::GotoBottom => "",
+            Command::Quit => "q",
+        }
+    }
+}
+
+/// Score `candidate` against `query` when every query character appears in it in order,
+/// ignoring ASCII case. Consecutive and early matches score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.chars().map(|c| c.to_ascii_lowercase()).filter(|c| !c.is_whitespace()) {
+        let found = position + candidate[position..].iter().position(|&candidate_char| candidate_char == c)?;
+        // 連続した一致と先頭に近い一致を優遇する
+        score += if previous.is_some_and(|previous| previous + 1 == found) { 5 } else { 1 };
+        score -= found as i32 / 4;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+/// State of the open palette: the query typed so far and the highlighted match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    pub query: String,
+    pub selected: usize,
+}
+
+impl Palette {
+    /// Commands matching the query, best first; ties keep the `Command::ALL` order
+    pub fn matches(&self) -> Vec<Command> {
+        let mut scored: Vec<(i32, Command)> = Command::ALL
+            .iter()
+            .filter_map(|&command| Some((fuzzy_score(&self.query, command.name())?, command)))
+            .collect();
+        scored.sort_by_key(|(score, _)| -score);
+        scored.into_iter().map(|(_, command)| command).collect()
+    }
+
+    /// The highlighted command, if anything matches
+    pub fn selected_command(&self) -> Option<Command> {
+        self.matches().get(self.selected).copied()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        let count = self.matches().len();
+        if count > 0 {
+            self.selected = (self.selected + 1) % count;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let count = self.matches().len();
+        if count > 0 {
+            self.selected = (self.selected + count - 1) % count;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matching_and_ranking() {
+        assert!(fuzzy_score("tgsp", "toggle-split").is_some());
+        assert_eq!(fuzzy_score("xyz", "toggle-split"), None);
+        assert!(fuzzy_score("quit", "quit") > fuzzy_score("quit", "q-u-i-t"));
+
+        let mut palette = Palette::default();
+        assert_eq!(palette.matches().len(), Command::ALL.len());
+        for c in "min".chars() {
+            palette.push(c);
+        }
+        assert_eq!(palette.selected_command(), Some(Command::ToggleMinimap));
+        palette.select_previous();
+        assert_eq!(palette.selected_command(), palette.matches().last().copied());
+    }
+}
@@ -0,0 +1,84 @@
+//! Live editor for the line pattern, run against a few raw input lines so a format
+//! mismatch can be fixed by trial instead of guesswork.
+
+use crate::log_parser::compile_pattern;
+
+/// Raw input lines kept around as samples for the tester
+pub const SAMPLE_LINES: usize = 10;
+
+/// Outcome of running the edited pattern over one sample line
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleMatch {
+    pub line: String,
+    /// Named groups and their values in pattern order, or `None` when the line doesn't match
+    pub captures: Option<Vec<(String, String)>>,
+}
+
+/// State of the pattern tester popup: the pattern being edited and the lines it is tried on
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternTester {
+    pub pattern: String,
+    pub samples: Vec<String>,
+}
+
+impl PatternTester {
+    pub fn new(pattern: &str, samples: &[String]) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            samples: samples.to_vec(),
+        }
+    }
+
+    /// Run the pattern over every sample; the error describes why the pattern can't be used
+    pub fn evaluate(&self) -> Result<Vec<SampleMatch>, String> {
+        let regex = compile_pattern(&self.pattern).map_err(|e| e.to_string())?;
+        Ok(self
+            .samples
+            .iter()
+            .map(|line| SampleMatch {
+                line: line.clone(),
+                captures: regex.captures(line).map(|captures| {
+                    regex
+                        .capture_names()
+                        .flatten()
+                        .filter_map(|name| Some((name.to_string(), captures.name(name)?.as_str().to_string())))
+                        .collect()
+                }),
+            })
+            .collect())
+    }
+
+    /// Number of samples the current pattern parses, or `None` while it is invalid
+    pub fn matched_count(&self) -> Option<usize> {
+        self.evaluate()
+            .ok()
+            .map(|matches| matches.iter().filter(|sample| sample.captures.is_some()).count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_parser::DEFAULT_PATTERN;
+
+    #[test]
+    fn test_evaluate_reports_matches_and_errors() {
+        let samples = vec![
+            "2024-01-01T12:00:00Z INFO app: ready".to_string(),
+            "[2024-01-01 12:00:00] WARN app::db - slow query".to_string(),
+        ];
+        let mut tester = PatternTester::new(&DEFAULT_PATTERN, &samples);
+        let matches = tester.evaluate().unwrap();
+        assert_eq!(matches[0].captures.as_ref().unwrap()[1], ("level".to_string(), "INFO".to_string()));
+        assert!(matches[1].captures.is_none());
+
+        tester.pattern = r"\[(?P<timestamp>[^\]]+)\] (?P<level>\w+) (?P<target>[\w:]+) - (?P<message>.*)".to_string();
+        assert_eq!(tester.matched_count(), Some(1));
+        assert_eq!(tester.evaluate().unwrap()[1].captures.as_ref().unwrap()[2].1, "app::db");
+
+        tester.pattern = r"(?P<level>\w+) (?P<message>.*)".to_string();
+        assert!(tester.evaluate().unwrap_err().contains("timestamp, target"));
+        tester.pattern = "(".to_string();
+        assert_eq!(tester.matched_count(), None);
+    }
+}
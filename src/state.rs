@@ -0,0 +1,108 @@
+//! Machine-readable snapshot of what the viewer is showing, written as JSON by the hidden
+//! `--dump-state` option for UI tests and scripts that drive the viewer.
+
+use crate::ui::{App, AppMode};
+use serde::Serialize;
+
+/// The filters in effect, in the same terms as the command-line flags where one exists
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FilterState {
+    pub shown_levels: Vec<String>,
+    /// Deselected modules whose parent is selected (see `App::hidden_module_roots`)
+    pub hidden_modules: Vec<String>,
+    /// Module paths with their own level threshold
+    pub module_min_levels: Vec<(String, String)>,
+    pub hidden_sources: Vec<String>,
+    pub required_field: Option<String>,
+    pub grep: Option<String>,
+    pub grep_case_sensitive: bool,
+    /// Field key and id the trace filter started from
+    pub trace: Option<(String, String)>,
+}
+
+/// Counts, focus, scroll position and filters of the log view
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ViewState {
+    pub mode: &'static str,
+    pub total_entries: usize,
+    pub filtered_entries: usize,
+    /// Index of the focused entry among the filtered ones, and its id
+    pub focused_index: Option<usize>,
+    pub focused_id: Option<u64>,
+    /// First display line shown at the top of the log list
+    pub scroll_position: usize,
+    pub auto_follow: bool,
+    pub frozen: bool,
+    pub search: Option<String>,
+    pub filters: FilterState,
+}
+
+impl App {
+    pub fn view_state(&self) -> ViewState {
+        let mut hidden_sources: Vec<String> = self.hidden_sources.iter().cloned().collect();
+        hidden_sources.sort();
+        let focused = self.filtered_logs.get(self.current_log_line);
+        ViewState {
+            mode: match self.mode {
+                AppMode::ModuleSelection => "module-selection",
+                AppMode::LogNavigation => "log-navigation",
+                AppMode::TextSelection => "text-selection",
+                AppMode::LogLevelFilter => "level-filter",
+                AppMode::SourceFilter => "source-filter",
+            },
+            total_entries: self.logs.len(),
+            filtered_entries: self.filtered_logs.len(),
+            focused_index: focused.map(|_| self.current_log_line),
+            focused_id: focused.map(|log| log.id),
+            scroll_position: self.log_scroll_position,
+            auto_follow: self.auto_follow,
+            frozen: self.frozen,
+            search: self.search_query.clone(),
+            filters: FilterState {
+                shown_levels: self.shown_levels().into_iter().map(str::to_string).collect(),
+                hidden_modules: self.hidden_module_roots().into_iter().map(str::to_string).collect(),
+                module_min_levels: self
+                    .module_items
+                    .iter()
+                    .filter_map(|item| Some((item.full_path.clone(), item.min_level.clone()?)))
+                    .collect(),
+                hidden_sources,
+                required_field: self.required_field.clone(),
+                grep: self.grep_query.clone(),
+                grep_case_sensitive: self.grep_case_sensitive,
+                trace: self.trace_filter.as_ref().map(|trace| (trace.key.clone(), trace.root.clone())),
+            },
+        }
+    }
+
+    /// `view_state` as pretty-printed JSON
+    pub fn view_state_json(&self) -> String {
+        serde_json::to_string_pretty(&self.view_state()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_parser::LogEntry;
+
+    #[test]
+    fn test_view_state_reflects_filters_and_focus() {
+        let entry = |level: &str, target: &str| LogEntry { level: level.to_string(), target: target.to_string(), ..Default::default() };
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app::db"), entry("ERROR", "app::http"), entry("WARN", "app::http")]);
+        app.toggle_log_level("INFO");
+        app.set_grep_query("http");
+
+        let state = app.view_state();
+        assert_eq!((state.total_entries, state.filtered_entries), (3, 2));
+        assert_eq!(state.focused_id, app.filtered_logs.get(app.current_log_line).map(|log| log.id));
+        assert!(!state.filters.shown_levels.contains(&"INFO".to_string()));
+        assert_eq!(state.filters.grep.as_deref(), Some("http"));
+
+        let json: serde_json::Value = serde_json::from_str(&app.view_state_json()).unwrap();
+        assert_eq!(json["filtered_entries"], 2);
+        assert_eq!(json["mode"], "module-selection");
+        assert_eq!(json["filters"]["trace"], serde_json::Value::Null);
+    }
+}
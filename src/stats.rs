@@ -0,0 +1,166 @@
+use crate::log_parser::{LogEntry, SeverityOrder};
+use crate::timestamp::{format_delta, parse_timestamp};
+use std::collections::{HashMap, HashSet};
+
+/// Entry counts aggregated per level and per target
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogStats {
+    pub total: usize,
+    pub by_level: HashMap<String, usize>,
+    pub by_target: HashMap<String, usize>,
+}
+
+impl LogStats {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a LogEntry>) -> Self {
+        let mut stats = Self::default();
+        for entry in entries {
+            stats.add(entry);
+        }
+        stats
+    }
+
+    pub fn add(&mut self, entry: &LogEntry) {
+        self.total += 1;
+        *self.by_level.entry(entry.level.clone()).or_default() += 1;
+        *self.by_target.entry(entry.target.clone()).or_default() += 1;
+    }
+}
+
+/// One-line summary of a session: entry and dropped-line counts, the time span from the
+/// earliest to the latest parseable timestamp, and the count per level by severity
+pub fn session_summary(entries: &[LogEntry], dropped_lines: usize, severity: &SeverityOrder) -> String {
+    let stats = LogStats::from_entries(entries);
+    let mut summary = format!("{} entries, {} dropped lines", stats.total, dropped_lines);
+
+    let timestamps: Vec<_> = entries.iter().filter_map(|entry| Some((parse_timestamp(&entry.timestamp)?, entry))).collect();
+    if let (Some((first, first_entry)), Some((last, last_entry))) = (
+        timestamps.iter().min_by_key(|(time, _)| *time),
+        timestamps.iter().max_by_key(|(time, _)| *time),
+    ) {
+        // 差分表示と同じ書式から符号を除いて期間として表示する
+        let span = format_delta(*last - *first);
+        summary.push_str(&format!(
+            ", {} → {} ({})",
+            first_entry.timestamp,
+            last_entry.timestamp,
+            span.trim_start_matches('+')
+        ));
+    }
+
+    let mut levels: Vec<(&String, &usize)> = stats.by_level.iter().collect();
+    levels.sort_by(|(a, _), (b, _)| severity.rank(a).cmp(&severity.rank(b)).then_with(|| a.cmp(b)));
+    for (level, count) in levels {
+        summary.push_str(&format!(", {} {}", level, count));
+    }
+    summary
+}
+
+/// A single key's count in two snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountDelta {
+    pub key: String,
+    pub a: usize,
+    pub b: usize,
+}
+
+impl CountDelta {
+    pub fn delta(&self) -> i64 {
+        self.b as i64 - self.a as i64
+    }
+}
+
+/// Side-by-side comparison of two `LogStats` snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsComparison {
+    pub total: CountDelta,
+    /// Levels ordered by severity
+    pub levels: Vec<CountDelta>,
+    /// Targets ordered by largest absolute change first
+    pub targets: Vec<CountDelta>,
+}
+
+impl StatsComparison {
+    pub fn new(a: &LogStats, b: &LogStats, severity: &SeverityOrder) -> Self {
+        let mut levels = count_deltas(&a.by_level, &b.by_level);
+        levels.sort_by(|x, y| severity.rank(&x.key).cmp(&severity.rank(&y.key)).then_with(|| x.key.cmp(&y.key)));
+
+        let mut targets = count_deltas(&a.by_target, &b.by_target);
+        targets.sort_by(|x, y| {
+            y.delta().abs().cmp(&x.delta().abs()).then_with(|| x.key.cmp(&y.key))
+        });
+
+        Self {
+            total: CountDelta { key: "total".to_string(), a: a.total, b: b.total },
+            levels,
+            targets,
+        }
+    }
+}
+
+fn count_deltas(a: &HashMap<String, usize>, b: &HashMap<String, usize>) -> Vec<CountDelta> {
+    let keys: HashSet<&String> = a.keys().chain(b.keys()).collect();
+    keys.into_iter()
+        .map(|key| CountDelta {
+            key: key.clone(),
+            a: a.get(key).copied().unwrap_or(0),
+            b: b.get(key).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: &str, target: &str) -> LogEntry {
+        LogEntry {
+            level: level.to_string(),
+            target: target.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_and_comparison() {
+        let good = [entry("INFO", "app::http"), entry("INFO", "app::db"), entry("WARN", "app::db")];
+        let bad = [
+            entry("ERROR", "app::db"),
+            entry("ERROR", "app::db"),
+            entry("WARN", "app::db"),
+            entry("INFO", "app::http"),
+        ];
+        let a = LogStats::from_entries(&good);
+        let b = LogStats::from_entries(&bad);
+        assert_eq!(a.total, 3);
+        assert_eq!(a.by_target["app::db"], 2);
+
+        let comparison = StatsComparison::new(&a, &b, &SeverityOrder::default());
+        assert_eq!(comparison.total.delta(), 1);
+
+        let levels: Vec<(&str, i64)> = comparison.levels.iter().map(|d| (d.key.as_str(), d.delta())).collect();
+        assert_eq!(levels, vec![("ERROR", 2), ("WARN", 0), ("INFO", -1)]);
+
+        assert_eq!(comparison.targets[0].key, "app::db");
+        assert_eq!((comparison.targets[0].a, comparison.targets[0].b), (2, 3));
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let at = |timestamp: &str, level: &str| LogEntry { timestamp: timestamp.to_string(), ..entry(level, "app") };
+        let entries = [
+            at("2024-01-01T12:00:05Z", "INFO"),
+            at("2024-01-01T12:00:00Z", "WARN"),
+            at("not a time", "INFO"),
+            at("2024-01-01T13:02:03Z", "ERROR"),
+        ];
+        assert_eq!(
+            session_summary(&entries, 2, &SeverityOrder::default()),
+            "4 entries, 2 dropped lines, 2024-01-01T12:00:00Z → 2024-01-01T13:02:03Z (1h02m03s), ERROR 1, WARN 1, INFO 2"
+        );
+        assert_eq!(session_summary(&[], 0, &SeverityOrder::default()), "0 entries, 0 dropped lines");
+
+        // 独自のレベルも指定した順序で並ぶ
+        let bunyan = SeverityOrder::new(["FATAL", "ERROR", "INFO"]);
+        assert!(session_summary(&[at("x", "INFO"), at("x", "FATAL")], 0, &bunyan).ends_with("FATAL 1, INFO 1"));
+    }
+}
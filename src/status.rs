@@ -0,0 +1,86 @@
+//! Optional indicator segments for the status bar.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// An indicator that can be placed on either side of the status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatusSegment {
+    /// Labels for every active filter, e.g. `[lvl:WARN+]`
+    Filters,
+    /// Visible and total entry counts
+    Counts,
+    /// Entries received per second over the last few seconds
+    Rate,
+    /// Whether the view follows new entries
+    Follow,
+    /// Local wall-clock time
+    Clock,
+}
+
+impl StatusSegment {
+    /// Whether the segment changes without any input, so the bar needs periodic redraws
+    pub fn is_live(self) -> bool {
+        matches!(self, StatusSegment::Rate | StatusSegment::Clock)
+    }
+}
+
+/// Sliding-window counter of received entries
+#[derive(Debug, Clone)]
+pub struct IngestRate {
+    samples: VecDeque<(Instant, usize)>,
+    window: Duration,
+}
+
+impl Default for IngestRate {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+impl IngestRate {
+    pub fn new(window: Duration) -> Self {
+        Self { samples: VecDeque::new(), window }
+    }
+
+    pub fn record(&mut self, now: Instant, count: usize) {
+        self.samples.push_back((now, count));
+        // 窓から外れた古いサンプルは捨てる
+        while let Some((time, _)) = self.samples.front()
+            && now.duration_since(*time) > self.window
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Entries per second received within the window ending at `now`
+    pub fn per_second(&self, now: Instant) -> f64 {
+        let count: usize = self
+            .samples
+            .iter()
+            .filter(|(time, _)| now.duration_since(*time) <= self.window)
+            .map(|(_, count)| count)
+            .sum();
+        count as f64 / self.window.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ingest_rate_window() {
+        let start = Instant::now();
+        let mut rate = IngestRate::new(Duration::from_secs(2));
+        assert_eq!(rate.per_second(start), 0.0);
+
+        rate.record(start, 10);
+        rate.record(start + Duration::from_secs(1), 6);
+        assert_eq!(rate.per_second(start + Duration::from_secs(1)), 8.0);
+        // 最初のサンプルが窓から外れる
+        assert_eq!(rate.per_second(start + Duration::from_millis(2500)), 3.0);
+        rate.record(start + Duration::from_secs(4), 0);
+        assert_eq!(rate.samples.len(), 1);
+    }
+}
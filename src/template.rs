@@ -0,0 +1,191 @@
+use crate::log_parser::LogEntry;
+
+/// Template used when no `--copy-format` is given
+pub const DEFAULT_COPY_FORMAT: &str = "[{timestamp}] {level} {target}: {message}";
+
+/// A parsed entry format such as `{timestamp}\t{level}\t{message}`
+///
+/// Placeholders: `{timestamp}`, `{level}`, `{target}`, `{message}`, `{source}`
+/// and `{fields.<name>}`. `{{`/`}}` produce literal braces, and `\t`, `\n`, `\\`
+/// are unescaped so templates can be passed from a shell without quoting tricks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Placeholder {
+    Timestamp,
+    Level,
+    Target,
+    Message,
+    Source,
+    Field(String),
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "timestamp" => Ok(Self::Timestamp),
+            "level" => Ok(Self::Level),
+            "target" => Ok(Self::Target),
+            "message" => Ok(Self::Message),
+            "source" => Ok(Self::Source),
+            _ => match name.strip_prefix("fields.") {
+                Some(field) if !field.is_empty() => Ok(Self::Field(field.to_string())),
+                _ => Err(anyhow::anyhow!("unknown placeholder `{{{}}}` in format template", name)),
+            },
+        }
+    }
+}
+
+impl EntryTemplate {
+    pub fn parse(template: &str) -> anyhow::Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(anyhow::anyhow!("unclosed `{{` in format template")),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(Placeholder::parse(name.trim())?));
+                }
+                '}' => return Err(anyhow::anyhow!("unmatched `}}` in format template (use `}}}}` for a literal brace)")),
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                _ => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn render(&self, entry: &LogEntry) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Placeholder(placeholder) => output.push_str(match placeholder {
+                    Placeholder::Timestamp => &entry.timestamp,
+                    Placeholder::Level => &entry.level,
+                    Placeholder::Target => &entry.target,
+                    Placeholder::Message => &entry.message,
+                    Placeholder::Source => &entry.source,
+                    Placeholder::Field(name) => entry.fields.get(name).map(String::as_str).unwrap_or(""),
+                }),
+            }
+        }
+        output
+    }
+
+    /// Render the entry, then append its fields as ` key=value` pairs in parser order.
+    /// Values that are empty or contain spaces, quotes or `=` are double-quoted.
+    pub fn render_with_fields(&self, entry: &LogEntry) -> String {
+        let mut output = self.render(entry);
+        for (key, value) in &entry.fields {
+            output.push(' ');
+            output.push_str(key);
+            output.push('=');
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+                output.push_str(&format!("{:?}", value));
+            } else {
+                output.push_str(value);
+            }
+        }
+        output
+    }
+}
+
+impl Default for EntryTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_COPY_FORMAT).expect("default copy format is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+
+    fn entry() -> LogEntry {
+        LogEntry {
+            timestamp: "2024-01-01T12:00:00.123Z".to_string(),
+            level: "INFO".to_string(),
+            target: "myapp::db".to_string(),
+            message: "connected".to_string(),
+            fields: IndexMap::from([("trace_id".to_string(), "abc123".to_string())]),
+            source: "app.log".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_template_matches_previous_format() {
+        let template = EntryTemplate::default();
+        assert_eq!(template.render(&entry()), "[2024-01-01T12:00:00.123Z] INFO myapp::db: connected");
+    }
+
+    #[test]
+    fn test_template_escapes_and_fields() {
+        let template = EntryTemplate::parse(r"{timestamp}\t{level}\t{fields.trace_id}\t{{{message}}}").unwrap();
+        assert_eq!(template.render(&entry()), "2024-01-01T12:00:00.123Z\tINFO\tabc123\t{connected}");
+
+        let missing = EntryTemplate::parse("{fields.missing}|{source}").unwrap();
+        assert_eq!(missing.render(&entry()), "|app.log");
+    }
+
+    #[test]
+    fn test_render_with_fields() {
+        let mut entry = entry();
+        entry.fields.insert("query".to_string(), "SELECT 1".to_string());
+        entry.fields.insert("empty".to_string(), String::new());
+        assert_eq!(
+            EntryTemplate::default().render_with_fields(&entry),
+            r#"[2024-01-01T12:00:00.123Z] INFO myapp::db: connected trace_id=abc123 query="SELECT 1" empty="""#
+        );
+    }
+
+    #[test]
+    fn test_template_rejects_invalid_input() {
+        assert!(EntryTemplate::parse("{unknown}").is_err());
+        assert!(EntryTemplate::parse("{fields.}").is_err());
+        assert!(EntryTemplate::parse("{level").is_err());
+        assert!(EntryTemplate::parse("level}").is_err());
+    }
+}
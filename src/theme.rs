@@ -0,0 +1,37 @@
+//! Colors of the log list, set from the `[theme]` table of the config file. Colors are
+//! written as for `--focus-color`: a name (`lightred`), an index `0`-`255` or `#rrggbb`.
+
+use crate::ui::level_style;
+use indexmap::IndexMap;
+use ratatui::style::{Color, Style};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Timestamp column
+    pub timestamp: Color,
+    /// Target column
+    pub target: Color,
+    /// `<source>` label shown with several inputs
+    pub source: Color,
+    /// Level name (any case) to the color replacing its built-in one
+    pub levels: IndexMap<String, Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self { timestamp: Color::Cyan, target: Color::Yellow, source: Color::Magenta, levels: IndexMap::new() }
+    }
+}
+
+impl Theme {
+    /// `level_style` with the color from `levels` when the level has one
+    pub fn level_style(&self, level: &str) -> Style {
+        let style = level_style(level);
+        match self.levels.iter().find(|(name, _)| name.eq_ignore_ascii_case(level)) {
+            Some((_, color)) => style.fg(*color),
+            None => style,
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::log_parser::LogEntry;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeDelta};
+use std::collections::VecDeque;
+
+/// How the timestamp column is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TimestampMode {
+    /// The timestamp as it appeared in the input
+    #[default]
+    Absolute,
+    /// Time elapsed since the first entry with a valid timestamp
+    Relative,
+    /// Time elapsed since the previous entry with a valid timestamp
+    Delta,
+}
+
+impl TimestampMode {
+    pub fn next(self) -> Self {
+        match self {
+            TimestampMode::Absolute => TimestampMode::Relative,
+            TimestampMode::Relative => TimestampMode::Delta,
+            TimestampMode::Delta => TimestampMode::Absolute,
+        }
+    }
+}
+
+/// Rendered in place of a delta when the entry's timestamp can't be parsed
+pub const UNKNOWN_DELTA: &str = "+?";
+
+/// Parse an RFC 3339 style timestamp. Timestamps without an offset are taken as UTC.
+/// Returns `None` for empty, whitespace-only or otherwise malformed input.
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    let timestamp = timestamp.trim();
+    if timestamp.is_empty() {
+        return None;
+    }
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(timestamp) {
+        return Some(parsed);
+    }
+    // コロンなしのオフセット（+0900）
+    if let Ok(parsed) = DateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f%z") {
+        return Some(parsed);
+    }
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(timestamp, format).ok())
+        .map(|naive| naive.and_utc().fixed_offset())
+}
+
+/// Interleave `inputs` (each in its own order, e.g. one per file) by timestamp. Entries
+/// keep their order within an input: one without a valid timestamp goes with the last
+/// valid one before it, or first when there is none. Ties go to the earlier input.
+pub fn merge_by_timestamp(inputs: Vec<Vec<LogEntry>>) -> Vec<LogEntry> {
+    if inputs.len() <= 1 {
+        return inputs.into_iter().flatten().collect();
+    }
+    let total = inputs.iter().map(Vec::len).sum();
+    let mut queues: Vec<VecDeque<(Option<DateTime<FixedOffset>>, LogEntry)>> = inputs
+        .into_iter()
+        .map(|entries| {
+            let mut last = None;
+            entries
+                .into_iter()
+                .map(|entry| {
+                    last = parse_timestamp(&entry.timestamp).or(last);
+                    (last, entry)
+                })
+                .collect()
+        })
+        .collect();
+    let mut merged = Vec::with_capacity(total);
+    // 各入力の先頭のうち最も早いものを順に取り出す（min_by_keyは同じ値なら先の入力を返す）
+    while let Some(index) = (0..queues.len()).filter(|&index| !queues[index].is_empty()).min_by_key(|&index| queues[index][0].0) {
+        if let Some((_, entry)) = queues[index].pop_front() {
+            merged.push(entry);
+        }
+    }
+    merged
+}
+
+/// Rewrite the fractional seconds of an ISO 8601 timestamp to exactly `precision` digits,
+/// padding with zeros or truncating (`12:00:00.1Z` → `12:00:00.100Z` at precision 3).
+/// The rest of the text, including any offset, is kept; unrecognized input is returned as is.
+pub fn with_precision(timestamp: &str, precision: usize) -> String {
+    let timestamp = timestamp.trim();
+    let Some(separator) = timestamp.find(['T', ' ']) else {
+        return timestamp.to_string();
+    };
+    // 区切り文字 + HH:MM:SS の直後が秒の終わり
+    let seconds_end = separator + 9;
+    if !timestamp
+        .get(separator + 1..seconds_end)
+        .is_some_and(|time| time.bytes().all(|b| b.is_ascii_digit() || b == b':'))
+    {
+        return timestamp.to_string();
+    }
+
+    let rest = &timestamp[seconds_end..];
+    let (digits, tail) = match rest.strip_prefix('.') {
+        Some(fraction) => {
+            let length = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            fraction.split_at(length)
+        }
+        None => ("", rest),
+    };
+
+    let mut output = timestamp[..seconds_end].to_string();
+    if precision > 0 {
+        output.push('.');
+        output.extend(digits.chars().chain(std::iter::repeat('0')).take(precision));
+    }
+    output.push_str(tail);
+    output
+}
+
+/// Compute the delta of each timestamp from the previous *valid* one.
+///
+/// Unparseable timestamps yield `None` and leave the reference untouched, so the
+/// next valid timestamp is measured against the last valid one before it.
+/// `previous` seeds the reference (e.g. the last valid timestamp above the visible window).
+pub fn compute_deltas<'a>(
+    timestamps: impl IntoIterator<Item = &'a str>,
+    mut previous: Option<DateTime<FixedOffset>>,
+) -> Vec<Option<TimeDelta>> {
+    timestamps
+        .into_iter()
+        .map(|timestamp| {
+            let current = parse_timestamp(timestamp)?;
+            let delta = previous.map(|previous| current - previous).unwrap_or_default();
+            previous = Some(current);
+            Some(delta)
+        })
+        .collect()
+}
+
+/// Format a duration compactly with a sign, e.g. `+0.250s`, `+2m03.100s`, `-1h05m00s`
+pub fn format_delta(delta: TimeDelta) -> String {
+    let sign = if delta < TimeDelta::zero() { '-' } else { '+' };
+    let delta = delta.abs();
+    let total_millis = delta.num_milliseconds();
+    let hours = total_millis / 3_600_000;
+    let minutes = (total_millis / 60_000) % 60;
+    let seconds = (total_millis / 1000) % 60;
+    let millis = total_millis % 1000;
+
+    if hours > 0 {
+        format!("{}{}h{:02}m{:02}s", sign, hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}{}m{:02}.{:03}s", sign, minutes, seconds, millis)
+    } else {
+        format!("{}{}.{:03}s", sign, seconds, millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp_variants() {
+        assert!(parse_timestamp("2024-01-01T12:00:00.123Z").is_some());
+        assert!(parse_timestamp("2024-01-01T12:00:00+09:00").is_some());
+        assert!(parse_timestamp("2024-01-01T12:00:00.5-0530").is_some());
+        assert!(parse_timestamp("2024-01-01T12:00:00.123456789").is_some());
+        assert!(parse_timestamp("").is_none());
+        assert!(parse_timestamp("   ").is_none());
+        assert!(parse_timestamp("not-a-time").is_none());
+        assert!(parse_timestamp("2024-13-45T99:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_deltas_carry_forward_last_valid_timestamp() {
+        let timestamps = [
+            "2024-01-01T12:00:00.000Z",
+            "garbage",
+            "   ",
+            "2024-01-01T12:00:01.500Z",
+            "2024-01-01T12:00:01.750Z",
+        ];
+        let deltas = compute_deltas(timestamps, None);
+
+        assert_eq!(deltas[0], Some(TimeDelta::zero()));
+        assert_eq!(deltas[1], None);
+        assert_eq!(deltas[2], None);
+        assert_eq!(deltas[3], Some(TimeDelta::milliseconds(1500)));
+        assert_eq!(deltas[4], Some(TimeDelta::milliseconds(250)));
+    }
+
+    #[test]
+    fn test_deltas_with_seed_and_only_garbage() {
+        let seed = parse_timestamp("2024-01-01T12:00:00Z");
+        let deltas = compute_deltas(["bogus", "2024-01-01T12:01:00Z"], seed);
+        assert_eq!(deltas, vec![None, Some(TimeDelta::minutes(1))]);
+
+        assert_eq!(compute_deltas(["", "x", "12:00"], None), vec![None, None, None]);
+    }
+
+    #[test]
+    fn test_merge_by_timestamp_interleaves_inputs() {
+        let entry = |timestamp: &str, message: &str| LogEntry { timestamp: timestamp.to_string(), message: message.to_string(), ..Default::default() };
+        let first = vec![entry("", "banner"), entry("2024-01-01T00:00:01Z", "a1"), entry("", "a1 cont"), entry("2024-01-01T00:00:05Z", "a5")];
+        let second = vec![entry("2024-01-01T00:00:01Z", "b1"), entry("2024-01-01T00:00:03+00:00", "b3"), entry("2024-01-01T00:00:09Z", "b9")];
+        let merged: Vec<String> = merge_by_timestamp(vec![first, second]).into_iter().map(|entry| entry.message).collect();
+        assert_eq!(merged, ["banner", "a1", "a1 cont", "b1", "b3", "a5", "b9"]);
+        assert_eq!(merge_by_timestamp(vec![vec![entry("x", "only")]]).len(), 1);
+    }
+
+    #[test]
+    fn test_with_precision() {
+        assert_eq!(with_precision("2024-01-01T12:00:00.1Z", 3), "2024-01-01T12:00:00.100Z");
+        assert_eq!(with_precision("2024-01-01T12:00:00.123456789Z", 3), "2024-01-01T12:00:00.123Z");
+        assert_eq!(with_precision("2024-01-01T12:00:00Z", 3), "2024-01-01T12:00:00.000Z");
+        assert_eq!(with_precision("2024-01-01T12:00:00.5+09:00", 0), "2024-01-01T12:00:00+09:00");
+        assert_eq!(with_precision("garbage", 3), "garbage");
+    }
+
+    #[test]
+    fn test_format_delta() {
+        assert_eq!(format_delta(TimeDelta::milliseconds(250)), "+0.250s");
+        assert_eq!(format_delta(TimeDelta::milliseconds(123_100)), "+2m03.100s");
+        assert_eq!(format_delta(TimeDelta::minutes(-65)), "-1h05m00s");
+    }
+}
@@ -1,38 +1,225 @@
-use crate::log_parser::{LogEntry, ModuleTree};
+use crate::export::{anchor_file, entry_anchor, EntryAnchor};
+use crate::history::InputHistory;
+use crate::log_parser::{LogEntry, ModuleTree, SeverityOrder, DEFAULT_PATTERN};
+use crate::launcher::{RecentFiles, RECENT_FILES_KEY};
+use crate::mouse::MouseBindings;
+use crate::notes::{note_file, SavedNotes};
+use crate::palette::{Command, Palette};
+use crate::pattern_tester::{PatternTester, SAMPLE_LINES};
+use crate::stats::{LogStats, StatsComparison};
+use crate::status::{IngestRate, StatusSegment};
+use crate::template::EntryTemplate;
+use crate::theme::Theme;
+use crate::timestamp::{
+    compute_deltas, format_delta, merge_by_timestamp, parse_timestamp, with_precision, TimestampMode, UNKNOWN_DELTA,
+};
+use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
-use std::collections::{hash_map::DefaultHasher, HashSet};
+use std::borrow::Cow;
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
 
 pub struct App {
     pub module_tree: ModuleTree,
     pub logs: Vec<LogEntry>,
     pub filtered_logs: Vec<LogEntry>,
-    pub selected_module_index: usize,
     pub log_scroll_position: usize,
     pub module_list_state: ListState,
     pub module_items: Vec<ModuleItem>,
+    /// Cap on module tree levels; deeper segments are kept in the last level's name
+    pub max_module_depth: Option<usize>,
+    /// Anchor index into `module_items` while a visual range is being marked
+    pub module_visual_anchor: Option<usize>,
     pub should_quit: bool,
     pub current_log_line: usize,
     pub selection_start: Option<usize>,
     pub selection_end: Option<usize>,
+    /// Id given to the next entry added; ids are never reused and increase in `logs` order
+    pub next_entry_id: u64,
     pub mode: AppMode,
-    pub copy_message: Option<String>,
+    pub status_message: Option<String>,
     pub auto_follow: bool,
     pub filter_dirty: bool,
     pub last_filter_hash: u64,
+    /// Module selection and level threshold per distinct target, valid while the module
+    /// tree hashes to `target_cache_tree_hash`
+    pub target_cache: HashMap<String, (bool, Option<String>)>,
+    pub target_cache_tree_hash: u64,
+    /// Bumped whenever `filtered_logs` changes, so cached log lines can tell they're stale
+    pub filtered_generation: u64,
+    /// Log list lines from the last draw, keyed by the state they were built from
+    /// (see `log_view_key`); idle redraws reuse them instead of formatting every entry again
+    pub log_view_cache: Option<(u64, Vec<Line<'static>>)>,
     pub last_terminal_size: (u16, u16),
+    /// Rows of the log list at the last draw; 0 until the first one
+    pub log_view_height: usize,
     pub log_level_filter: HashSet<String>,
+    /// Levels listed in the level filter: the severity ladder, then any other level seen
     pub available_log_levels: Vec<String>,
+    /// Ordering used by level thresholds and severity sorting
+    pub severity: SeverityOrder,
     pub selected_log_level_index: usize,
     pub show_filter_panel: bool,
+    /// Keep the filter panel visible when focus moves to the log view; set by `toggle_filter_panel`
+    pub keep_filter_panel: bool,
+    /// Set while the terminal is too small for the filter panel; the panel stays hidden
+    /// regardless of `show_filter_panel` until the terminal grows again
+    pub compact_layout: bool,
     pub filter_panel_width: u16,
     pub last_action_was_focus_move: bool,
+    /// Lines scrolled per mouse wheel notch
+    pub scroll_step: usize,
+    /// Action of each mouse button and wheel direction (`--mouse`)
+    pub mouse_bindings: MouseBindings,
+    /// Keys from the config file's `[keys]` table and the palette command each runs,
+    /// checked before the built-in bindings of the log view
+    pub key_bindings: Vec<(KeyCode, Command)>,
+    /// Colors from the config file's `[theme]` table
+    pub theme: Theme,
+    /// Sticky follow: with `Some(n)`, new entries snap the view to the bottom only while it
+    /// is within `n` display lines of it, whether or not `auto_follow` is set
+    pub follow_stickiness: Option<usize>,
+    /// Require a second `q` within `QUIT_CONFIRM_WINDOW` before quitting
+    pub confirm_quit: bool,
+    /// When the first `q` of a confirmed quit was pressed
+    pub quit_armed_at: Option<std::time::Instant>,
+    pub show_day_separators: bool,
+    pub sources: Vec<String>,
+    /// Sources unchecked in the source panel; new sources start visible
+    pub hidden_sources: HashSet<String>,
+    pub selected_source_index: usize,
+    /// Only entries whose fields contain this key are shown, whatever its value
+    pub required_field: Option<String>,
+    /// Only entries of one trace, or one span and the spans started under it
+    pub trace_filter: Option<TraceFilter>,
+    /// Only entries whose target or message contains this plain substring are shown
+    pub grep_query: Option<String>,
+    /// Match `grep_query` exactly instead of ignoring ASCII case
+    pub grep_case_sensitive: bool,
+    /// Terms colored wherever they appear in messages, each in its own style. Unlike the
+    /// filters they hide nothing.
+    pub highlight_terms: Vec<(String, Style)>,
+    pub copy_template: EntryTemplate,
+    pub editor: String,
+    pub input: Option<InputPrompt>,
+    pub search_query: Option<String>,
+    /// Match targeted by `n`/`N`: index into `filtered_logs` and occurrence within its message
+    pub search_match: Option<(usize, usize)>,
+    pub timestamp_mode: TimestampMode,
+    pub line_number_mode: LineNumberMode,
+    /// Drawn dimmed in the level's color at the start of each continuation line of a
+    /// multi-line message; empty for plain indentation
+    pub continuation_marker: String,
+    /// Draw only the message of each entry, colored by level, without timestamp, level or target
+    pub message_only: bool,
+    /// Draw each entry as the lines it was parsed from instead of the reconstructed
+    /// `[ts] level target: message`; copies take the raw lines too
+    pub raw_view: bool,
+    /// Background of the focused entry, filled across the full width of the log area
+    pub focus_color: Color,
+    pub entry_separator: EntrySeparator,
+    /// Open command palette, taking every key until it closes
+    pub palette: Option<Palette>,
+    /// Recent-files picker shown when started without any input, taking every key
+    pub recent_files: Option<RecentFiles>,
+    /// Draw a header line with the inputs, format and follow state above everything else
+    pub show_header: bool,
+    /// Inputs named in the header, e.g. file paths, `stdin` or a listen address
+    pub input_label: String,
+    /// Input format named in the header
+    pub format_label: String,
+    /// Arguments recreating this session's inputs and parsing; `view_command` starts with them
+    pub launch_args: Vec<String>,
+    /// Modules to hide (with their subtrees) once they first appear, from `--hide-module`
+    pub pending_hidden_modules: Vec<String>,
+    /// Hold incoming entries back so the shown entries don't change while investigating
+    pub frozen: bool,
+    /// Entries received while frozen, added in order when thawed. Only the newest
+    /// `frozen_log_limit` are kept; `frozen_dropped` counts the rest.
+    pub frozen_logs: VecDeque<LogEntry>,
+    pub frozen_log_limit: usize,
+    pub frozen_dropped: usize,
+    /// Sources re-read while frozen and their new entries, applied when thawed
+    pub frozen_reloads: Vec<(String, Vec<LogEntry>)>,
+    /// Files followed by polling because native change events don't work for them
+    pub polled_files: Vec<String>,
+    /// Fractional-second digits shown for absolute timestamps; as written when `None`
+    pub timestamp_precision: Option<usize>,
+    pub max_target_width: Option<usize>,
+    pub compare_windows: [Option<StatsWindow>; 2],
+    /// Ids of the entries marked A (0) and B (1) to measure the time between them
+    pub entry_marks: [Option<u64>; 2],
+    /// Notes on entries by id, shown with a gutter marker
+    pub notes: HashMap<u64, String>,
+    /// Notes on file entries kept across sessions, and where they're saved
+    pub saved_notes: SavedNotes,
+    pub notes_path: Option<std::path::PathBuf>,
+    /// Whether reports and copied lines include the notes (`--export-notes`)
+    pub export_notes: bool,
+    /// `note_file` of each source seen, so files are resolved once
+    note_files: HashMap<String, Option<String>>,
+    pub show_compare: bool,
+    /// Show the details popup for the focused entry
+    pub show_details: bool,
+    /// Show the level-density strip on the right edge of the log
+    pub show_minimap: bool,
+    /// Where the minimap was last drawn, for mapping mouse clicks back to entries
+    pub minimap_area: Option<Rect>,
+    /// Show the severity pane above the main log list
+    pub show_split: bool,
+    /// Least severe level shown in the severity pane
+    pub split_min_level: String,
+    /// Recoverable error shown above the log until dismissed with `x`
+    pub error_banner: Option<String>,
+    /// Startup warning that none of the first input lines parsed, shown over everything
+    /// until any key is pressed
+    pub format_warning: Option<String>,
+    /// Field keys listed first in the details popup, in this order
+    pub pinned_fields: Vec<String>,
+    /// Columns between tab stops when tabs in messages are expanded for display
+    pub tab_width: usize,
+    /// Text a key handler wants copied; the main loop performs the copy and clears it
+    pub clipboard_request: Option<String>,
+    /// Set by the reload key; the main loop re-reads the input file and clears it
+    pub reload_requested: bool,
+    /// File picked in the recent-files picker; the main loop loads and watches it
+    pub open_file_request: Option<String>,
+    /// Report a key handler wants written; the main loop writes it and clears it
+    pub export_request: Option<ExportRequest>,
+    /// Line pattern the parser currently uses
+    pub parse_pattern: String,
+    /// First few raw input lines, used as samples by the pattern tester
+    pub raw_samples: Vec<String>,
+    pub pattern_tester: Option<PatternTester>,
+    /// Pattern accepted in the tester; the main loop swaps it into the parser and clears it
+    pub pattern_request: Option<String>,
+    /// Progress text shown as an overlay while the initial file is being loaded
+    pub loading: Option<String>,
+    /// Indicators shown before the help text and at the right end of the status bar
+    pub status_left: Vec<StatusSegment>,
+    pub status_right: Vec<StatusSegment>,
+    pub ingest_rate: IngestRate,
+    pub input_history: InputHistory,
+    pub history_path: Option<std::path::PathBuf>,
+    /// Per-level counts of `filtered_logs`, refreshed whenever the filter is re-run
+    pub visible_stats: LogStats,
+    /// Visible entries per module path, including those of submodules
+    pub module_counts: HashMap<String, usize>,
+}
+
+/// How long a first `q` stays armed when quitting needs confirmation
+pub const QUIT_CONFIRM_WINDOW: std::time::Duration = std::time::Duration::from_secs(1);
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -41,6 +228,184 @@ pub enum AppMode {
     LogNavigation,
     TextSelection,
     LogLevelFilter,
+    SourceFilter,
+}
+
+/// What the line-number gutter left of the log shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum)]
+pub enum LineNumberMode {
+    /// No gutter
+    #[default]
+    Off,
+    /// Position of the entry among the visible entries, starting at 1
+    Entry,
+    /// Line number of the entry in its source file, where known
+    Source,
+}
+
+impl LineNumberMode {
+    pub fn next(self) -> Self {
+        match self {
+            LineNumberMode::Off => LineNumberMode::Entry,
+            LineNumberMode::Entry => LineNumberMode::Source,
+            LineNumberMode::Source => LineNumberMode::Off,
+        }
+    }
+}
+
+/// How consecutive entries are set apart in the log list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum)]
+pub enum EntrySeparator {
+    /// Entries follow each other directly
+    #[default]
+    None,
+    /// A faint horizontal rule between entries
+    Rule,
+    /// Every other entry on a slightly lighter background
+    Zebra,
+}
+
+/// Background of every other entry with `EntrySeparator::Zebra`
+const ZEBRA_BACKGROUND: Color = Color::Indexed(236);
+
+/// What a committed input prompt is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    Search,
+    /// Name of a field of the focused entry whose value is copied
+    YankField,
+    /// Field name entries must carry to stay visible
+    FieldFilter,
+    /// Substring entries must contain in their target or message to stay visible
+    Grep,
+    /// Note attached to the focused entry
+    Note,
+    /// Term to highlight, or to stop highlighting when it already is
+    Highlight,
+}
+
+impl InputKind {
+    /// Prefix shown in front of the input line
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            InputKind::Search => "/",
+            InputKind::YankField => "yank field: ",
+            InputKind::FieldFilter => "has field: ",
+            InputKind::Grep => "grep: ",
+            InputKind::Note => "note: ",
+            InputKind::Highlight => "highlight: ",
+        }
+    }
+
+    /// Key under which committed values are remembered in the input history, or `None` for
+    /// prompts whose values aren't kept
+    pub fn history_key(&self) -> Option<&'static str> {
+        match self {
+            InputKind::Search => Some("search"),
+            InputKind::YankField => Some("yank-field"),
+            InputKind::FieldFilter => Some("field-filter"),
+            InputKind::Grep => Some("grep"),
+            // メモは入力履歴に残さない（履歴ファイルに書き出されるため）
+            InputKind::Note => None,
+            InputKind::Highlight => Some("highlight"),
+        }
+    }
+}
+
+/// A single-line text prompt. While one is active all keystrokes go to its buffer;
+/// Enter commits the buffer to `kind` and Esc discards it.
+#[derive(Debug, Clone)]
+pub struct InputPrompt {
+    pub kind: InputKind,
+    pub buffer: String,
+    /// Position while cycling through history with Up/Down (`None` = editing a new value)
+    pub history_index: Option<usize>,
+}
+
+/// Aggregated counts for a captured range of entries, compared in the compare view
+#[derive(Debug, Clone)]
+pub struct StatsWindow {
+    pub label: String,
+    pub stats: LogStats,
+}
+
+/// A report to write, built by a key handler and written by the main loop
+#[derive(Debug, Clone)]
+pub struct ExportRequest {
+    pub path: String,
+    pub content: String,
+    /// Entries in the report, for the status message
+    pub entries: usize,
+}
+
+/// Default `App::frozen_log_limit`
+pub const FROZEN_LOG_LIMIT: usize = 100_000;
+
+/// Backgrounds given to highlight terms, in the order they're added
+const HIGHLIGHT_COLORS: [Color; 6] = [Color::Cyan, Color::Magenta, Color::Green, Color::LightBlue, Color::LightMagenta, Color::LightCyan];
+
+/// Default `App::tab_width`, matching the usual terminal tab stops
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Field keys holding a trace id, tried in order by the trace filter
+pub const TRACE_ID_FIELDS: [&str; 3] = ["trace_id", "traceId", "trace.id"];
+/// Field keys holding a span id, used when an entry has no trace id
+pub const SPAN_ID_FIELDS: [&str; 3] = ["span_id", "spanId", "span.id"];
+/// Field keys holding the id of the span a span was started under
+pub const PARENT_SPAN_FIELDS: [&str; 3] = ["parent_span_id", "parentSpanId", "parent_id"];
+
+/// Entries kept by the trace filter: those whose `key` field is one of `ids`. For a
+/// span filter, `ids` grows with the spans whose parent is already in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFilter {
+    pub key: String,
+    /// The id the filter was started from
+    pub root: String,
+    pub ids: HashSet<String>,
+    /// Whether `key` is a span id, so descendant spans are followed
+    pub follows_children: bool,
+}
+
+impl TraceFilter {
+    /// Filter for the trace of `entry`, or its span and descendant spans when it has no
+    /// trace id. `None` when it has neither.
+    pub fn for_entry(entry: &LogEntry) -> Option<Self> {
+        let id_field = |keys: &[&str]| keys.iter().find_map(|key| Some((key.to_string(), entry.fields.get(*key)?.clone())));
+        let (key, root, follows_children) = match id_field(&TRACE_ID_FIELDS) {
+            Some((key, root)) => (key, root, false),
+            None => {
+                let (key, root) = id_field(&SPAN_ID_FIELDS)?;
+                (key, root, true)
+            }
+        };
+        Some(Self { ids: HashSet::from([root.clone()]), key, root, follows_children })
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        entry.fields.get(&self.key).is_some_and(|id| self.ids.contains(id))
+    }
+
+    /// Add the spans in `logs` started under a span already in the filter, repeating until
+    /// nothing changes so children logged before their parent are found too
+    pub fn add_children(&mut self, logs: &[LogEntry]) {
+        if !self.follows_children {
+            return;
+        }
+        loop {
+            let before = self.ids.len();
+            for log in logs {
+                let parent = PARENT_SPAN_FIELDS.iter().find_map(|key| log.fields.get(*key));
+                if parent.is_some_and(|parent| self.ids.contains(parent))
+                    && let Some(span) = log.fields.get(&self.key)
+                {
+                    self.ids.insert(span.clone());
+                }
+            }
+            if self.ids.len() == before {
+                break;
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -49,33 +414,57 @@ pub struct ModuleItem {
     pub full_path: String,
     pub level: usize,
     pub is_selected: bool,
+    pub min_level: Option<String>,
 }
 
 impl App {
-    /// Calculate the number of display lines for a log entry
-    fn calculate_display_lines(entry: &LogEntry) -> usize {
-        entry.message.lines().count()
+    /// Calculate the number of display lines for the filtered entry at `index`
+    fn calculate_display_lines(&self, index: usize) -> usize {
+        let message_lines = self.displayed_text(&self.filtered_logs[index]).lines().count().max(1);
+        if self.starts_new_day(index) || self.draws_rule_above(index) {
+            message_lines + 1
+        } else {
+            message_lines
+        }
     }
 
-    /// Find which entry corresponds to the current scroll position
-    fn find_entry_at_scroll_position(&self) -> usize {
-        let mut current_line = 0;
-        for (entry_idx, entry) in self.filtered_logs.iter().enumerate() {
-            let entry_lines = Self::calculate_display_lines(entry);
-            if current_line + entry_lines > self.log_scroll_position {
-                return entry_idx;
-            }
-            current_line += entry_lines;
+    /// Text drawn for `log`: its raw lines in the raw view, otherwise its message. Entries
+    /// without raw lines (e.g. deserialized from older records) keep their message.
+    fn displayed_text<'a>(&self, log: &'a LogEntry) -> &'a str {
+        if self.raw_view && !log.raw.is_empty() { &log.raw } else { &log.message }
+    }
+
+    /// Whether a separator rule is drawn above the filtered entry at `index`; a day
+    /// header already separates its entry, so no rule is drawn there
+    fn draws_rule_above(&self, index: usize) -> bool {
+        self.entry_separator == EntrySeparator::Rule && index > 0 && !self.starts_new_day(index)
+    }
+
+    /// Total number of display lines for all filtered entries
+    fn total_display_lines(&self) -> usize {
+        (0..self.filtered_logs.len())
+            .map(|index| self.calculate_display_lines(index))
+            .sum()
+    }
+
+    /// Whether a day-boundary header should be drawn above the filtered entry at `index`
+    fn starts_new_day(&self, index: usize) -> bool {
+        if !self.show_day_separators || index == 0 {
+            return false;
+        }
+        match (
+            date_part(&self.filtered_logs[index - 1].timestamp),
+            date_part(&self.filtered_logs[index].timestamp),
+        ) {
+            (Some(previous), Some(current)) => previous != current,
+            _ => false,
         }
-        self.filtered_logs.len().saturating_sub(1)
     }
 
     /// Calculate the display line position of a specific entry
     fn get_entry_display_position(&self, entry_index: usize) -> usize {
-        self.filtered_logs
-            .iter()
-            .take(entry_index)
-            .map(Self::calculate_display_lines)
+        (0..entry_index.min(self.filtered_logs.len()))
+            .map(|index| self.calculate_display_lines(index))
             .sum()
     }
 
@@ -91,7 +480,7 @@ impl App {
         }
 
         let focused_entry_start = self.get_entry_display_position(self.current_log_line);
-        let focused_entry_end = focused_entry_start + Self::calculate_display_lines(&self.filtered_logs[self.current_log_line]);
+        let focused_entry_end = focused_entry_start + self.calculate_display_lines(self.current_log_line);
         let scroll_end = self.log_scroll_position + visible_lines;
         
         // Calculate display positions for buffer entries
@@ -103,7 +492,7 @@ impl App {
         
         let next_entry_end = if self.current_log_line < self.filtered_logs.len() - 1 {
             let next_entry_start = self.get_entry_display_position(self.current_log_line + 1);
-            next_entry_start + Self::calculate_display_lines(&self.filtered_logs[self.current_log_line + 1])
+            next_entry_start + self.calculate_display_lines(self.current_log_line + 1)
         } else {
             focused_entry_end
         };
@@ -114,11 +503,7 @@ impl App {
         }
         // If we can't see the next entry, scroll down to show it
         else if next_entry_end > scroll_end && self.current_log_line < self.filtered_logs.len() - 1 {
-            let total_display_lines = self.filtered_logs
-                .iter()
-                .map(Self::calculate_display_lines)
-                .sum::<usize>();
-            let max_scroll = total_display_lines.saturating_sub(visible_lines);
+            let max_scroll = self.total_display_lines().saturating_sub(visible_lines);
             
             // Position so that the next entry is fully visible at the bottom
             let target_scroll = next_entry_end - visible_lines;
@@ -138,7 +523,7 @@ impl App {
         }
 
         let focused_entry_start = self.get_entry_display_position(self.current_log_line);
-        let focused_entry_end = focused_entry_start + Self::calculate_display_lines(&self.filtered_logs[self.current_log_line]);
+        let focused_entry_end = focused_entry_start + self.calculate_display_lines(self.current_log_line);
         let scroll_end = self.log_scroll_position + visible_lines;
         
         // If focus is completely off screen, find the best visible entry
@@ -146,24 +531,21 @@ impl App {
             // Find the closest visible entry to the current focus
             let mut best_entry = self.current_log_line;
             let mut best_distance = usize::MAX;
+            let mut entry_start = 0;
             
-            for (entry_idx, entry) in self.filtered_logs.iter().enumerate() {
-                let entry_start = self.get_entry_display_position(entry_idx);
-                let entry_end = entry_start + Self::calculate_display_lines(entry);
+            for entry_idx in 0..self.filtered_logs.len() {
+                let entry_end = entry_start + self.calculate_display_lines(entry_idx);
                 
                 // Check if this entry is visible
                 if entry_start < scroll_end && entry_end > self.log_scroll_position {
-                    let distance = if entry_idx > self.current_log_line {
-                        entry_idx - self.current_log_line
-                    } else {
-                        self.current_log_line - entry_idx
-                    };
+                    let distance = entry_idx.abs_diff(self.current_log_line);
                     
                     if distance < best_distance {
                         best_distance = distance;
                         best_entry = entry_idx;
                     }
                 }
+                entry_start = entry_end;
             }
             
             self.current_log_line = best_entry;
@@ -182,8 +564,8 @@ impl App {
         let mut end_entry = 0;
         let mut found_start = false;
 
-        for (entry_idx, entry) in self.filtered_logs.iter().enumerate() {
-            let entry_lines = Self::calculate_display_lines(entry);
+        for entry_idx in 0..self.filtered_logs.len() {
+            let entry_lines = self.calculate_display_lines(entry_idx);
             
             // Find start entry
             if !found_start && current_line + entry_lines > scroll_position {
@@ -213,28 +595,114 @@ impl App {
     pub fn new() -> Self {
         let mut app = Self {
             module_tree: ModuleTree::new("root".to_string()),
+            max_module_depth: None,
             logs: Vec::new(),
             filtered_logs: Vec::new(),
-            selected_module_index: 0,
             log_scroll_position: 0,
             module_list_state: ListState::default(),
             module_items: Vec::new(),
+            module_visual_anchor: None,
             should_quit: false,
             current_log_line: 0,
             selection_start: None,
             selection_end: None,
+            next_entry_id: 0,
             mode: AppMode::ModuleSelection,
-            copy_message: None,
+            status_message: None,
             auto_follow: true,
             filter_dirty: true,
             last_filter_hash: 0,
+            filtered_generation: 0,
+            log_view_cache: None,
+            target_cache: HashMap::new(),
+            target_cache_tree_hash: 0,
             last_terminal_size: (0, 0),
             log_level_filter: ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"].iter().map(|s| s.to_string()).collect(),
             available_log_levels: vec!["ERROR".to_string(), "WARN".to_string(), "INFO".to_string(), "DEBUG".to_string(), "TRACE".to_string()],
+            severity: SeverityOrder::default(),
             selected_log_level_index: 0,
             show_filter_panel: true,
+            keep_filter_panel: false,
+            compact_layout: false,
             filter_panel_width: 25,
             last_action_was_focus_move: false,
+            scroll_step: 3,
+            mouse_bindings: MouseBindings::default(),
+            key_bindings: Vec::new(),
+            theme: Theme::default(),
+            follow_stickiness: None,
+            log_view_height: 0,
+            confirm_quit: false,
+            quit_armed_at: None,
+            show_day_separators: false,
+            sources: Vec::new(),
+            hidden_sources: HashSet::new(),
+            required_field: None,
+            trace_filter: None,
+            grep_query: None,
+            grep_case_sensitive: false,
+            highlight_terms: Vec::new(),
+            selected_source_index: 0,
+            copy_template: EntryTemplate::default(),
+            editor: "vi".to_string(),
+            input: None,
+            search_query: None,
+            search_match: None,
+            timestamp_mode: TimestampMode::Absolute,
+            line_number_mode: LineNumberMode::Off,
+            continuation_marker: String::new(),
+            message_only: false,
+            raw_view: false,
+            focus_color: Color::Blue,
+            entry_separator: EntrySeparator::None,
+            palette: None,
+            recent_files: None,
+            show_header: false,
+            input_label: String::new(),
+            format_label: String::new(),
+            launch_args: Vec::new(),
+            pending_hidden_modules: Vec::new(),
+            frozen: false,
+            frozen_logs: VecDeque::new(),
+            frozen_log_limit: FROZEN_LOG_LIMIT,
+            frozen_dropped: 0,
+            frozen_reloads: Vec::new(),
+            polled_files: Vec::new(),
+            timestamp_precision: None,
+            max_target_width: None,
+            compare_windows: [None, None],
+            entry_marks: [None, None],
+            notes: HashMap::new(),
+            saved_notes: SavedNotes::default(),
+            notes_path: None,
+            export_notes: false,
+            note_files: HashMap::new(),
+            show_compare: false,
+            show_details: false,
+            show_minimap: false,
+            minimap_area: None,
+            show_split: false,
+            split_min_level: "WARN".to_string(),
+            error_banner: None,
+            format_warning: None,
+            pinned_fields: Vec::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            clipboard_request: None,
+            reload_requested: false,
+            open_file_request: None,
+            export_request: None,
+            parse_pattern: DEFAULT_PATTERN.to_string(),
+            raw_samples: Vec::new(),
+            pattern_tester: None,
+            pattern_request: None,
+            loading: None,
+            status_left: Vec::new(),
+            status_right: vec![StatusSegment::Filters],
+            ingest_rate: IngestRate::default(),
+            input_history: InputHistory::default(),
+            history_path: None,
+            visible_stats: LogStats::default(),
+            module_counts: HashMap::new(),
         };
         app.module_list_state.select(Some(0));
         app
@@ -242,13 +710,21 @@ impl App {
 
     pub fn update_logs(&mut self, logs: Vec<LogEntry>) {
         let old_log_count = self.filtered_logs.len();
+        let follow = self.follows_new_entries();
         self.logs = logs;
+        self.assign_entry_ids(0);
+        // 番号を振り直したので、ファイル上の位置で保存したメモだけを付け直す
+        self.notes.clear();
+        self.attach_saved_notes(0);
+        self.sources.clear();
+        self.register_sources(0);
+        self.register_levels(0);
         self.rebuild_module_tree();
         self.filter_dirty = true;  // フィルタ再実行を強制
         self.filter_logs();
         
         // 新しいログが追加されたときの自動追従
-        if self.auto_follow && self.filtered_logs.len() > old_log_count {
+        if follow && self.filtered_logs.len() > old_log_count {
             self.scroll_to_bottom();
         }
     }
@@ -260,43 +736,183 @@ impl App {
         if new_log_count == 0 {
             return;
         }
+        if self.frozen {
+            self.frozen_logs.extend(new_logs);
+            // 上限を超えた分は古いものから捨てる
+            let excess = self.frozen_logs.len().saturating_sub(self.frozen_log_limit);
+            self.frozen_logs.drain(..excess);
+            self.frozen_dropped += excess;
+            return;
+        }
         
+        self.ingest_rate.record(std::time::Instant::now(), new_log_count);
+        let follow = self.follows_new_entries();
+
         // 新しいログを追加
         self.logs.extend(new_logs);
+        self.assign_entry_ids(self.logs.len() - new_log_count);
+        self.attach_saved_notes(self.logs.len() - new_log_count);
+        self.register_sources(self.logs.len() - new_log_count);
+        self.register_levels(self.logs.len() - new_log_count);
         
         // 新しいモジュールのみを追加
         for log in &self.logs[(self.logs.len() - new_log_count)..] {
             self.module_tree.insert_module(&log.target);
         }
+        self.hide_pending_modules();
         
         self.rebuild_module_items();
         
         // 新しいログのみをフィルタリングして効率化
-        let new_filtered_logs: Vec<LogEntry> = self.logs[(self.logs.len() - new_log_count)..]
-            .iter()
-            .filter(|log| {
-                self.module_tree.is_module_selected(&log.target) && 
-                self.log_level_filter.contains(&log.level)
-            })
-            .cloned()
-            .collect();
+        if let Some(trace) = &mut self.trace_filter {
+            trace.add_children(&self.logs[(self.logs.len() - new_log_count)..]);
+        }
+        let mut target_cache = self.take_target_cache();
+        let new_filtered_logs = self.visible_entries(&self.logs[(self.logs.len() - new_log_count)..], &mut target_cache);
+        self.target_cache = target_cache;
         
+        let first_new = self.filtered_logs.len();
         self.filtered_logs.extend(new_filtered_logs);
+        self.filtered_generation += 1;
+        self.tally_visible(first_new);
         
         // 新しいログが追加されたときの自動追従
-        if self.auto_follow && self.filtered_logs.len() > old_log_count {
+        if follow && self.filtered_logs.len() > old_log_count {
             self.scroll_to_bottom();
         }
     }
 
+    /// Whether entries arriving now should scroll the view to the bottom
+    fn follows_new_entries(&self) -> bool {
+        match self.follow_stickiness {
+            None => self.auto_follow,
+            // 画面の最下行から最終行までの距離で判断し、離れていれば表示位置を動かさない
+            Some(lines) => {
+                let last_line = self.total_display_lines().saturating_sub(1);
+                let bottom_line = self.log_scroll_position + self.log_view_height.saturating_sub(1);
+                last_line.saturating_sub(bottom_line) <= lines
+            }
+        }
+    }
+
+    /// Replace every entry read from `source` with `logs`, merged by timestamp with the
+    /// entries from other sources as on startup. Module selection and level thresholds are
+    /// kept by path.
+    pub fn reload_source(&mut self, source: &str, logs: Vec<LogEntry>) {
+        // 読み直した内容に含まれるので、凍結中に受け取った分は捨てる
+        self.frozen_logs.retain(|log| log.source != source);
+        if self.frozen {
+            // 凍結中は表示を変えず、解除したときに反映する
+            self.frozen_reloads.retain(|(reloaded, _)| reloaded != source);
+            self.frozen_reloads.push((source.to_string(), logs));
+            return;
+        }
+        let others: Vec<LogEntry> = self.logs.iter().filter(|log| log.source != source).cloned().collect();
+        self.update_logs(merge_by_timestamp(vec![logs, others]));
+    }
+
+    fn assign_entry_ids(&mut self, start: usize) {
+        for log in &mut self.logs[start..] {
+            log.id = self.next_entry_id;
+            self.next_entry_id += 1;
+        }
+    }
+
+    /// Pick up the saved notes of file entries in `logs[start..]`
+    fn attach_saved_notes(&mut self, start: usize) {
+        if self.saved_notes.is_empty() {
+            return;
+        }
+        for log in &self.logs[start..] {
+            let Some(offset) = log.byte_offset else {
+                continue;
+            };
+            let file = self.note_files.entry(log.source.clone()).or_insert_with(|| note_file(&log.source));
+            if let Some(note) = file.as_deref().and_then(|file| self.saved_notes.get(file, offset, &log.message)) {
+                self.notes.insert(log.id, note.to_string());
+            }
+        }
+    }
+
+    /// Record any sources seen in `logs[start..]` that aren't known yet
+    fn register_sources(&mut self, start: usize) {
+        for log in &self.logs[start..] {
+            if !self.sources.contains(&log.source) {
+                self.sources.push(log.source.clone());
+            }
+        }
+    }
+
+    /// Add levels seen in `logs[start..]` that the level filter doesn't list yet, in severity
+    /// order. A new level starts enabled when some level at least as low on the ladder is
+    /// enabled, so "show everything" and thresholds keep their meaning.
+    fn register_levels(&mut self, start: usize) {
+        for index in start..self.logs.len() {
+            let level = &self.logs[index].level;
+            if self.available_log_levels.contains(level) {
+                continue;
+            }
+            let level = level.clone();
+            let ladder_len = self.severity.levels().len();
+            let rank = self.severity.rank(&level).min(ladder_len.saturating_sub(1));
+            if self.log_level_filter.iter().any(|enabled| self.severity.rank(enabled) >= rank) {
+                self.log_level_filter.insert(level.clone());
+            }
+            let position = self
+                .available_log_levels
+                .partition_point(|known| (self.severity.rank(known), known.as_str()) <= (self.severity.rank(&level), level.as_str()));
+            self.available_log_levels.insert(position, level);
+        }
+    }
+
+    /// Replace the severity ladder, listing and enabling exactly its levels (plus any other
+    /// level already seen) in the level filter
+    pub fn set_severity_order(&mut self, severity: SeverityOrder) {
+        self.available_log_levels = severity.levels().to_vec();
+        self.log_level_filter = self.available_log_levels.iter().cloned().collect();
+        self.severity = severity;
+        self.register_levels(0);
+        self.filter_dirty = true;
+        self.filter_logs();
+    }
+
     fn rebuild_module_tree(&mut self) {
-        self.module_tree = ModuleTree::new("root".to_string());
+        // 再構築後も同じパスのモジュールの選択状態としきい値を引き継ぐ
+        let previous: Vec<ModuleItem> = std::mem::take(&mut self.module_items);
+        self.module_tree = ModuleTree::new("root".to_string()).with_max_depth(self.max_module_depth);
         for log in &self.logs {
             self.module_tree.insert_module(&log.target);
         }
+        // module_itemsは親が先に並ぶので、子の状態が親からの伝播で上書きされない
+        for item in &previous {
+            self.module_tree.set_selection(&item.full_path, item.is_selected);
+            if item.min_level.is_some() {
+                self.module_tree.set_min_level(&item.full_path, item.min_level.clone());
+            }
+        }
+        self.hide_pending_modules();
         self.rebuild_module_items();
     }
 
+    /// Deselect the pending `--hide-module` paths that exist in the tree by now
+    fn hide_pending_modules(&mut self) {
+        let module_tree = &mut self.module_tree;
+        self.pending_hidden_modules.retain(|path| {
+            if !module_tree.contains(path) {
+                return true;
+            }
+            module_tree.set_selection(path, false);
+            false
+        });
+    }
+
+    pub fn set_max_module_depth(&mut self, max_depth: Option<usize>) {
+        self.max_module_depth = max_depth;
+        self.rebuild_module_tree();
+        self.filter_dirty = true;
+        self.filter_logs();
+    }
+
     fn rebuild_module_items(&mut self) {
         self.module_items.clear();
         let tree_clone = self.module_tree.clone();
@@ -316,6 +932,7 @@ impl App {
                 full_path: full_path.clone(),
                 level,
                 is_selected: node.is_selected,
+                min_level: node.min_level.clone(),
             };
             self.module_items.push(item);
 
@@ -347,10 +964,185 @@ impl App {
         for level in levels {
             level.hash(&mut hasher);
         }
+
+        let mut hidden_sources: Vec<_> = self.hidden_sources.iter().collect();
+        hidden_sources.sort();
+        hidden_sources.hash(&mut hasher);
+        self.required_field.hash(&mut hasher);
+        if let Some(trace) = &self.trace_filter {
+            (&trace.key, &trace.root).hash(&mut hasher);
+        }
+        self.grep_query.hash(&mut hasher);
+        self.grep_case_sensitive.hash(&mut hasher);
         
         hasher.finish()
     }
 
+    /// Filter predicate shared by `filter_logs` and `add_logs`. A per-module level
+    /// threshold, when set, replaces the global level filter for that module.
+    ///
+    /// The module tree lookups are memoized per distinct target in `target_cache`, and the
+    /// cheap level check runs first when no thresholds are set.
+    fn visible_entries(&self, logs: &[LogEntry], target_cache: &mut HashMap<String, (bool, Option<String>)>) -> Vec<LogEntry> {
+        let has_thresholds = self.module_tree.has_min_levels();
+        let mut visible = Vec::new();
+        for log in logs {
+            if self.hidden_sources.contains(&log.source) {
+                continue;
+            }
+            if let Some(field) = &self.required_field
+                && !log.fields.contains_key(field)
+            {
+                continue;
+            }
+            if self.trace_filter.as_ref().is_some_and(|trace| !trace.matches(log)) {
+                continue;
+            }
+            // しきい値がなければレベルだけで判定でき、ツリーをたどる必要がない
+            if !has_thresholds && !self.log_level_filter.contains(&log.level) {
+                continue;
+            }
+            if !target_cache.contains_key(&log.target) {
+                let decision = (
+                    self.module_tree.is_module_selected(&log.target),
+                    self.module_tree.module_min_level(&log.target).map(str::to_string),
+                );
+                target_cache.insert(log.target.clone(), decision);
+            }
+            let (selected, min_level) = &target_cache[&log.target];
+            let level_visible = match min_level {
+                Some(min_level) => self.severity.rank(&log.level) <= self.severity.rank(min_level),
+                None => self.log_level_filter.contains(&log.level),
+            };
+            if !*selected || !level_visible {
+                continue;
+            }
+            // 文字列の検索は最も重いので最後に行う
+            if let Some(query) = &self.grep_query
+                && !contains_text(&log.target, query, self.grep_case_sensitive)
+                && !contains_text(&log.message, query, self.grep_case_sensitive)
+            {
+                continue;
+            }
+            visible.push(log.clone());
+        }
+        visible
+    }
+
+    /// Focus the visible entry with id `id`, or the closest visible entry before it when it
+    /// was filtered out (the first entry when none precedes it), keeping the focus on the same
+    /// logical line while indices shift
+    pub fn refocus_by_id(&mut self, id: u64) {
+        if self.filtered_logs.is_empty() {
+            return;
+        }
+        // filtered_logsはIDの昇順に並んでいる
+        self.current_log_line = self.filtered_logs.partition_point(|log| log.id <= id).saturating_sub(1);
+        self.last_action_was_focus_move = true;
+    }
+
+    /// Focus the visible entry nearest to the filtered-out entry `id`: whichever neighbour
+    /// is closer in time, or the earlier one when timestamps can't tell
+    fn refocus_near_hidden(&mut self, id: u64, timestamp: &str) {
+        if self.filtered_logs.is_empty() {
+            return;
+        }
+        let next = self.filtered_logs.partition_point(|log| log.id < id);
+        let distance = |index: usize| {
+            let other = parse_timestamp(&self.filtered_logs.get(index)?.timestamp)?;
+            Some((other - parse_timestamp(timestamp)?).abs())
+        };
+        self.current_log_line = match next.checked_sub(1) {
+            None => next,
+            Some(previous) => match (distance(previous), distance(next)) {
+                (Some(before), Some(after)) if after < before => next,
+                _ => previous,
+            },
+        };
+        self.last_action_was_focus_move = true;
+        self.status_message = Some("The focused entry was filtered out; moved to the nearest visible entry".to_string());
+    }
+
+    /// Focus the entry `anchor` points at (as in `--goto`), or the visible entry nearest to it when
+    /// the filters hide it
+    pub fn goto_entry(&mut self, anchor: &EntryAnchor) {
+        let Some(index) = self.resolve_anchor(anchor) else {
+            self.status_message = Some(format!("No entry {}", anchor));
+            return;
+        };
+        let id = self.logs[index].id;
+        match self.filtered_logs.binary_search_by_key(&id, |log| log.id) {
+            Ok(position) => self.focus_entry(position),
+            Err(_) => {
+                let timestamp = self.logs[index].timestamp.clone();
+                self.refocus_near_hidden(id, &timestamp);
+                self.auto_follow = false;
+                self.status_message = Some(format!("Entry {} is hidden by the filters", anchor));
+            }
+        }
+    }
+
+    /// Index in `logs` of the entry `anchor` points at. A line anchor resolves to the entry
+    /// of that file covering the line, so lines inside multi-line messages work too.
+    fn resolve_anchor(&self, anchor: &EntryAnchor) -> Option<usize> {
+        match anchor {
+            EntryAnchor::Id(id) => self.logs.binary_search_by_key(id, |log| log.id).ok(),
+            EntryAnchor::Line { file, line } => self
+                .logs
+                .iter()
+                .enumerate()
+                .filter(|(_, log)| log.line_number.is_some_and(|start| start <= *line) && anchor_file(&log.source) == *file)
+                .max_by_key(|(_, log)| log.line_number)
+                .map(|(index, _)| index),
+        }
+    }
+
+    /// Point the selection at the visible entries between ids `low` and `high`, shrinking it
+    /// to the nearest visible entries when an endpoint was filtered out. `forward` tells
+    /// whether the cursor end (`selection_end`) is the later one.
+    fn restore_selection(&mut self, low: u64, high: u64, forward: bool) {
+        if self.filtered_logs.is_empty() {
+            self.selection_start = None;
+            self.selection_end = None;
+            return;
+        }
+        // filtered_logsはIDの昇順に並んでいる
+        let last = self.filtered_logs.len() - 1;
+        let start = self.filtered_logs.partition_point(|log| log.id < low).min(last);
+        let end = self.filtered_logs.partition_point(|log| log.id <= high).saturating_sub(1).max(start);
+        let (anchor, cursor) = if forward { (start, end) } else { (end, start) };
+        self.selection_start = Some(anchor);
+        self.selection_end = Some(cursor);
+        if self.mode == AppMode::TextSelection {
+            self.current_log_line = cursor;
+        }
+    }
+
+    /// Take the per-target cache for a filter pass, emptied first if the module tree
+    /// changed since it was filled; put it back in `target_cache` afterwards
+    fn take_target_cache(&mut self) -> HashMap<String, (bool, Option<String>)> {
+        let mut hasher = DefaultHasher::new();
+        self.module_tree.hash(&mut hasher);
+        let tree_hash = hasher.finish();
+        let mut target_cache = std::mem::take(&mut self.target_cache);
+        if tree_hash != self.target_cache_tree_hash {
+            target_cache.clear();
+            self.target_cache_tree_hash = tree_hash;
+        }
+        target_cache
+    }
+
+    /// Add `filtered_logs[start..]` to the cached level and module counts
+    fn tally_visible(&mut self, start: usize) {
+        for log in &self.filtered_logs[start..] {
+            self.visible_stats.add(log);
+            let ancestors = log.target.match_indices("::").map(|(position, _)| &log.target[..position]);
+            for path in ancestors.chain(std::iter::once(log.target.as_str())) {
+                *self.module_counts.entry(path.to_string()).or_default() += 1;
+            }
+        }
+    }
+
     pub fn filter_logs(&mut self) {
         let current_hash = self.calculate_filter_hash();
         
@@ -359,17 +1151,44 @@ impl App {
             return;
         }
         
-        self.filtered_logs.clear();
-        self.filtered_logs.extend(
-            self.logs
-                .iter()
-                .filter(|log| {
-                    self.module_tree.is_module_selected(&log.target) && 
-                    self.log_level_filter.contains(&log.level)
-                })
-                .cloned()
-        );
+        // 選択範囲はエントリのIDで覚えておき、絞り込み後の位置に置き直す
+        let selection_ids = self.selection_start.zip(self.selection_end).and_then(|(start, end)| {
+            let low = self.filtered_logs.get(start.min(end))?.id;
+            let high = self.filtered_logs.get(start.max(end))?.id;
+            Some((low, high, start <= end))
+        });
+        // 追従中でなければフォーカスもIDで覚えておく
+        let focused = self
+            .filtered_logs
+            .get(self.current_log_line)
+            .filter(|_| !self.auto_follow)
+            .map(|log| (log.id, log.timestamp.clone()));
+        if let Some(trace) = &mut self.trace_filter {
+            trace.add_children(&self.logs);
+        }
+        let mut target_cache = self.take_target_cache();
+        self.filtered_logs = self.visible_entries(&self.logs, &mut target_cache);
+        self.filtered_generation += 1;
+        // 絞り込み直すとインデックスがずれるので、現在のマッチは次のn/Nで選び直す
+        self.search_match = None;
+        self.target_cache = target_cache;
+        self.visible_stats = LogStats::default();
+        self.module_counts.clear();
+        self.tally_visible(0);
         
+        if let Some((low, high, forward)) = selection_ids {
+            self.restore_selection(low, high, forward);
+        } else if let Some((id, timestamp)) = focused
+            && self.logs.first().is_some_and(|log| log.id <= id)
+        {
+            // 再読み込みでIDが振り直された場合は対象がないので、位置をそのまま保つ
+            if self.filtered_logs.binary_search_by_key(&id, |log| log.id).is_ok() {
+                self.refocus_by_id(id);
+            } else {
+                self.refocus_near_hidden(id, &timestamp);
+            }
+        }
+
         // Ensure current_log_line is within bounds after filtering
         if self.current_log_line >= self.filtered_logs.len() {
             self.current_log_line = self.filtered_logs.len().saturating_sub(1);
@@ -380,15 +1199,76 @@ impl App {
     }
 
     pub fn toggle_selected_module(&mut self) {
-        if let Some(selected_index) = self.module_list_state.selected() {
-            if !self.module_items.is_empty() && selected_index < self.module_items.len() {
-                let module_path = self.module_items[selected_index].full_path.clone();
-                self.module_tree.toggle_selection(&module_path);
-                self.rebuild_module_items();
-                self.filter_dirty = true;
-                self.filter_logs();
-            }
+        if let Some(selected_index) = self.module_list_state.selected()
+            && !self.module_items.is_empty()
+            && selected_index < self.module_items.len()
+        {
+            let module_path = self.module_items[selected_index].full_path.clone();
+            self.module_tree.toggle_selection(&module_path);
+            self.rebuild_module_items();
+            self.filter_dirty = true;
+            self.filter_logs();
+        }
+    }
+
+    /// Start marking a range of modules from the cursor
+    pub fn start_module_visual(&mut self) {
+        if !self.module_items.is_empty() {
+            self.module_visual_anchor = Some(self.module_list_state.selected().unwrap_or(0));
+        }
+    }
+
+    pub fn cancel_module_visual(&mut self) {
+        self.module_visual_anchor = None;
+    }
+
+    /// Inclusive range of `module_items` covered by the visual selection
+    pub fn module_visual_range(&self) -> Option<(usize, usize)> {
+        let anchor = self.module_visual_anchor?;
+        let cursor = self.module_list_state.selected().unwrap_or(0);
+        Some((anchor.min(cursor), anchor.max(cursor).min(self.module_items.len().saturating_sub(1))))
+    }
+
+    /// Toggle every module in the visual range: select them all unless all are already
+    /// selected, in which case deselect them. The filter is re-run once.
+    pub fn toggle_module_visual_range(&mut self) {
+        let Some((start, end)) = self.module_visual_range() else {
+            return;
+        };
+        self.module_visual_anchor = None;
+        let Some(items) = self.module_items.get(start..=end) else {
+            return;
+        };
+        let select = !items.iter().all(|item| item.is_selected);
+        let paths: Vec<String> = items.iter().map(|item| item.full_path.clone()).collect();
+        for path in &paths {
+            self.module_tree.set_selection(path, select);
         }
+        self.rebuild_module_items();
+        self.filter_dirty = true;
+        self.filter_logs();
+        self.status_message = Some(format!(
+            "{} {} modules",
+            if select { "Selected" } else { "Deselected" },
+            paths.len()
+        ));
+    }
+
+    /// Cycle the selected module's level threshold: none → ERROR → WARN → … → TRACE → none
+    pub fn cycle_selected_module_min_level(&mut self) {
+        let Some(item) = self.module_list_state.selected().and_then(|index| self.module_items.get(index)) else {
+            return;
+        };
+        let module_path = item.full_path.clone();
+        let levels = self.severity.levels();
+        let next = match item.min_level.as_deref() {
+            None => levels.first(),
+            Some(current) => levels.get(self.severity.rank(current) + 1),
+        };
+        self.module_tree.set_min_level(&module_path, next.cloned());
+        self.rebuild_module_items();
+        self.filter_dirty = true;
+        self.filter_logs();
     }
 
     pub fn next_module(&mut self) {
@@ -411,16 +1291,47 @@ impl App {
         }
     }
 
+    /// Quit, or with `confirm_quit` set, only arm the quit until `q` is pressed again
+    /// within `QUIT_CONFIRM_WINDOW`
     pub fn quit(&mut self) {
-        self.should_quit = true;
+        if !self.confirm_quit {
+            self.should_quit = true;
+            return;
+        }
+        match self.quit_armed_at {
+            Some(armed_at) if armed_at.elapsed() <= QUIT_CONFIRM_WINDOW => self.should_quit = true,
+            _ => {
+                self.quit_armed_at = Some(std::time::Instant::now());
+                self.status_message = Some("Press q again to quit".to_string());
+            }
+        }
     }
 
     pub fn switch_to_log_mode(&mut self) {
         self.mode = AppMode::LogNavigation;
-        self.show_filter_panel = false;
+        self.show_filter_panel = self.keep_filter_panel;
+    }
+
+    /// Show or hide the filter panel without moving focus, and keep that choice when focus
+    /// moves between the panel and the log view. Hiding it while a panel list has focus
+    /// moves focus to the log view.
+    pub fn toggle_filter_panel(&mut self) {
+        self.show_filter_panel = !self.show_filter_panel;
+        self.keep_filter_panel = self.show_filter_panel;
+        if self.show_filter_panel && self.compact_layout {
+            self.status_message = Some("Terminal too small for the filter panel".to_string());
+        }
+        if !self.show_filter_panel && matches!(self.mode, AppMode::ModuleSelection | AppMode::LogLevelFilter | AppMode::SourceFilter) {
+            self.mode = AppMode::LogNavigation;
+            self.module_visual_anchor = None;
+        }
     }
 
     pub fn switch_to_module_mode(&mut self) {
+        if self.compact_layout {
+            self.status_message = Some("Terminal too small for the filter panel".to_string());
+            return;
+        }
         self.mode = AppMode::ModuleSelection;
         self.show_filter_panel = true;
     }
@@ -435,7 +1346,7 @@ impl App {
         self.selection_start = None;
         self.selection_end = None;
         self.mode = AppMode::LogNavigation;
-        self.copy_message = None;
+        self.status_message = None;
     }
 
     /// Move focus to next entry and auto-scroll if needed
@@ -480,10 +1391,7 @@ impl App {
     /// Scroll display area without changing focus
     pub fn scroll_down(&mut self, lines: usize) {
         if !self.filtered_logs.is_empty() {
-            let total_display_lines = self.filtered_logs
-                .iter()
-                .map(Self::calculate_display_lines)
-                .sum::<usize>();
+            let total_display_lines = self.total_display_lines();
             
             let max_scroll = total_display_lines.saturating_sub(1);
             self.log_scroll_position = (self.log_scroll_position + lines).min(max_scroll);
@@ -503,10 +1411,41 @@ impl App {
         self.last_action_was_focus_move = false;
     }
 
+    /// Mouse wheel up: scroll the view by `scroll_step`, leaving focus alone
+    pub fn wheel_up(&mut self) {
+        self.scroll_up(self.scroll_step);
+    }
+
+    /// Mouse wheel down: scroll the view by `scroll_step`, leaving focus alone
+    pub fn wheel_down(&mut self) {
+        self.scroll_down(self.scroll_step);
+    }
 
+    /// Scroll up a page of `visible_lines` display lines, keeping one line of the old page.
+    /// Like every scroll position this counts the rows `calculate_display_lines` gives each
+    /// entry, not entries.
     pub fn page_up(&mut self, visible_lines: usize) {
-        let scroll_amount = visible_lines.saturating_sub(1).max(1);
-        self.log_scroll_position = self.log_scroll_position.saturating_sub(scroll_amount);
+        self.page_scroll_up(visible_lines.saturating_sub(1).max(1));
+    }
+
+    /// Scroll down a page of `visible_lines` display lines; see `page_up`
+    pub fn page_down(&mut self, visible_lines: usize) {
+        self.page_scroll_down(visible_lines.saturating_sub(1).max(1), visible_lines);
+    }
+
+    /// Scroll up half a page of `visible_lines` display lines (Ctrl-u)
+    pub fn half_page_up(&mut self, visible_lines: usize) {
+        self.page_scroll_up((visible_lines / 2).max(1));
+    }
+
+    /// Scroll down half a page of `visible_lines` display lines (Ctrl-d)
+    pub fn half_page_down(&mut self, visible_lines: usize) {
+        self.page_scroll_down((visible_lines / 2).max(1), visible_lines);
+    }
+
+    /// Move the view up `amount` display lines for the paging motions
+    fn page_scroll_up(&mut self, amount: usize) {
+        self.log_scroll_position = self.log_scroll_position.saturating_sub(amount);
         self.auto_follow = false;
         
         // Mark this as a scroll action
@@ -517,16 +1456,13 @@ impl App {
         }
     }
 
-    pub fn page_down(&mut self, visible_lines: usize) {
-        let total_display_lines = self.filtered_logs
-            .iter()
-            .map(Self::calculate_display_lines)
-            .sum::<usize>();
-        
-        let scroll_amount = visible_lines.saturating_sub(1).max(1);
+    /// Move the view down `amount` display lines for the paging motions, stopping once the
+    /// last line reaches the bottom of a view `visible_lines` high
+    fn page_scroll_down(&mut self, amount: usize, visible_lines: usize) {
+        let total_display_lines = self.total_display_lines();
         let max_scroll = total_display_lines.saturating_sub(visible_lines.min(total_display_lines));
         
-        self.log_scroll_position = (self.log_scroll_position + scroll_amount).min(max_scroll);
+        self.log_scroll_position = (self.log_scroll_position + amount).min(max_scroll);
         self.auto_follow = false;
         
         // Mark this as a scroll action
@@ -538,6 +1474,15 @@ impl App {
     }
 
     pub fn copy_selected_logs(&mut self) -> anyhow::Result<String> {
+        self.copy_selection(false)
+    }
+
+    /// Like `copy_selected_logs`, but each line also carries the entry's fields as `key=value`
+    pub fn copy_selected_logs_with_fields(&mut self) -> anyhow::Result<String> {
+        self.copy_selection(true)
+    }
+
+    fn copy_selection(&mut self, with_fields: bool) -> anyhow::Result<String> {
         if let (Some(selection_start), Some(selection_end)) = (self.selection_start, self.selection_end) {
             let start = selection_start.min(selection_end);
             let end = selection_start.max(selection_end);
@@ -546,36 +1491,919 @@ impl App {
                 .iter()
                 .skip(start)
                 .take(end - start + 1)
-                .map(|log| format!("[{}] {} {}: {}", log.timestamp, log.level, log.target, log.message))
+                .map(|log| {
+                    let line = if self.raw_view && !log.raw.is_empty() {
+                        log.raw.clone()
+                    } else if with_fields {
+                        self.copy_template.render_with_fields(log)
+                    } else {
+                        self.copy_template.render(log)
+                    };
+                    match self.exported_notes().and_then(|notes| notes.get(&log.id)) {
+                        Some(note) => format!("{}\n{}", line, crate::export::note_line(note)),
+                        None => line,
+                    }
+                })
                 .collect();
             
             let content = selected_logs.join("\n");
             let lines_count = end - start + 1;
-            self.copy_message = Some(format!("Copied {} lines to clipboard", lines_count));
+            self.status_message = Some(format!("Copied {} lines to clipboard", lines_count));
             Ok(content)
         } else {
             Ok(String::new())
         }
     }
 
-    pub fn clear_copy_message(&mut self) {
-        self.copy_message = None;
+    /// Show exactly `levels` in the level filter
+    pub fn set_shown_levels(&mut self, levels: &[String]) {
+        self.log_level_filter = levels.iter().map(|level| level.to_ascii_uppercase()).collect();
+        self.filter_dirty = true;
+        self.filter_logs();
     }
 
-    pub fn scroll_to_bottom(&mut self) {
-        if !self.filtered_logs.is_empty() {
-            self.current_log_line = self.filtered_logs.len() - 1;
-            // 最後のエントリの最後の表示行にスクロール
-            let total_display_lines = self.filtered_logs
-                .iter()
-                .map(Self::calculate_display_lines)
-                .sum::<usize>();
-            self.log_scroll_position = total_display_lines.saturating_sub(1);
-            self.auto_follow = true;
+    /// Levels enabled in the level filter, in severity order
+    pub fn shown_levels(&self) -> Vec<&str> {
+        self.available_log_levels
+            .iter()
+            .filter(|level| self.log_level_filter.contains(*level))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Deselected modules whose parent is selected; their submodules are hidden with them
+    pub fn hidden_module_roots(&self) -> Vec<&str> {
+        let mut roots = Vec::new();
+        let mut hidden_depth: Option<usize> = None;
+        for item in &self.module_items {
+            if hidden_depth.is_some_and(|depth| item.level > depth) {
+                continue;
+            }
+            hidden_depth = (!item.is_selected).then_some(item.level);
+            if !item.is_selected {
+                roots.push(item.full_path.as_str());
+            }
         }
+        roots
     }
 
-    pub fn toggle_log_level(&mut self, level: &str) {
+    /// Command line that reopens the inputs with the current filters: `launch_args`, then
+    /// the shown levels, hidden modules, field filter, grep and search as flags. Module
+    /// level thresholds and the trace filter have no flag and aren't included.
+    pub fn view_command(&self) -> String {
+        let mut args = vec!["tracing-viewer".to_string()];
+        args.extend(self.launch_args.iter().map(|arg| shell_quote(arg)));
+        let flag = |args: &mut Vec<String>, name: &str, value: &str| {
+            args.push(name.to_string());
+            args.push(shell_quote(value));
+        };
+
+        let shown = self.shown_levels();
+        if shown.len() < self.available_log_levels.len() {
+            flag(&mut args, "--show-levels", &shown.join(","));
+        }
+        for path in self.hidden_module_roots() {
+            flag(&mut args, "--hide-module", path);
+        }
+        for path in &self.pending_hidden_modules {
+            flag(&mut args, "--hide-module", path);
+        }
+        if let Some(key) = &self.required_field {
+            flag(&mut args, "--has-field", key);
+        }
+        if let Some(query) = &self.grep_query {
+            flag(&mut args, "--grep", query);
+            if self.grep_case_sensitive {
+                args.push("--grep-case-sensitive".to_string());
+            }
+        }
+        if let Some(query) = &self.search_query {
+            flag(&mut args, "--search", query);
+        }
+        for (term, _) in &self.highlight_terms {
+            flag(&mut args, "--highlight", term);
+        }
+        args.join(" ")
+    }
+
+    /// Highlight `term` (ignoring ASCII case) in the first color no other term uses.
+    /// Returns false when it's empty or already highlighted.
+    pub fn add_highlight_term(&mut self, term: &str) -> bool {
+        self.add_highlight_rule(term, None)
+    }
+
+    /// `add_highlight_term` with a fixed background `color`, as for the config file's
+    /// `[[highlight]]` rules
+    pub fn add_highlight_rule(&mut self, term: &str, color: Option<Color>) -> bool {
+        if term.is_empty() || self.highlight_terms.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(term)) {
+            return false;
+        }
+        let color = color.unwrap_or_else(|| {
+            HIGHLIGHT_COLORS
+                .into_iter()
+                .find(|color| self.highlight_terms.iter().all(|(_, style)| style.bg != Some(*color)))
+                .unwrap_or(HIGHLIGHT_COLORS[self.highlight_terms.len() % HIGHLIGHT_COLORS.len()])
+        });
+        self.highlight_terms.push((term.to_string(), Style::default().fg(Color::Black).bg(color)));
+        true
+    }
+
+    /// Add `term` as a highlight term, or remove it when it's already highlighted
+    pub fn toggle_highlight_term(&mut self, term: &str) {
+        if self.add_highlight_term(term) {
+            self.status_message = Some(format!("Highlighting `{}`", term));
+        } else if let Some(index) = self.highlight_terms.iter().position(|(existing, _)| existing.eq_ignore_ascii_case(term)) {
+            self.highlight_terms.remove(index);
+            self.status_message = Some(format!("Stopped highlighting `{}`", term));
+        }
+    }
+
+    pub fn clear_highlight_terms(&mut self) {
+        self.highlight_terms.clear();
+        self.status_message = Some("Cleared highlights".to_string());
+    }
+
+    pub fn copy_view_command(&mut self) {
+        let command = self.view_command();
+        self.status_message = Some(format!("Copied `{}` to clipboard", command));
+        self.clipboard_request = Some(command);
+    }
+
+    /// Heading of the HTML report: the inputs, entry counts and active filters
+    pub fn report_title(&self) -> String {
+        let mut title = format!("{}: {} of {} entries", self.input_label, self.filtered_logs.len(), self.logs.len());
+        let filters = self.active_filter_labels();
+        if !filters.is_empty() {
+            title.push_str(&format!(" ({})", filters.join(" ")));
+        }
+        title
+    }
+
+    /// The notes to put in reports and copies, if `export_notes` is set
+    pub fn exported_notes(&self) -> Option<&HashMap<u64, String>> {
+        self.export_notes.then_some(&self.notes)
+    }
+
+    /// Request an HTML report of the visible entries, named after the current time and
+    /// written into the working directory
+    pub fn export_html(&mut self) {
+        let path = chrono::Local::now().format("tracing-viewer-%Y%m%d-%H%M%S.html").to_string();
+        let content = crate::export::html_report(&self.filtered_logs, &self.report_title(), self.exported_notes());
+        self.export_request = Some(ExportRequest { path, content, entries: self.filtered_logs.len() });
+    }
+
+    /// Like `export_html`, but as text lines in the copy format
+    pub fn export_text(&mut self) {
+        let path = chrono::Local::now().format("tracing-viewer-%Y%m%d-%H%M%S.txt").to_string();
+        let content = crate::export::text_report(&self.filtered_logs, &self.copy_template, self.exported_notes());
+        self.export_request = Some(ExportRequest { path, content, entries: self.filtered_logs.len() });
+    }
+
+    /// Report how writing `export` went
+    pub fn finish_export(&mut self, export: &ExportRequest, result: std::io::Result<()>) {
+        self.status_message = Some(match result {
+            Ok(()) => format!("Exported {} entries to {}", export.entries, export.path),
+            Err(e) => format!("Failed to write {}: {}", export.path, e),
+        });
+    }
+
+    /// Build an `editor +line file` command that opens the focused entry in its source file
+    pub fn editor_command_for_focused(&self) -> Option<String> {
+        let entry = self.filtered_logs.get(self.current_log_line)?;
+        let line = entry.line_number?;
+        Some(format!("{} +{} {}", self.editor, line, shell_quote(&entry.source)))
+    }
+
+    pub fn start_input(&mut self, kind: InputKind) {
+        self.input = Some(InputPrompt { kind, buffer: String::new(), history_index: None });
+    }
+
+    /// Prompt for a note on the focused entry, starting from its current note
+    pub fn start_note(&mut self) {
+        let Some(id) = self.filtered_logs.get(self.current_log_line).map(|log| log.id) else {
+            return;
+        };
+        let note = self.notes.get(&id).cloned().unwrap_or_default();
+        self.input = Some(InputPrompt { kind: InputKind::Note, buffer: note, history_index: None });
+    }
+
+    /// Attach `note` to the focused entry (an empty one removes it). Notes on file entries
+    /// are saved for later sessions.
+    pub fn set_note(&mut self, note: &str) {
+        let Some(entry) = self.filtered_logs.get(self.current_log_line) else {
+            return;
+        };
+        let note = note.trim();
+        let (id, offset, message) = (entry.id, entry.byte_offset, entry.message.clone());
+        let file = self.note_files.entry(entry.source.clone()).or_insert_with(|| note_file(&entry.source)).clone();
+        if note.is_empty() {
+            self.notes.remove(&id);
+            self.status_message = Some("Removed the note".to_string());
+        } else {
+            self.notes.insert(id, note.to_string());
+            self.status_message = Some("Noted".to_string());
+        }
+        if let (Some(file), Some(offset)) = (file, offset) {
+            self.saved_notes.set(&file, offset, &message, note);
+            if let Some(path) = &self.notes_path
+                && let Err(e) = self.saved_notes.save(path)
+            {
+                self.status_message = Some(format!("Failed to save notes: {}", e));
+            }
+        }
+    }
+
+    pub fn input_char(&mut self, c: char) {
+        if let Some(input) = self.input.as_mut() {
+            input.buffer.push(c);
+        }
+    }
+
+    pub fn input_backspace(&mut self) {
+        if let Some(input) = self.input.as_mut() {
+            input.buffer.pop();
+        }
+    }
+
+    /// Replace the prompt buffer with the previous (older) history entry
+    pub fn input_history_previous(&mut self) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+        let Some(key) = input.kind.history_key() else {
+            return;
+        };
+        let entries = self.input_history.entries(key);
+        if entries.is_empty() {
+            return;
+        }
+        let index = match input.history_index {
+            Some(index) => index.saturating_sub(1),
+            None => entries.len() - 1,
+        };
+        input.history_index = Some(index);
+        input.buffer = entries[index].clone();
+    }
+
+    /// Replace the prompt buffer with the next (newer) history entry, or clear it past the newest
+    pub fn input_history_next(&mut self) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+        let Some(key) = input.kind.history_key() else {
+            return;
+        };
+        let entries = self.input_history.entries(key);
+        match input.history_index {
+            Some(index) if index + 1 < entries.len() => {
+                input.history_index = Some(index + 1);
+                input.buffer = entries[index + 1].clone();
+            }
+            Some(_) => {
+                input.history_index = None;
+                input.buffer.clear();
+            }
+            None => {}
+        }
+    }
+
+    /// Open the recent-files picker over the files remembered by `remember_file`
+    pub fn open_recent_files(&mut self) {
+        self.recent_files = Some(RecentFiles::new(self.input_history.entries(RECENT_FILES_KEY)));
+    }
+
+    /// Remember `path` (made absolute) for the recent-files picker of later sessions
+    pub fn remember_file(&mut self, path: &str) {
+        let path = std::fs::canonicalize(path).map_or_else(|_| path.to_string(), |path| path.display().to_string());
+        self.input_history.push(RECENT_FILES_KEY, &path);
+        self.save_input_history();
+    }
+
+    fn save_input_history(&self) {
+        if let Some(path) = &self.history_path
+            && let Err(e) = self.input_history.save(path)
+        {
+            log::debug!("failed to save input history: {}", e);
+        }
+    }
+
+    pub fn cancel_input(&mut self) {
+        self.input = None;
+    }
+
+    pub fn commit_input(&mut self) {
+        if let Some(input) = self.input.take() {
+            if let Some(key) = input.kind.history_key() {
+                self.input_history.push(key, &input.buffer);
+                self.save_input_history();
+            }
+            match input.kind {
+                InputKind::Search => self.apply_search(input.buffer),
+                InputKind::YankField => self.yank_field(&input.buffer),
+                InputKind::FieldFilter => self.set_required_field(&input.buffer),
+                InputKind::Grep => self.set_grep_query(&input.buffer),
+                InputKind::Note => self.set_note(&input.buffer),
+                InputKind::Highlight => self.toggle_highlight_term(input.buffer.trim()),
+            }
+        }
+    }
+
+    /// Complete the prompt from the focused entry's field names (first match in field order)
+    pub fn complete_input(&mut self) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+        if !matches!(input.kind, InputKind::YankField | InputKind::FieldFilter) {
+            return;
+        }
+        let Some(entry) = self.filtered_logs.get(self.current_log_line) else {
+            return;
+        };
+        if let Some((key, _)) = ordered_fields(entry, &self.pinned_fields).find(|(key, _)| key.starts_with(&input.buffer)) {
+            input.buffer = key.clone();
+        }
+    }
+
+    /// Ask for a field name to copy from the focused entry
+    pub fn start_yank_field(&mut self) {
+        match self.filtered_logs.get(self.current_log_line) {
+            Some(entry) if !entry.fields.is_empty() => self.start_input(InputKind::YankField),
+            _ => self.status_message = Some("The focused entry has no fields".to_string()),
+        }
+    }
+
+    /// Toggle the field-presence filter: clear it when set, otherwise ask for a field name
+    pub fn toggle_field_filter(&mut self) {
+        if self.required_field.is_some() {
+            self.set_required_field("");
+        } else {
+            self.start_input(InputKind::FieldFilter);
+        }
+    }
+
+    /// Show only the trace (or span tree) of the focused entry, or clear the trace filter
+    pub fn toggle_trace_filter(&mut self) {
+        if self.trace_filter.take().is_some() {
+            self.status_message = Some("Trace filter cleared".to_string());
+        } else {
+            let Some(trace) = self.filtered_logs.get(self.current_log_line).and_then(TraceFilter::for_entry) else {
+                self.status_message = Some("The focused entry has no trace or span id".to_string());
+                return;
+            };
+            self.status_message = Some(if trace.follows_children {
+                format!("Showing span {} and the spans under it", trace.root)
+            } else {
+                format!("Showing trace {}", trace.root)
+            });
+            self.trace_filter = Some(trace);
+        }
+        self.filter_dirty = true;
+        self.filter_logs();
+    }
+
+    /// Show only entries carrying the field `key`; an empty key removes the filter
+    pub fn set_required_field(&mut self, key: &str) {
+        self.required_field = (!key.is_empty()).then(|| key.to_string());
+        self.filter_dirty = true;
+        self.filter_logs();
+        self.status_message = Some(match &self.required_field {
+            Some(key) => format!("Showing entries with field `{}`", key),
+            None => "Field filter cleared".to_string(),
+        });
+    }
+
+    /// Toggle the substring filter: clear it when set, otherwise ask for the text to keep
+    pub fn toggle_grep(&mut self) {
+        if self.grep_query.is_some() {
+            self.set_grep_query("");
+        } else {
+            self.start_input(InputKind::Grep);
+        }
+    }
+
+    /// Show only entries whose target or message contains `query`; an empty query removes the filter
+    pub fn set_grep_query(&mut self, query: &str) {
+        self.grep_query = (!query.is_empty()).then(|| query.to_string());
+        self.filter_dirty = true;
+        self.filter_logs();
+        self.status_message = Some(match &self.grep_query {
+            Some(query) => format!("Showing entries containing `{}`", query),
+            None => "Grep filter cleared".to_string(),
+        });
+    }
+
+    fn yank_field(&mut self, key: &str) {
+        let Some(entry) = self.filtered_logs.get(self.current_log_line) else {
+            return;
+        };
+        match entry.fields.get(key) {
+            Some(value) => {
+                self.clipboard_request = Some(value.clone());
+                self.status_message = Some(format!("Copied {} to clipboard", key));
+            }
+            None if key.is_empty() => {}
+            None => self.status_message = Some(format!("No field `{}` on the focused entry", key)),
+        }
+    }
+
+    /// Highlight `query` and move to its first match at or after the focus; empty clears the search
+    pub fn apply_search(&mut self, query: String) {
+        self.search_match = None;
+        if query.is_empty() {
+            self.search_query = None;
+            return;
+        }
+        self.search_query = Some(query);
+        if self.search_match_count(self.current_log_line) > 0 {
+            self.search_match = Some((self.current_log_line, 0));
+        } else {
+            self.search_next();
+        }
+    }
+
+    /// Occurrences of the search query in the shown text (message or raw line) of the
+    /// entry at `index`, numbered as they are highlighted
+    fn search_match_count(&self, index: usize) -> usize {
+        match (&self.search_query, self.filtered_logs.get(index)) {
+            (Some(query), Some(entry)) => find_matches(self.displayed_text(entry), query).len(),
+            _ => 0,
+        }
+    }
+
+    /// Move focus to the next entry matching the search query, wrapping around
+    pub fn search_next(&mut self) {
+        self.search_step(true);
+    }
+
+    /// Move focus to the previous entry matching the search query, wrapping around
+    pub fn search_previous(&mut self) {
+        self.search_step(false);
+    }
+
+    fn search_step(&mut self, forward: bool) {
+        let len = self.filtered_logs.len();
+        if self.search_query.is_none() || len == 0 {
+            return;
+        }
+        // 現在のマッチがフォーカス中のエントリにあれば、まず同じエントリ内の次の出現へ
+        if let Some((index, occurrence)) = self.search_match
+            && index == self.current_log_line
+        {
+            let next = if forward { occurrence.checked_add(1) } else { occurrence.checked_sub(1) };
+            if let Some(next) = next.filter(|next| *next < self.search_match_count(index)) {
+                self.search_match = Some((index, next));
+                return;
+            }
+        }
+        for step in 1..=len {
+            let index = if forward {
+                (self.current_log_line + step) % len
+            } else {
+                (self.current_log_line + len - step % len) % len
+            };
+            let count = self.search_match_count(index);
+            if count > 0 {
+                self.focus_entry(index);
+                self.search_match = Some((index, if forward { 0 } else { count - 1 }));
+                return;
+            }
+        }
+        self.status_message = Some("Pattern not found".to_string());
+    }
+
+    /// Focus the given entry as if the user navigated to it
+    fn focus_entry(&mut self, index: usize) {
+        self.current_log_line = index.min(self.filtered_logs.len().saturating_sub(1));
+        self.auto_follow = self.current_log_line + 1 == self.filtered_logs.len();
+        self.last_action_was_focus_move = true;
+        if self.mode == AppMode::TextSelection {
+            self.selection_end = Some(self.current_log_line);
+        }
+    }
+
+    pub fn cycle_timestamp_mode(&mut self) {
+        self.timestamp_mode = self.timestamp_mode.next();
+    }
+
+    pub fn toggle_message_only(&mut self) {
+        self.message_only = !self.message_only;
+        self.status_message = Some(if self.message_only { "Showing messages only" } else { "Showing full entries" }.to_string());
+    }
+
+    pub fn toggle_raw_view(&mut self) {
+        self.raw_view = !self.raw_view;
+        // 表示する本文が変わると出現の数え方も変わるので、フォーカス中のエントリの最初の出現からやり直す
+        if self.search_match.is_some() {
+            self.search_match = (self.search_match_count(self.current_log_line) > 0).then_some((self.current_log_line, 0));
+        }
+        self.status_message = Some(if self.raw_view { "Showing raw lines" } else { "Showing parsed entries" }.to_string());
+    }
+
+    pub fn toggle_header(&mut self) {
+        self.show_header = !self.show_header;
+    }
+
+    /// Freeze the shown entries, or thaw and apply the reloads and entries received in the
+    /// meantime
+    pub fn toggle_freeze(&mut self) {
+        self.frozen = !self.frozen;
+        if self.frozen {
+            self.status_message = Some("Frozen: new entries are held until thawed".to_string());
+        } else {
+            let held = std::mem::take(&mut self.frozen_logs);
+            let dropped = std::mem::take(&mut self.frozen_dropped);
+            // 読み直しを先に反映し、その後に届いた分を追加する
+            for (source, logs) in std::mem::take(&mut self.frozen_reloads) {
+                self.reload_source(&source, logs);
+            }
+            self.status_message = Some(match dropped {
+                0 => format!("Thawed: added {} held entries", held.len()),
+                dropped => format!("Thawed: added {} held entries ({} older ones were dropped)", held.len(), dropped),
+            });
+            self.add_logs(held.into());
+        }
+    }
+
+    /// Header and status bar badge while frozen
+    fn frozen_badge(&self) -> String {
+        let mut badge = format!("FROZEN +{}", self.frozen_logs.len());
+        if self.frozen_dropped > 0 {
+            badge.push_str(&format!(" ({} dropped)", self.frozen_dropped));
+        }
+        if !self.frozen_reloads.is_empty() {
+            badge.push_str(" reloaded");
+        }
+        badge
+    }
+
+    /// Record that `path` is followed by polling, shown as a badge rather than an error
+    pub fn note_polling_fallback(&mut self, path: String, reason: &str) {
+        self.status_message = Some(format!("Polling {} (native file watching unavailable: {})", path, reason));
+        if !self.polled_files.contains(&path) {
+            self.polled_files.push(path);
+        }
+    }
+
+    /// Badge text for files followed by polling, if any
+    fn polling_badge(&self) -> Option<String> {
+        match self.polled_files.len() {
+            0 => None,
+            1 => Some("POLLING".to_string()),
+            count => Some(format!("POLLING {}", count)),
+        }
+    }
+
+    pub fn cycle_line_number_mode(&mut self) {
+        self.line_number_mode = self.line_number_mode.next();
+    }
+
+    /// Gutter number for the filtered entry at `index`, if the current mode has one for it
+    fn line_number(&self, index: usize) -> Option<usize> {
+        match self.line_number_mode {
+            LineNumberMode::Off => None,
+            LineNumberMode::Entry => Some(index + 1),
+            LineNumberMode::Source => self.filtered_logs.get(index)?.line_number,
+        }
+    }
+
+    /// Hash of everything the log list lines for entries `start..end` at `width` columns
+    /// depend on: the filtered entries, focus, selection, search and display options.
+    /// The scroll offset within the window isn't part of it; it's applied when drawing.
+    fn log_view_key(&self, start: usize, end: usize, width: u16) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.filtered_generation, start, end, width).hash(&mut hasher);
+        let shows_focus = matches!(self.mode, AppMode::LogNavigation | AppMode::TextSelection);
+        (self.current_log_line, shows_focus, self.selection_start, self.selection_end).hash(&mut hasher);
+        (&self.search_query, self.search_match, &self.highlight_terms).hash(&mut hasher);
+        (self.timestamp_mode, self.timestamp_precision, self.line_number_mode, self.message_only, self.raw_view).hash(&mut hasher);
+        (&self.continuation_marker, self.focus_color, self.entry_separator, self.show_day_separators).hash(&mut hasher);
+        (self.sources.len(), self.max_target_width, self.tab_width).hash(&mut hasher);
+        // メモの印はウィンドウ内のエントリの分だけ見ればよい
+        let noted: Vec<u64> = self.filtered_logs[start..end].iter().map(|log| log.id).filter(|id| self.notes.contains_key(id)).collect();
+        (self.notes.is_empty(), noted).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Columns taken by the gutter: the widest number plus a separating space
+    fn gutter_width(&self) -> usize {
+        let largest = match self.line_number_mode {
+            LineNumberMode::Off => return 0,
+            LineNumberMode::Entry => self.filtered_logs.len(),
+            LineNumberMode::Source => self.filtered_logs.iter().filter_map(|log| log.line_number).max().unwrap_or(0),
+        };
+        largest.max(1).to_string().len() + 1
+    }
+
+    /// Timestamp column text for filtered entries in `start..end`, honoring `timestamp_mode`.
+    /// Entries whose timestamp can't be parsed render as `+?` in the relative modes.
+    fn timestamp_labels(&self, start: usize, end: usize) -> Vec<String> {
+        let window = &self.filtered_logs[start..end];
+        let format_or_unknown = |delta: Option<chrono::TimeDelta>| {
+            delta.map(format_delta).unwrap_or_else(|| UNKNOWN_DELTA.to_string())
+        };
+        match self.timestamp_mode {
+            TimestampMode::Absolute => {
+                let Some(precision) = self.timestamp_precision else {
+                    return window.iter().map(|log| log.timestamp.clone()).collect();
+                };
+                // 全行でメッセージの開始位置がそろうよう桁数をそろえ、オフセットの長さの違いは最長に合わせる
+                let labels: Vec<String> = window.iter().map(|log| with_precision(&log.timestamp, precision)).collect();
+                let width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0);
+                labels.into_iter().map(|label| format!("{:<width$}", label)).collect()
+            }
+            TimestampMode::Relative => {
+                let origin = self.filtered_logs.iter().find_map(|log| parse_timestamp(&log.timestamp));
+                window
+                    .iter()
+                    .map(|log| {
+                        let delta = parse_timestamp(&log.timestamp).zip(origin).map(|(current, origin)| current - origin);
+                        format_or_unknown(delta)
+                    })
+                    .collect()
+            }
+            TimestampMode::Delta => {
+                // 表示範囲より前で最後に解析できたタイムスタンプを基準にする
+                let previous = self.filtered_logs[..start]
+                    .iter()
+                    .rev()
+                    .find_map(|log| parse_timestamp(&log.timestamp));
+                compute_deltas(window.iter().map(|log| log.timestamp.as_str()), previous)
+                    .into_iter()
+                    .map(format_or_unknown)
+                    .collect()
+            }
+        }
+    }
+
+    /// Compact labels for every filter currently hiding or marking entries,
+    /// e.g. `[search:foo]`, `[lvl:WARN+]`, `[mod:3 hidden]`
+    pub fn active_filter_labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+
+        if let Some(query) = &self.search_query {
+            labels.push(format!("[search:{}]", query));
+        }
+
+        let enabled: Vec<&String> = self.available_log_levels
+            .iter()
+            .filter(|level| self.log_level_filter.contains(*level))
+            .collect();
+        if enabled.len() < self.available_log_levels.len() {
+            // 重大度の高い方から連続して有効なら「WARN+」のように表示
+            let is_threshold = !enabled.is_empty()
+                && enabled.iter().zip(&self.available_log_levels).all(|(a, b)| *a == b);
+            if is_threshold {
+                labels.push(format!("[lvl:{}+]", enabled[enabled.len() - 1]));
+            } else if enabled.is_empty() {
+                labels.push("[lvl:none]".to_string());
+            } else {
+                let names: Vec<&str> = enabled.iter().map(|level| level.as_str()).collect();
+                labels.push(format!("[lvl:{}]", names.join(",")));
+            }
+        }
+
+        // 非表示のサブツリーはその最上位のモジュールだけを数える
+        let hidden_modules = self.hidden_module_roots().len();
+        if hidden_modules > 0 {
+            labels.push(format!("[mod:{} hidden]", hidden_modules));
+        }
+
+        if !self.hidden_sources.is_empty() {
+            labels.push(format!("[src:{} hidden]", self.hidden_sources.len()));
+        }
+
+        if let Some(field) = &self.required_field {
+            labels.push(format!("[has:{}]", field));
+        }
+
+        if let Some(query) = &self.grep_query {
+            labels.push(format!("[grep:{}]", query));
+        }
+
+        if let Some(trace) = &self.trace_filter {
+            labels.push(format!("[{}:{}]", trace.key, trace.root));
+        }
+
+        let module_thresholds = self.module_items.iter().filter(|item| item.min_level.is_some()).count();
+        if module_thresholds > 0 {
+            labels.push(format!("[mod-lvl:{}]", module_thresholds));
+        }
+
+        labels
+    }
+
+    /// Text of a status-bar segment, or `None` when it has nothing to show
+    pub fn status_segment_text(&self, segment: StatusSegment) -> Option<String> {
+        match segment {
+            StatusSegment::Filters => {
+                let labels = self.active_filter_labels();
+                (!labels.is_empty()).then(|| labels.join(" "))
+            }
+            StatusSegment::Counts => Some(format!("{}/{}", self.filtered_logs.len(), self.logs.len())),
+            StatusSegment::Rate => Some(format!("{:.1}/s", self.ingest_rate.per_second(std::time::Instant::now()))),
+            StatusSegment::Follow => Some(if self.auto_follow { "FOLLOW" } else { "SCROLL" }.to_string()),
+            StatusSegment::Clock => Some(chrono::Local::now().format("%H:%M:%S").to_string()),
+        }
+    }
+
+    /// Whether any enabled status-bar segment changes on its own and needs periodic redraws
+    pub fn has_live_status(&self) -> bool {
+        self.status_left.iter().chain(&self.status_right).any(|segment| segment.is_live())
+    }
+
+    /// Snapshot the current selection's counts into compare slot A (0) or B (1)
+    pub fn capture_compare_window(&mut self, slot: usize) {
+        let (Some(selection_start), Some(selection_end)) = (self.selection_start, self.selection_end) else {
+            return;
+        };
+        let start = selection_start.min(selection_end);
+        let end = selection_start.max(selection_end).min(self.filtered_logs.len().saturating_sub(1));
+        let Some(window) = self.filtered_logs.get(start..=end) else {
+            return;
+        };
+
+        let label = format!("{} → {}", window[0].timestamp, window[window.len() - 1].timestamp);
+        let name = if slot == 0 { 'A' } else { 'B' };
+        self.status_message = Some(format!("Captured window {} ({} entries)", name, window.len()));
+        self.compare_windows[slot] = Some(StatsWindow { label, stats: LogStats::from_entries(window) });
+    }
+
+    /// Mark the focused entry as A (0) or B (1), or clear the mark when it's already there
+    pub fn toggle_entry_mark(&mut self, slot: usize) {
+        let Some(id) = self.filtered_logs.get(self.current_log_line).map(|log| log.id) else {
+            return;
+        };
+        let name = if slot == 0 { 'A' } else { 'B' };
+        if self.entry_marks[slot] == Some(id) {
+            self.entry_marks[slot] = None;
+            self.status_message = Some(format!("Cleared mark {}", name));
+            return;
+        }
+        self.entry_marks[slot] = Some(id);
+        self.status_message = Some(self.mark_summary().unwrap_or_else(|| format!("Marked {}", name)));
+    }
+
+    /// The entry marked in `slot`, unless a reload replaced it
+    fn marked_entry(&self, slot: usize) -> Option<&LogEntry> {
+        let id = self.entry_marks[slot]?;
+        // logsはIDの昇順に並んでいる
+        self.logs.binary_search_by_key(&id, |log| log.id).ok().map(|index| &self.logs[index])
+    }
+
+    /// Time from mark A to mark B and how many shown entries apart they are, e.g.
+    /// `A→B +1.250s, 12 entries`; only the set mark's name when one is missing
+    pub fn mark_summary(&self) -> Option<String> {
+        let (a, b) = match (self.marked_entry(0), self.marked_entry(1)) {
+            (Some(a), Some(b)) => (a, b),
+            (Some(_), None) => return Some("mark A".to_string()),
+            (None, Some(_)) => return Some("mark B".to_string()),
+            (None, None) => return None,
+        };
+        let delta = parse_timestamp(&b.timestamp).zip(parse_timestamp(&a.timestamp)).map(|(b, a)| b - a);
+        // 絞り込みで隠れていても、表示中のエントリでの位置で数える
+        let position = |id: u64| self.filtered_logs.partition_point(|log| log.id < id);
+        let distance = position(a.id).abs_diff(position(b.id));
+        Some(format!(
+            "A→B {}, {} {}",
+            delta.map(format_delta).unwrap_or_else(|| UNKNOWN_DELTA.to_string()),
+            distance,
+            if distance == 1 { "entry" } else { "entries" }
+        ))
+    }
+
+    /// Show a recoverable error without interrupting the session; a newer error replaces the old one
+    pub fn show_error(&mut self, message: String) {
+        self.error_banner = Some(message);
+    }
+
+    pub fn dismiss_error(&mut self) {
+        self.error_banner = None;
+    }
+
+    /// Warn that none of the first `sampled` lines parsed with the selected format
+    pub fn show_format_warning(&mut self, sampled: usize) {
+        let format = if self.format_label.is_empty() { "auto" } else { self.format_label.as_str() };
+        self.format_warning = Some(format!(
+            "None of the first {} lines could be parsed (format: {}), so they were dropped. \
+             Run again with --detect to find a matching format, or pick one with --format or --log-regex \
+             (the pattern tester on p helps build a regex).",
+            sampled, format
+        ));
+    }
+
+    pub fn dismiss_format_warning(&mut self) {
+        self.format_warning = None;
+    }
+
+    pub fn toggle_details(&mut self) {
+        self.show_details = !self.show_details && self.filtered_logs.get(self.current_log_line).is_some();
+    }
+
+    pub fn toggle_minimap(&mut self) {
+        self.show_minimap = !self.show_minimap;
+    }
+
+    /// Range of filtered entries covered by minimap row `row` out of `rows`
+    fn minimap_band(&self, row: usize, rows: usize) -> std::ops::Range<usize> {
+        let total = self.filtered_logs.len();
+        (row * total / rows)..((row + 1) * total / rows).max(row * total / rows + 1).min(total)
+    }
+
+    /// Most severe level rank in each of `rows` equal bands of `filtered_logs`,
+    /// `None` for bands without entries
+    pub fn minimap_bands(&self, rows: usize) -> Vec<Option<usize>> {
+        if rows == 0 || self.filtered_logs.is_empty() {
+            return Vec::new();
+        }
+        (0..rows)
+            .map(|row| {
+                self.filtered_logs[self.minimap_band(row, rows)]
+                    .iter()
+                    .map(|log| self.severity.rank(&log.level))
+                    .min()
+            })
+            .collect()
+    }
+
+    /// Jump to the entries under a click on the minimap; returns false for clicks elsewhere
+    pub fn on_click(&mut self, column: u16, row: u16) -> bool {
+        let Some(area) = self.minimap_area else {
+            return false;
+        };
+        if !area.contains(ratatui::layout::Position { x: column, y: row }) || self.filtered_logs.is_empty() {
+            return false;
+        }
+        let band = self.minimap_band((row - area.y) as usize, area.height as usize);
+        self.focus_entry(band.start);
+        true
+    }
+
+    /// Keep the first non-blank raw lines seen as samples for the pattern tester
+    pub fn record_raw_samples<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>) {
+        if self.raw_samples.len() >= SAMPLE_LINES {
+            return;
+        }
+        let needed = SAMPLE_LINES - self.raw_samples.len();
+        self.raw_samples.extend(lines.into_iter().filter(|line| !line.trim().is_empty()).take(needed).map(str::to_string));
+    }
+
+    pub fn open_pattern_tester(&mut self) {
+        if self.raw_samples.is_empty() {
+            self.status_message = Some("No input lines to test the pattern against yet".to_string());
+            return;
+        }
+        self.pattern_tester = Some(PatternTester::new(&self.parse_pattern, &self.raw_samples));
+    }
+
+    pub fn open_palette(&mut self) {
+        self.palette = Some(Palette::default());
+    }
+
+    pub fn close_palette(&mut self) {
+        self.palette = None;
+    }
+
+    pub fn close_pattern_tester(&mut self) {
+        self.pattern_tester = None;
+    }
+
+    /// Use the edited pattern for the rest of the session; an invalid one keeps the tester open
+    pub fn accept_pattern(&mut self) {
+        let Some(tester) = &self.pattern_tester else {
+            return;
+        };
+        if let Err(e) = tester.evaluate() {
+            self.status_message = Some(format!("Invalid pattern: {}", e));
+            return;
+        }
+        self.parse_pattern = tester.pattern.clone();
+        self.pattern_request = Some(tester.pattern.clone());
+        self.pattern_tester = None;
+    }
+
+    pub fn toggle_split_view(&mut self) {
+        self.show_split = !self.show_split;
+    }
+
+    pub fn toggle_compare_view(&mut self) {
+        self.show_compare = !self.show_compare;
+    }
+
+    pub fn clear_status_message(&mut self) {
+        self.status_message = None;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        if !self.filtered_logs.is_empty() {
+            self.current_log_line = self.filtered_logs.len() - 1;
+            // 最後のエントリの最後の表示行にスクロール
+            let total_display_lines = self.total_display_lines();
+            self.log_scroll_position = total_display_lines.saturating_sub(1);
+            self.auto_follow = true;
+        }
+    }
+
+    pub fn toggle_log_level(&mut self, level: &str) {
         if self.log_level_filter.contains(level) {
             self.log_level_filter.remove(level);
         } else {
@@ -585,7 +2413,54 @@ impl App {
         self.filter_logs();
     }
 
+    /// The least severe level shown when the level filter is a plain threshold
+    /// (every level from ERROR down to it, nothing else); `None` otherwise
+    pub fn level_threshold(&self) -> Option<&str> {
+        let levels = self.severity.levels();
+        let enabled = levels.iter().take_while(|level| self.log_level_filter.contains(*level)).count();
+        let is_threshold = enabled > 0
+            && enabled < levels.len()
+            && self.log_level_filter.len() == enabled;
+        is_threshold.then(|| levels[enabled - 1].as_str())
+    }
+
+    /// Raise the minimum visible level one step (all → DEBUG → INFO → WARN → ERROR → all)
+    pub fn cycle_min_level(&mut self) {
+        self.step_min_level(true);
+    }
+
+    /// Lower the minimum visible level one step, the reverse of `cycle_min_level`
+    pub fn cycle_min_level_back(&mut self) {
+        self.step_min_level(false);
+    }
+
+    fn step_min_level(&mut self, raise: bool) {
+        // しきい値になっていない場合は「すべて表示」から始める
+        let levels = self.severity.levels();
+        let all = levels.len().saturating_sub(1);
+        let current = self.level_threshold().map_or(all, |level| self.severity.rank(level));
+        let next = match (raise, current) {
+            (true, 0) => all,
+            (true, current) => current - 1,
+            (false, current) if current == all => 0,
+            (false, current) => (current + 1).min(all),
+        };
+        if next == all {
+            self.log_level_filter = self.available_log_levels.iter().cloned().collect();
+            self.status_message = Some("Showing all levels".to_string());
+        } else {
+            self.status_message = Some(format!("Minimum level: {}", levels[next]));
+            self.log_level_filter = levels[..=next].iter().cloned().collect();
+        }
+        self.filter_dirty = true;
+        self.filter_logs();
+    }
+
     pub fn switch_to_log_level_mode(&mut self) {
+        if self.compact_layout {
+            self.status_message = Some("Terminal too small for the filter panel".to_string());
+            return;
+        }
         self.mode = AppMode::LogLevelFilter;
         self.show_filter_panel = true;
     }
@@ -597,6 +2472,22 @@ impl App {
         self.filter_logs();
     }
 
+    /// Reset every filter dimension: all modules and levels on, search cleared
+    pub fn clear_all_filters(&mut self) {
+        self.module_tree.select_all();
+        self.module_tree.clear_min_levels();
+        self.rebuild_module_items();
+        self.log_level_filter = self.available_log_levels.iter().cloned().collect();
+        self.hidden_sources.clear();
+        self.required_field = None;
+        self.trace_filter = None;
+        self.grep_query = None;
+        self.search_query = None;
+        self.filter_dirty = true;
+        self.filter_logs();
+        self.status_message = Some("All filters cleared".to_string());
+    }
+
     pub fn deselect_all_modules(&mut self) {
         self.module_tree.deselect_all();
         self.rebuild_module_items();
@@ -627,55 +2518,278 @@ impl App {
         }
     }
 
-    pub fn decrease_panel_width(&mut self) {
-        if self.filter_panel_width > 10 {
-            self.filter_panel_width -= 5;
-        }
+    /// The source panel is only shown once entries come from more than one source
+    pub fn has_source_panel(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    pub fn switch_to_source_mode(&mut self) {
+        if self.compact_layout {
+            self.status_message = Some("Terminal too small for the filter panel".to_string());
+            return;
+        }
+        self.mode = AppMode::SourceFilter;
+        self.show_filter_panel = true;
+    }
+
+    pub fn toggle_source(&mut self, source: &str) {
+        if !self.hidden_sources.remove(source) {
+            self.hidden_sources.insert(source.to_string());
+        }
+        self.filter_dirty = true;
+        self.filter_logs();
+    }
+
+    pub fn next_source(&mut self) {
+        if !self.sources.is_empty() {
+            self.selected_source_index = (self.selected_source_index + 1) % self.sources.len();
+        }
+    }
+
+    pub fn previous_source(&mut self) {
+        if !self.sources.is_empty() {
+            self.selected_source_index = if self.selected_source_index == 0 {
+                self.sources.len() - 1
+            } else {
+                self.selected_source_index - 1
+            };
+        }
+    }
+
+    pub fn toggle_selected_source(&mut self) {
+        if let Some(source) = self.sources.get(self.selected_source_index).cloned() {
+            self.toggle_source(&source);
+        }
+    }
+
+    pub fn decrease_panel_width(&mut self) {
+        if self.filter_panel_width > 10 {
+            self.filter_panel_width -= 5;
+        }
+    }
+
+    pub fn increase_panel_width(&mut self) {
+        if self.filter_panel_width < 50 {
+            self.filter_panel_width += 5;
+        }
+    }
+    pub fn update_scroll_position_with_height(&mut self, visible_lines: usize) {
+        if visible_lines <= 2 || self.filtered_logs.is_empty() {
+            return;
+        }
+        
+        // 総表示行数を計算
+        let total_display_lines = self.total_display_lines();
+        
+        // 表示可能な最大スクロール位置を計算
+        let max_scroll = total_display_lines.saturating_sub(visible_lines);
+        
+        // スクロール位置が範囲内に収まるように制限
+        self.log_scroll_position = self.log_scroll_position.min(max_scroll);
+        
+        // Update helper methods with correct visible lines
+        self.update_focus_visibility_with_lines(visible_lines);
+    }
+
+    /// Update focus visibility calculations with actual visible lines
+    fn update_focus_visibility_with_lines(&mut self, visible_lines: usize) {
+        if self.filtered_logs.is_empty() {
+            return;
+        }
+
+        // Apply different logic based on last action
+        if self.last_action_was_focus_move && (self.mode == AppMode::LogNavigation || self.mode == AppMode::TextSelection) {
+            // Focus movement: adjust scroll to ensure buffer visibility
+            self.ensure_focus_visible_with_buffer(visible_lines);
+        } else {
+            // Scroll action: adjust focus to stay on screen
+            self.adjust_focus_for_scroll_with_lines(visible_lines);
+        }
+    }
+}
+
+/// Extract the `YYYY-MM-DD` date component from a timestamp, if present
+fn date_part(timestamp: &str) -> Option<&str> {
+    let date = timestamp.get(..10)?;
+    let bytes = date.as_bytes();
+    let is_date = bytes.iter().enumerate().all(|(i, b)| match i {
+        4 | 7 => *b == b'-',
+        _ => b.is_ascii_digit(),
+    });
+    is_date.then_some(date)
+}
+
+fn format_timestamp_label(label: &str, mode: TimestampMode) -> String {
+    match mode {
+        TimestampMode::Absolute => format!("[{}] ", label),
+        // 相対表示では桁をそろえる
+        TimestampMode::Relative | TimestampMode::Delta => format!("[{:>11}] ", label),
+    }
+}
+
+/// Replace tabs in `line` with spaces up to the next multiple of `tab_width` columns,
+/// counted in characters from the start of the line, so tab-separated columns line up
+fn expand_tabs(line: &str, tab_width: usize) -> Cow<'_, str> {
+    if !line.contains('\t') {
+        return Cow::Borrowed(line);
+    }
+    let tab_width = tab_width.max(1);
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_width - column % tab_width;
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    Cow::Owned(expanded)
+}
+
+/// Shorten `target` to at most `max_width` characters with a middle ellipsis, keeping the
+/// crate and as many trailing path segments as fit (`myapp::…::users`)
+fn truncate_middle(target: &str, max_width: Option<usize>) -> Cow<'_, str> {
+    let Some(max_width) = max_width else {
+        return Cow::Borrowed(target);
+    };
+    let width = target.chars().count();
+    if width <= max_width {
+        return Cow::Borrowed(target);
+    }
+
+    let segments: Vec<&str> = target.split("::").collect();
+    if segments.len() > 2 {
+        let head = format!("{}::…", segments[0]);
+        let mut tail: Vec<&str> = Vec::new();
+        for segment in segments[1..].iter().rev() {
+            let candidate_width = head.chars().count()
+                + tail.iter().map(|s| s.chars().count() + 2).sum::<usize>()
+                + segment.chars().count()
+                + 2;
+            if candidate_width > max_width {
+                break;
+            }
+            tail.push(segment);
+        }
+        if !tail.is_empty() {
+            tail.reverse();
+            return Cow::Owned(format!("{}::{}", head, tail.join("::")));
+        }
+    }
+
+    // パス単位で収まらない場合は文字単位で中央を省略
+    if max_width < 3 {
+        return Cow::Owned(target.chars().take(max_width).collect());
+    }
+    let keep = max_width - 1;
+    let head_len = keep.div_ceil(2);
+    let tail_len = keep - head_len;
+    let head: String = target.chars().take(head_len).collect();
+    let tail: String = target.chars().skip(width - tail_len).collect();
+    Cow::Owned(format!("{}…{}", head, tail))
+}
+
+/// Whether `text` contains `query`, ignoring ASCII case unless `case_sensitive`.
+/// Compares bytes in place so the grep filter doesn't allocate per entry.
+fn contains_text(text: &str, query: &str, case_sensitive: bool) -> bool {
+    if case_sensitive {
+        return text.contains(query);
+    }
+    let (text, query) = (text.as_bytes(), query.as_bytes());
+    query.is_empty() || text.windows(query.len()).any(|window| window.eq_ignore_ascii_case(query))
+}
+
+/// Byte ranges of ASCII case-insensitive occurrences of `query` in `text`
+fn find_matches(text: &str, query: &str) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return Vec::new();
     }
+    let haystack = text.to_ascii_lowercase();
+    let needle = query.to_ascii_lowercase();
+    haystack
+        .match_indices(&needle)
+        .map(|(start, matched)| (start, start + matched.len()))
+        .collect()
+}
 
-    pub fn increase_panel_width(&mut self) {
-        if self.filter_panel_width < 50 {
-            self.filter_panel_width += 5;
+/// Split `text` into spans, styling search matches and then `terms` (highlight terms with
+/// their styles) on top of `base_style`. The search match at index `current` (the one
+/// `n`/`N` targets) stands out from the others.
+fn highlight_spans<'a>(text: &'a str, query: Option<&str>, current: Option<usize>, terms: &[(String, Style)], base_style: Style) -> Vec<Span<'a>> {
+    let mut ranges: Vec<(usize, usize, Style)> = query
+        .map(|query| find_matches(text, query))
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(match_index, (start, end))| {
+            let match_style = if current == Some(match_index) {
+                base_style.fg(Color::Black).bg(Color::LightRed).add_modifier(Modifier::BOLD)
+            } else {
+                base_style.fg(Color::Black).bg(Color::Yellow)
+            };
+            (start, end, match_style)
+        })
+        .collect();
+    // 検索のマッチと先に追加した語を優先し、重ならない出現にだけ色を付ける
+    for (term, style) in terms {
+        for (start, end) in find_matches(text, term) {
+            if ranges.iter().all(|(other_start, other_end, _)| end <= *other_start || start >= *other_end) {
+                ranges.push((start, end, base_style.patch(*style)));
+            }
         }
     }
-    pub fn update_scroll_position_with_height(&mut self, visible_lines: usize) {
-        if visible_lines <= 2 || self.filtered_logs.is_empty() {
-            return;
+    ranges.sort_by_key(|(start, _, _)| *start);
+
+    let mut spans = Vec::new();
+    let mut position = 0;
+    for (start, end, style) in ranges {
+        if start > position {
+            spans.push(Span::styled(&text[position..start], base_style));
         }
-        
-        // 総表示行数を計算
-        let total_display_lines = self.filtered_logs
-            .iter()
-            .map(Self::calculate_display_lines)
-            .sum::<usize>();
-        
-        // 表示可能な最大スクロール位置を計算
-        let max_scroll = total_display_lines.saturating_sub(visible_lines);
-        
-        // スクロール位置が範囲内に収まるように制限
-        self.log_scroll_position = self.log_scroll_position.min(max_scroll);
-        
-        // Update helper methods with correct visible lines
-        self.update_focus_visibility_with_lines(visible_lines);
+        spans.push(Span::styled(&text[start..end], style));
+        position = end;
     }
+    if position < text.len() || spans.is_empty() {
+        spans.push(Span::styled(&text[position..], base_style));
+    }
+    spans
+}
 
-    /// Update focus visibility calculations with actual visible lines
-    fn update_focus_visibility_with_lines(&mut self, visible_lines: usize) {
-        if self.filtered_logs.is_empty() {
-            return;
-        }
+/// Fields of `entry` with the `pinned` keys first, in the order given, then the others in
+/// the order they appeared in the entry
+fn ordered_fields<'a>(entry: &'a LogEntry, pinned: &'a [String]) -> impl Iterator<Item = (&'a String, &'a String)> {
+    let pinned_fields = pinned.iter().filter_map(|key| entry.fields.get_key_value(key));
+    pinned_fields.chain(entry.fields.iter().filter(|(key, _)| !pinned.contains(key)))
+}
 
-        // Apply different logic based on last action
-        if self.last_action_was_focus_move && (self.mode == AppMode::LogNavigation || self.mode == AppMode::TextSelection) {
-            // Focus movement: adjust scroll to ensure buffer visibility
-            self.ensure_focus_visible_with_buffer(visible_lines);
-        } else {
-            // Scroll action: adjust focus to stay on screen
-            self.adjust_focus_for_scroll_with_lines(visible_lines);
-        }
+/// Quote `value` for a POSIX shell if it contains anything beyond a safe character set
+fn shell_quote(value: &str) -> String {
+    let is_safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | ':' | '+' | ',' | '@'));
+    if is_safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
     }
 }
 
+/// Short label for a source: the file name for paths, the source itself otherwise
+pub fn source_label(source: &str) -> &str {
+    std::path::Path::new(source)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(source)
+}
+
+/// Below this terminal width or height the filter panel is hidden
+const MIN_PANEL_LAYOUT_WIDTH: u16 = 60;
+const MIN_PANEL_LAYOUT_HEIGHT: u16 = 10;
+
 pub fn render(f: &mut Frame, app: &mut App) {
     let current_size = (f.area().width, f.area().height);
     
@@ -687,13 +2801,41 @@ pub fn render(f: &mut Frame, app: &mut App) {
             app.scroll_to_bottom();
         }
     }
+
+    app.compact_layout = current_size.0 < MIN_PANEL_LAYOUT_WIDTH || current_size.1 < MIN_PANEL_LAYOUT_HEIGHT;
+    // パネルが隠れている間はパネル側のモードに留まらない
+    if app.compact_layout && matches!(app.mode, AppMode::ModuleSelection | AppMode::LogLevelFilter | AppMode::SourceFilter) {
+        app.mode = AppMode::LogNavigation;
+        app.module_visual_anchor = None;
+    }
+
+    // 表示領域がない場合は何も描画しない
+    if current_size.0 == 0 || current_size.1 == 0 {
+        return;
+    }
     
+    let header_height = if app.show_header { 1 } else { 0 };
+    let banner_height = if app.error_banner.is_some() { 1 } else { 0 };
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .constraints([
+            Constraint::Length(header_height),
+            Constraint::Length(banner_height),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
         .split(f.area());
+    if app.show_header {
+        render_header(f, app, main_chunks[0]);
+    }
+    if let Some(message) = &app.error_banner {
+        let banner = Paragraph::new(format!(" ✗ {}  (x: dismiss)", message))
+            .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD));
+        f.render_widget(banner, main_chunks[1]);
+    }
+    let main_chunks = [main_chunks[2], main_chunks[3]];
 
-    if app.show_filter_panel {
+    if app.show_filter_panel && !app.compact_layout {
         let remaining_width = 100 - app.filter_panel_width;
         let top_chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -706,34 +2848,334 @@ pub fn render(f: &mut Frame, app: &mut App) {
 
         render_left_panel(f, app, top_chunks[0]);
         render_separator(f, top_chunks[1]);
-        render_logs(f, app, top_chunks[2]);
+        render_log_panes(f, app, top_chunks[2]);
     } else {
-        render_logs(f, app, main_chunks[0]);
+        render_log_panes(f, app, main_chunks[0]);
     }
     render_status_bar(f, app, main_chunks[1]);
+
+    if app.show_compare {
+        render_compare_popup(f, app, f.area());
+    }
+    if let Some(tester) = &app.pattern_tester {
+        render_pattern_tester_popup(f, tester, f.area());
+    }
+    if let Some(palette) = &app.palette {
+        render_palette_popup(f, palette, f.area());
+    }
+    if app.show_details
+        && let Some(entry) = app.filtered_logs.get(app.current_log_line)
+    {
+        render_details_popup(f, app, entry, f.area());
+    }
+    if let Some(recent_files) = &app.recent_files {
+        render_recent_files_popup(f, recent_files, f.area());
+    }
+    if let Some(loading) = &app.loading {
+        render_loading_popup(f, loading, f.area());
+    }
+    if let Some(warning) = &app.format_warning {
+        render_format_warning_popup(f, warning, f.area());
+    }
+}
+
+fn render_format_warning_popup(f: &mut Frame, warning: &str, area: Rect) {
+    let popup_area = centered_rect(60, 40, area);
+    let block = Block::default()
+        .title(" Input doesn't match the format (any key: continue) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(warning).block(block).wrap(Wrap { trim: true }), popup_area);
+}
+
+/// The pattern being edited, then each sample line marked as parsed or not with its captures
+fn render_pattern_tester_popup(f: &mut Frame, tester: &PatternTester, area: Rect) {
+    let popup_area = centered_rect(90, 80, area);
+    let block = Block::default()
+        .title(" Pattern tester (Enter: apply, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled("pattern ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Span::raw(tester.pattern.clone()),
+        Span::styled("█", Style::default().fg(Color::Gray)),
+    ])];
+    lines.push(Line::from(""));
+
+    match tester.evaluate() {
+        Ok(matches) => {
+            let matched = matches.iter().filter(|sample| sample.captures.is_some()).count();
+            lines.push(Line::from(Span::styled(
+                format!("{} of {} sample lines parse", matched, matches.len()),
+                Style::default().fg(Color::DarkGray),
+            )));
+            for sample in matches {
+                match sample.captures {
+                    Some(captures) => {
+                        lines.push(Line::from(Span::styled(format!("✓ {}", sample.line), Style::default().fg(Color::Green))));
+                        let mut spans = vec![Span::raw("    ")];
+                        for (name, value) in captures {
+                            spans.push(Span::styled(format!("{}=", name), Style::default().fg(Color::Cyan)));
+                            spans.push(Span::raw(format!("{}  ", value)));
+                        }
+                        lines.push(Line::from(spans));
+                    }
+                    None => {
+                        lines.push(Line::from(Span::styled(format!("✗ {}", sample.line), Style::default().fg(Color::Red))));
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            lines.push(Line::from(Span::styled(e, Style::default().fg(Color::Red))));
+        }
+    }
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), popup_area);
+}
+
+fn render_palette_popup(f: &mut Frame, palette: &Palette, area: Rect) {
+    let popup_area = centered_rect(50, 60, area);
+    let block = Block::default()
+        .title(" Commands (Enter: run, Esc: close) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(":", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(palette.query.clone()),
+            Span::styled("█", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(""),
+    ];
+    let matches = palette.matches();
+    if matches.is_empty() {
+        lines.push(Line::from(Span::styled("No matching commands", Style::default().fg(Color::DarkGray))));
+    }
+    for (index, command) in matches.into_iter().enumerate() {
+        let style = if index == palette.selected {
+            Style::default().bg(Color::Blue).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<24}", command.name()), style),
+            Span::styled(command.key_hint(), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+fn render_recent_files_popup(f: &mut Frame, recent_files: &RecentFiles, area: Rect) {
+    let popup_area = centered_rect(70, 60, area);
+    let block = Block::default()
+        .title(" Recent files (Enter: open, q/Esc: quit) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let lines: Vec<Line> = if recent_files.files.is_empty() {
+        vec![
+            Line::from("No recently viewed files."),
+            Line::from(""),
+            Line::from(Span::styled(
+                "Start with --input PATH, or pipe logs in: some-app | tracing-viewer",
+                Style::default().fg(Color::DarkGray),
+            )),
+        ]
+    } else {
+        recent_files
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let style = if index == recent_files.selected {
+                    Style::default().bg(Color::Blue).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Line::from(Span::styled(path.clone(), style))
+            })
+            .collect()
+    };
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+fn render_loading_popup(f: &mut Frame, text: &str, area: Rect) {
+    let width = (text.chars().count() as u16 + 4).min(area.width);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(3) / 2,
+        width,
+        height: area.height.min(3),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(text).block(block), popup_area);
+}
+
+/// A rectangle of the given percentage size centered in `area`
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn render_compare_popup(f: &mut Frame, app: &App, area: Rect) {
+    let popup_area = centered_rect(70, 70, area);
+    let block = Block::default()
+        .title(" Compare windows (D/Esc: close) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let header_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+
+    let [Some(a), Some(b)] = &app.compare_windows else {
+        lines.push(Line::from("Select a range with v, then press A and B to capture the two windows."));
+        for (name, window) in ['A', 'B'].iter().zip(&app.compare_windows) {
+            let state = match window {
+                Some(window) => format!("{} ({} entries)", window.label, window.stats.total),
+                None => "not captured".to_string(),
+            };
+            lines.push(Line::from(format!("{}: {}", name, state)));
+        }
+        f.render_widget(Clear, popup_area);
+        f.render_widget(Paragraph::new(lines).block(block), popup_area);
+        return;
+    };
+
+    let comparison = StatsComparison::new(&a.stats, &b.stats, &app.severity);
+    let delta_line = |key: &str, a: usize, b: usize, delta: i64| {
+        let delta_style = match delta {
+            d if d > 0 => Style::default().fg(Color::Red),
+            d if d < 0 => Style::default().fg(Color::Green),
+            _ => Style::default().fg(Color::DarkGray),
+        };
+        Line::from(vec![
+            Span::raw(format!("{:<32} {:>8} {:>8} ", key, a, b)),
+            Span::styled(format!("{:>+8}", delta), delta_style),
+        ])
+    };
+
+    lines.push(Line::from(format!("A: {}", a.label)));
+    lines.push(Line::from(format!("B: {}", b.label)));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(format!("{:<32} {:>8} {:>8} {:>8}", "Level", "A", "B", "Δ"), header_style)));
+    lines.push(delta_line("total", comparison.total.a, comparison.total.b, comparison.total.delta()));
+    for level in &comparison.levels {
+        lines.push(delta_line(&level.key, level.a, level.b, level.delta()));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(format!("{:<32} {:>8} {:>8} {:>8}", "Target", "A", "B", "Δ"), header_style)));
+    for target in &comparison.targets {
+        let name = truncate_middle(&target.key, Some(32));
+        lines.push(delta_line(&name, target.a, target.b, target.delta()));
+    }
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Every attribute of `entry`, with fields listed in the order the parser found them
+fn render_details_popup(f: &mut Frame, app: &App, entry: &LogEntry, area: Rect) {
+    let popup_area = centered_rect(80, 70, area);
+    let block = Block::default()
+        .title(" Entry details (y: copy field, Enter/Esc: close) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let key_style = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+    let mut location = entry.source.clone();
+    if let Some(line) = entry.line_number {
+        location.push_str(&format!(":{}", line));
+    }
+    let mut lines: Vec<Line> = [
+        ("timestamp", entry.timestamp.clone()),
+        ("level", entry.level.clone()),
+        ("target", entry.target.clone()),
+        ("source", location),
+        ("id", entry_anchor(entry).to_string()),
+    ]
+    .into_iter()
+    .map(|(key, value)| Line::from(vec![Span::styled(format!("{:<10} ", key), key_style), Span::raw(value)]))
+    .collect();
+
+    if let Some(note) = app.notes.get(&entry.id) {
+        lines.push(Line::from(vec![Span::styled(format!("{:<10} ", "note"), key_style), Span::styled(note.clone(), Style::default().fg(Color::Yellow))]));
+    }
+
+    lines.push(Line::from(""));
+    lines.extend(entry.message.lines().map(|line| Line::from(expand_tabs(line, app.tab_width).into_owned())));
+
+    if !entry.fields.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("fields", key_style)));
+        let key_width = entry.fields.keys().map(|key| key.chars().count()).max().unwrap_or(0);
+        for (key, value) in ordered_fields(entry, &app.pinned_fields) {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<key_width$} ", key), Style::default().fg(Color::Cyan)),
+                Span::raw(value.clone()),
+            ]));
+        }
+    }
+
+    f.render_widget(Clear, popup_area);
+    f.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), popup_area);
 }
 
 fn render_left_panel(f: &mut Frame, app: &mut App, area: Rect) {
+    // 入力元が複数ある場合のみ、ログレベルとモジュールツリーの間に入力元一覧を挟む
+    let source_height = if app.has_source_panel() { (app.sources.len() as u16).min(5) } else { 0 };
     let left_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(5),  // ログレベルフィルター用
             Constraint::Length(1),  // 区切り線用
+            Constraint::Length(source_height),  // 入力元フィルター用
+            Constraint::Length(if source_height > 0 { 1 } else { 0 }),  // 区切り線用
             Constraint::Min(0),     // モジュールツリー用
         ])
         .split(area);
 
     render_log_level_filter(f, app, left_chunks[0]);
     render_horizontal_separator(f, left_chunks[1]);
-    render_module_tree(f, app, left_chunks[2]);
+    if source_height > 0 {
+        render_source_filter(f, app, left_chunks[2]);
+        render_horizontal_separator(f, left_chunks[3]);
+    }
+    render_module_tree(f, app, left_chunks[4]);
 }
 
 
 fn render_module_tree(f: &mut Frame, app: &mut App, area: Rect) {
+    let visual_range = app.module_visual_range();
     let items: Vec<ListItem> = app.module_items
         .iter()
         .enumerate()
-        .map(|(_index, item)| {
+        .map(|(index, item)| {
             let indent = "  ".repeat(item.level.saturating_sub(1));
             let checkbox = if item.is_selected { "☑" } else { "☐" };
             
@@ -746,13 +3188,20 @@ fn render_module_tree(f: &mut Frame, app: &mut App, area: Rect) {
                 "  "
             };
             
-            let content = format!("{}{}{} {}", prefix, indent, checkbox, item.name);
+            let count = app.module_counts.get(&item.full_path).copied().unwrap_or(0);
+            let mut content = format!("{}{}{} {} ({})", prefix, indent, checkbox, item.name, count);
+            if let Some(min_level) = &item.min_level {
+                content.push_str(&format!(" [{}+]", min_level));
+            }
             
-            let style = if item.is_selected {
+            let mut style = if item.is_selected {
                 Style::default().fg(Color::Green)
             } else {
                 Style::default().fg(Color::Gray)
             };
+            if visual_range.is_some_and(|(start, end)| (start..=end).contains(&index)) {
+                style = style.bg(Color::DarkGray);
+            }
 
             ListItem::new(Line::from(Span::styled(content, style)))
         })
@@ -790,16 +3239,115 @@ fn render_horizontal_separator(f: &mut Frame, area: Rect) {
     f.render_widget(separator, area);
 }
 
+/// Color of a level name in the log list (also used by the HTML export)
+pub(crate) fn level_style(level: &str) -> Style {
+    match level {
+        "FATAL" | "EMERG" | "ALERT" | "CRITICAL" => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        "ERROR" => Style::default().fg(Color::Red),
+        "WARN" => Style::default().fg(Color::Yellow),
+        "NOTICE" => Style::default().fg(Color::Cyan),
+        "INFO" => Style::default().fg(Color::Green),
+        "DEBUG" => Style::default().fg(Color::Blue),
+        "TRACE" => Style::default().fg(Color::Magenta),
+        _ => Style::default().fg(Color::White),
+    }
+}
+
+/// Draw the log list, with the severity pane above it when the split view is on
+fn render_log_panes(f: &mut Frame, app: &mut App, area: Rect) {
+    if !app.show_split || area.height < 8 {
+        render_logs(f, app, area);
+        return;
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(30), Constraint::Min(3)])
+        .split(area);
+    render_severity_pane(f, app, chunks[0]);
+    render_logs(f, app, chunks[1]);
+}
+
+/// The most recent visible entries at or above `split_min_level`, newest at the bottom
+fn render_severity_pane(f: &mut Frame, app: &App, area: Rect) {
+    let capacity = area.height.saturating_sub(1) as usize;
+    let min_rank = app.severity.rank(&app.split_min_level);
+    let mut recent: Vec<&LogEntry> = app.filtered_logs
+        .iter()
+        .rev()
+        .filter(|log| app.severity.rank(&log.level) <= min_rank)
+        .take(capacity)
+        .collect();
+    recent.reverse();
+
+    let mut lines: Vec<Line> = recent
+        .iter()
+        .map(|log| {
+            Line::from(vec![
+                Span::styled(format!("[{}] ", log.timestamp), Style::default().fg(app.theme.timestamp)),
+                Span::styled(format!("{:<5} ", log.level), app.theme.level_style(&log.level)),
+                Span::styled(format!("{}: ", truncate_middle(&log.target, app.max_target_width)), Style::default().fg(app.theme.target)),
+                Span::raw(log.message.lines().next().unwrap_or_default().to_string()),
+            ])
+        })
+        .collect();
+    let header = format!("── {}+ ── S: close split ", app.split_min_level);
+    let fill = (area.width as usize).saturating_sub(header.chars().count());
+    lines.push(Line::from(Span::styled(
+        format!("{}{}", header, "─".repeat(fill)),
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    // 件数が少ない場合も区切り線はペインの最下行に置く
+    let padding = area.height as usize - lines.len().min(area.height as usize);
+    let mut padded = vec![Line::from(""); padding];
+    padded.extend(lines);
+    f.render_widget(Paragraph::new(padded), area);
+}
+
+/// One cell per band of entries, colored by the band's most severe level.
+/// Bands overlapping `visible` are drawn solid to show where the view is.
+fn render_minimap(f: &mut Frame, app: &App, area: Rect, visible: std::ops::Range<usize>) {
+    let rows = area.height as usize;
+    let lines: Vec<Line> = app
+        .minimap_bands(rows)
+        .into_iter()
+        .enumerate()
+        .map(|(row, rank)| {
+            let band = app.minimap_band(row, rows);
+            let in_view = band.start < visible.end && visible.start < band.end;
+            let symbol = if in_view { "█" } else { "▐" };
+            let style = match rank {
+                Some(rank) => app.theme.level_style(app.severity.levels().get(rank).map_or("", String::as_str)),
+                None => Style::default().fg(Color::DarkGray),
+            };
+            Line::from(Span::styled(symbol, style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), area);
+}
+
 fn render_logs(f: &mut Frame, app: &mut App, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Min(0), Constraint::Length(1)])
         .split(area);
-    let log_area = chunks[0];
+    let mut log_area = chunks[0];
     let pagination_area = chunks[1];
 
+    // ミニマップは右端の1列に描画する
+    app.minimap_area = None;
+    if app.show_minimap && log_area.width > 10 {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(log_area);
+        log_area = columns[0];
+        app.minimap_area = Some(columns[1]);
+    }
+
     // 実際の表示可能行数でスクロール位置を更新
     let visible_lines = log_area.height as usize;
+    app.log_view_height = visible_lines;
     app.update_scroll_position_with_height(visible_lines);
     
     // ログが空の場合は早期リターン
@@ -816,29 +3364,66 @@ fn render_logs(f: &mut Frame, app: &mut App, area: Rect) {
     let end_index = end_entry.min(app.filtered_logs.len()).max(start_index);
     
     // スクロール位置から開始エントリまでの表示行数を計算
-    let skip_lines = app.filtered_logs
-        .iter()
-        .take(start_index)
-        .map(App::calculate_display_lines)
-        .sum::<usize>();
+    let skip_lines = app.get_entry_display_position(start_index);
     
     let scroll_offset = app.log_scroll_position.saturating_sub(skip_lines);
+    // 前回の描画から表示内容が変わっていなければ、作った行をそのまま使う
+    let view_key = app.log_view_key(start_index, end_index, log_area.width);
+    if app.log_view_cache.as_ref().is_none_or(|(key, _)| *key != view_key) {
+        let lines = build_log_lines(app, start_index, end_index, log_area.width);
+        app.log_view_cache = Some((view_key, lines));
+    }
+    let cached_lines = app.log_view_cache.as_ref().map_or(&[][..], |(_, lines)| lines.as_slice());
+    // 文字列は複製せず借用して描画する
+    let log_content: Vec<Line> = cached_lines
+        .iter()
+        .map(|line| Line::from(line.spans.iter().map(|span| Span::styled(span.content.as_ref(), span.style)).collect::<Vec<_>>()))
+        .collect();
+
+    let paragraph = Paragraph::new(log_content)
+        .scroll((scroll_offset as u16, 0));
+
+    f.render_widget(paragraph, log_area);
+
+    if let Some(minimap_area) = app.minimap_area {
+        render_minimap(f, app, minimap_area, start_index..end_index);
+    }
+
+    if !app.filtered_logs.is_empty() {
+        let total_display_lines = app.total_display_lines();
+        let start_line = app.log_scroll_position + 1;
+        let end_line = (app.log_scroll_position + visible_lines).min(total_display_lines);
+        
+        let mut pagination_text = format!("{}-{} of {} lines ({} entries)", 
+            start_line, end_line, total_display_lines, app.filtered_logs.len());
+        // フォーカス中のエントリの元ファイル上の位置
+        if let Some(focused) = app.filtered_logs.get(app.current_log_line)
+            && let (Some(line), Some(offset)) = (focused.line_number, focused.byte_offset)
+        {
+            pagination_text.push_str(&format!(" | {}:{} (byte {})", source_label(&focused.source), line, offset));
+        }
+        let pagination_paragraph = Paragraph::new(pagination_text)
+            .style(Style::default().fg(Color::DarkGray));
+            
+        f.render_widget(pagination_paragraph, pagination_area);
+    }
+}
 
-    let log_content: Vec<Line> = app.filtered_logs
+/// Lines for filtered entries `start_index..end_index` drawn `width` columns wide, with
+/// day separators or rules above entries and the focus, selection and search highlights
+fn build_log_lines(app: &App, start_index: usize, end_index: usize, width: u16) -> Vec<Line<'static>> {
+    let search_query = app.search_query.as_deref();
+    let timestamp_labels = app.timestamp_labels(start_index, end_index);
+    let gutter_width = app.gutter_width();
+
+    app.filtered_logs
         .iter()
         .skip(start_index)
         .take(end_index - start_index)
         .enumerate()
         .flat_map(|(relative_index, log)| {
             let index = start_index + relative_index;
-            let level_style = match log.level.as_str() {
-                "ERROR" => Style::default().fg(Color::Red),
-                "WARN" => Style::default().fg(Color::Yellow),
-                "INFO" => Style::default().fg(Color::Green),
-                "DEBUG" => Style::default().fg(Color::Blue),
-                "TRACE" => Style::default().fg(Color::Magenta),
-                _ => Style::default().fg(Color::White),
-            };
+            let level_style = app.theme.level_style(&log.level);
 
             let is_selected = app.selection_start.is_some() && app.selection_end.is_some() && {
                 let start = app.selection_start.unwrap().min(app.selection_end.unwrap());
@@ -849,67 +3434,137 @@ fn render_logs(f: &mut Frame, app: &mut App, area: Rect) {
             let is_current = index == app.current_log_line && 
                 (app.mode == AppMode::LogNavigation || app.mode == AppMode::TextSelection);
 
+            // 縞模様の背景を一番下に敷き、選択とフォーカスの背景で上書きする
+            let striped = app.entry_separator == EntrySeparator::Zebra && index % 2 == 1;
             let mut base_style = Style::default();
+            if striped {
+                base_style = base_style.bg(ZEBRA_BACKGROUND);
+            }
             if is_selected {
                 base_style = base_style.bg(Color::DarkGray);
             }
             if is_current {
                 // Make focus more prominent with bright background and bold text
-                base_style = base_style.bg(Color::Blue).add_modifier(Modifier::BOLD);
+                base_style = base_style.bg(app.focus_color).add_modifier(Modifier::BOLD);
             }
 
-            let message_lines: Vec<&str> = log.message.lines().collect();
+            let raw = app.raw_view && !log.raw.is_empty();
+            let mut message_lines: Vec<Cow<str>> = app.displayed_text(log).lines().map(|line| expand_tabs(line, app.tab_width)).collect();
+            if message_lines.is_empty() {
+                message_lines.push(Cow::Borrowed(""));
+            }
+            let show_source = app.sources.len() > 1;
             let mut lines = Vec::new();
+            // 現在のマッチの、このエントリ内での出現番号（行をまたいで数える）
+            let mut current_match = app.search_match.filter(|(match_entry, _)| *match_entry == index).map(|(_, occurrence)| occurrence);
+            let mut line_current_match = |message_line: &str| {
+                let line_matches = search_query.map_or(0, |query| find_matches(message_line, query).len());
+                let current = current_match.filter(|occurrence| *occurrence < line_matches);
+                current_match = current_match.and_then(|occurrence| occurrence.checked_sub(line_matches));
+                current
+            };
+
+            // 日付が変わったエントリの前に日付の区切り行を挿入
+            if app.starts_new_day(index) {
+                let date = date_part(&log.timestamp).unwrap_or_default();
+                lines.push(Line::from(Span::styled(
+                    format!("── {} ──", date),
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD),
+                )));
+            } else if app.draws_rule_above(index) {
+                lines.push(Line::from(Span::styled(
+                    "─".repeat(width as usize),
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::DIM),
+                )));
+            }
+            let first_entry_line = lines.len();
             
             for (line_index, message_line) in message_lines.iter().enumerate() {
-                if line_index == 0 {
+                // 行番号は先頭行にだけ表示し、続きの行は同じ幅の空白で揃える
+                let gutter = match app.line_number(index).filter(|_| line_index == 0) {
+                    Some(number) => format!("{:>width$} ", number, width = gutter_width.saturating_sub(1)),
+                    None => " ".repeat(gutter_width),
+                };
+                let gutter_span = Span::styled(gutter, Style::default().fg(Color::DarkGray));
+                if app.message_only || raw {
+                    // メッセージのみ表示する場合は前置きを省き、レベルの色で描画する。元の行はそれ自体に前置きを含む
+                    let mut spans = vec![gutter_span];
+                    if line_index > 0 && !app.continuation_marker.is_empty() {
+                        spans.push(Span::styled(format!("{} ", app.continuation_marker), base_style.patch(level_style).add_modifier(Modifier::DIM)));
+                    }
+                    spans.extend(highlight_spans(message_line, search_query, line_current_match(message_line), &app.highlight_terms, base_style.patch(level_style)));
+                    lines.push(Line::from(spans));
+                } else if line_index == 0 {
                     // First line includes timestamp, level, and target
-                    lines.push(Line::from(vec![
-                        Span::styled(format!("[{}] ", log.timestamp), base_style.fg(Color::Cyan)),
+                    let mut spans = vec![
+                        gutter_span,
+                        Span::styled(format_timestamp_label(&timestamp_labels[relative_index], app.timestamp_mode), base_style.fg(app.theme.timestamp)),
+                    ];
+                    // 複数の入力元がある場合は入力元を表示
+                    if show_source {
+                        spans.push(Span::styled(format!("<{}> ", source_label(&log.source)), base_style.fg(app.theme.source)));
+                    }
+                    spans.extend([
                         Span::styled(format!("{:<5} ", log.level), base_style.patch(level_style)),
-                        Span::styled(format!("{}: ", log.target), base_style.fg(Color::Yellow)),
-                        Span::styled(*message_line, base_style),
-                    ]));
+                        // 幅の上限がある場合はその幅まで詰めてメッセージの開始位置をそろえる
+                        Span::styled(
+                            format!(
+                                "{:<width$} ",
+                                format!("{}:", truncate_middle(&log.target, app.max_target_width)),
+                                width = app.max_target_width.map_or(0, |width| width + 1),
+                            ),
+                            base_style.fg(app.theme.target),
+                        ),
+                    ]);
+                    spans.extend(highlight_spans(message_line, search_query, line_current_match(message_line), &app.highlight_terms, base_style));
+                    lines.push(Line::from(spans));
                 } else {
                     // Continuation lines are indented
-                    lines.push(Line::from(vec![
-                        Span::styled("    ", base_style), // Indentation for continuation
-                        Span::styled(*message_line, base_style),
-                    ]));
+                    let mut spans = vec![gutter_span];
+                    // マーカーがある場合もインデント幅（4桁、最低1つの空白）は保つ
+                    let marker_width = app.continuation_marker.chars().count();
+                    if marker_width > 0 {
+                        spans.push(Span::styled(app.continuation_marker.clone(), base_style.patch(level_style).add_modifier(Modifier::DIM)));
+                    }
+                    let indent = if marker_width > 0 { 4usize.saturating_sub(marker_width).max(1) } else { 4 };
+                    spans.push(Span::styled(" ".repeat(indent), base_style)); // Indentation for continuation
+                    spans.extend(highlight_spans(message_line, search_query, line_current_match(message_line), &app.highlight_terms, base_style));
+                    lines.push(Line::from(spans));
                 }
             }
-            
-            lines
-        })
-        .collect();
 
-    let paragraph = Paragraph::new(log_content)
-        .scroll((scroll_offset as u16, 0));
-
-    f.render_widget(paragraph, log_area);
+            // メモのあるエントリは先頭行の左端に印を付け、他の行は同じ幅の空白でそろえる
+            if !app.notes.is_empty() {
+                let noted = app.notes.contains_key(&log.id);
+                for (line_index, line) in lines[first_entry_line..].iter_mut().enumerate() {
+                    let marker = if noted && line_index == 0 { Span::styled("✎ ", Style::default().fg(Color::Yellow)) } else { Span::raw("  ") };
+                    line.spans.insert(0, marker);
+                }
+            }
 
-    if !app.filtered_logs.is_empty() {
-        let total_display_lines = app.filtered_logs
-            .iter()
-            .map(App::calculate_display_lines)
-            .sum::<usize>();
-        let start_line = app.log_scroll_position + 1;
-        let end_line = (app.log_scroll_position + visible_lines).min(total_display_lines);
-        
-        let pagination_text = format!("{}-{} of {} lines ({} entries)", 
-            start_line, end_line, total_display_lines, app.filtered_logs.len());
-        let pagination_paragraph = Paragraph::new(pagination_text)
-            .style(Style::default().fg(Color::DarkGray));
+            // 背景色のあるエントリ（フォーカス中・縞模様）は背景色を行の右端まで伸ばす
+            if is_current || striped {
+                for line in &mut lines[first_entry_line..] {
+                    let padding = (width as usize).saturating_sub(line.width());
+                    if padding > 0 {
+                        line.spans.push(Span::styled(" ".repeat(padding), base_style));
+                    }
+                }
+            }
             
-        f.render_widget(pagination_paragraph, pagination_area);
-    }
+            // キャッシュに残すので、借用している文字列を所有する形にする
+            lines
+                .into_iter()
+                .map(|line| Line::from(line.spans.into_iter().map(|span| Span::styled(span.content.into_owned(), span.style)).collect::<Vec<_>>()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
 }
 
 fn render_log_level_filter(f: &mut Frame, app: &App, area: Rect) {
     let items: Vec<ListItem> = app.available_log_levels
         .iter()
-        .enumerate()
-        .map(|(_index, level)| {
+        .map(|level| {
             let checkbox = if app.log_level_filter.contains(level) { "☑" } else { "☐" };
             
             // フォーカスがある場合は矢印分を空けておく、ない場合は直接スペースを追加
@@ -921,16 +3576,10 @@ fn render_log_level_filter(f: &mut Frame, app: &App, area: Rect) {
                 "  "
             };
             
-            let content = format!("{}{} {}", prefix, checkbox, level);
+            let count = app.visible_stats.by_level.get(level).copied().unwrap_or(0);
+            let content = format!("{}{} {} ({})", prefix, checkbox, level, count);
             
-            let style = match level.as_str() {
-                "ERROR" => Style::default().fg(Color::Red),
-                "WARN" => Style::default().fg(Color::Yellow),
-                "INFO" => Style::default().fg(Color::Green),
-                "DEBUG" => Style::default().fg(Color::Blue),
-                "TRACE" => Style::default().fg(Color::Magenta),
-                _ => Style::default().fg(Color::White),
-            };
+            let style = app.theme.level_style(level);
 
             let final_style = if !app.log_level_filter.contains(level) {
                 style.fg(Color::DarkGray)
@@ -955,6 +3604,30 @@ fn render_log_level_filter(f: &mut Frame, app: &App, area: Rect) {
     f.render_stateful_widget(list, area, &mut list_state);
 }
 
+fn render_source_filter(f: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app.sources
+        .iter()
+        .map(|source| {
+            let visible = !app.hidden_sources.contains(source);
+            let checkbox = if visible { "☑" } else { "☐" };
+            let prefix = if app.mode == AppMode::SourceFilter { "" } else { "  " };
+            let style = if visible { Style::default() } else { Style::default().fg(Color::DarkGray) };
+            ListItem::new(Line::from(Span::styled(format!("{}{} {}", prefix, checkbox, source), style)))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .highlight_style(Style::default().bg(Color::DarkGray).add_modifier(Modifier::BOLD))
+        .highlight_symbol("→ ");
+
+    let mut list_state = ListState::default();
+    if app.mode == AppMode::SourceFilter && app.selected_source_index < app.sources.len() {
+        list_state.select(Some(app.selected_source_index));
+    }
+
+    f.render_stateful_widget(list, area, &mut list_state);
+}
+
 fn create_colored_help_line(parts: Vec<(&str, &str)>) -> Line<'static> {
     let mut spans = Vec::new();
     
@@ -970,8 +3643,52 @@ fn create_colored_help_line(parts: Vec<(&str, &str)>) -> Line<'static> {
     Line::from(spans)
 }
 
+/// One line naming the inputs and format, with the follow and frozen state on the right
+fn render_header(f: &mut Frame, app: &App, area: Rect) {
+    let style = Style::default().fg(Color::White).bg(Color::DarkGray);
+    let mut state = vec![Span::styled(
+        if app.auto_follow { " FOLLOW " } else { " SCROLL " },
+        Style::default().fg(Color::Black).bg(if app.auto_follow { Color::Green } else { Color::Magenta }),
+    )];
+    if app.frozen {
+        state.push(Span::styled(
+            format!(" {} ", app.frozen_badge()),
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+    }
+    if let Some(badge) = app.polling_badge() {
+        state.push(Span::styled(format!(" {} ", badge), Style::default().fg(Color::Black).bg(Color::Yellow)));
+    }
+    let state = Line::from(state);
+    let state_width = (state.width() as u16).min(area.width / 2);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(state_width)])
+        .split(area);
+
+    let title = Line::from(vec![
+        Span::styled(" tracing-viewer ", style.add_modifier(Modifier::BOLD)),
+        Span::styled(format!("│ {} ", app.input_label), style),
+        Span::styled(format!("│ {} ", app.format_label), style.fg(Color::Gray)),
+    ]);
+    f.render_widget(Paragraph::new(title).style(style), chunks[0]);
+    if state_width > 0 {
+        f.render_widget(Paragraph::new(state).style(style), chunks[1]);
+    }
+}
+
 fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
-    if let Some(ref message) = app.copy_message {
+    if let Some(ref input) = app.input {
+        let input_line = Line::from(vec![
+            Span::styled(input.kind.prompt(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::raw(input.buffer.clone()),
+            Span::styled("▏", Style::default().fg(Color::White).add_modifier(Modifier::SLOW_BLINK)),
+        ]);
+        f.render_widget(Paragraph::new(input_line), area);
+        return;
+    }
+
+    if let Some(ref message) = app.status_message {
         let status_paragraph = Paragraph::new(message.clone())
             .style(Style::default().fg(Color::Green));
         f.render_widget(status_paragraph, area);
@@ -979,12 +3696,30 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
     }
 
     let help_line = match app.mode {
+        AppMode::ModuleSelection if app.module_visual_anchor.is_some() => {
+            let parts = vec![
+                ("↑↓/jk", "Extend range"),
+                ("Space", "Toggle range"),
+                ("Esc/V", "Cancel"),
+            ];
+
+            let mut spans = vec![
+                Span::styled("Module Range: ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            ];
+            spans.extend(create_colored_help_line(parts).spans);
+            Line::from(spans)
+        },
         AppMode::ModuleSelection => {
             let mut parts = vec![
                 ("↑↓/jk", "Navigate"),
                 ("Space", "Toggle"),
+                ("V", "Range"),
+                ("l", "Module level"),
                 ("a", "All"),
                 ("n", "None"),
+                ("C", "Clear filters"),
+                (":", "Commands"),
+                ("b", "Hide panel"),
             ];
             if app.show_filter_panel {
                 parts.extend_from_slice(&[(",/.", "Resize panel")]);
@@ -1002,7 +3737,35 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
                 ("↑↓/jk", "Move focus"),
                 ("Wheel", "Scroll view"),
                 ("PgUp/PgDn", "Page scroll"),
+                ("^u/^d", "Half page"),
                 ("v", "Select text"),
+                ("/", "Search"),
+                ("n/N", "Next/prev match"),
+                ("t", "Time mode"),
+                ("#", "Line numbers"),
+                ("M", "Messages only"),
+                ("P", "Freeze"),
+                ("H", "Header"),
+                ("L/<", "Min level"),
+                ("D", "Compare"),
+                ("A/B", "Mark entry"),
+                ("a", "Note"),
+                ("r", "Raw"),
+                ("h", "Highlight"),
+                ("C", "Clear filters"),
+                ("e", "Copy editor cmd"),
+                ("E", "Copy view cmd"),
+                ("y", "Copy field"),
+                ("R", "Reload"),
+                ("S", "Split"),
+                ("m", "Minimap"),
+                ("p", "Test pattern"),
+                ("F", "Has field"),
+                ("T", "Trace"),
+                ("&", "Grep"),
+                (":", "Commands"),
+                ("b", if app.show_filter_panel { "Hide panel" } else { "Show panel" }),
+                ("Enter", "Details"),
             ];
             if app.show_filter_panel {
                 parts.push(("Tab", "Modules"));
@@ -1022,7 +3785,10 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
                 ("↑↓/jk", "Extend selection"),
                 ("Wheel", "Scroll view"),
                 ("PgUp/PgDn", "Page scroll"),
+                ("^u/^d", "Half page"),
                 ("y", "Copy"),
+                ("Y", "Copy with fields"),
+                ("A/B", "Capture compare window"),
                 ("Esc", "Cancel"),
             ];
             
@@ -1033,10 +3799,14 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
             Line::from(spans)
         },
         AppMode::LogLevelFilter => {
+            let quick_keys = format!("1-{}", app.severity.levels().len().clamp(1, 9));
             let mut parts = vec![
                 ("↑↓/jk", "Navigate"),
                 ("Space", "Toggle level"),
-                ("1-5", "Quick toggle"),
+                (quick_keys.as_str(), "Quick toggle"),
+                ("L/<", "Cycle min level"),
+                ("C", "Clear filters"),
+                ("b", "Hide panel"),
             ];
             if app.show_filter_panel {
                 parts.push((",/.", "Resize panel"));
@@ -1049,8 +3819,1297 @@ fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
             spans.extend(create_colored_help_line(parts).spans);
             Line::from(spans)
         },
+        AppMode::SourceFilter => {
+            let mut parts = vec![
+                ("↑↓/jk", "Navigate"),
+                ("Space", "Toggle source"),
+                ("C", "Clear filters"),
+                ("b", "Hide panel"),
+            ];
+            if app.show_filter_panel {
+                parts.push((",/.", "Resize panel"));
+            }
+            parts.extend_from_slice(&[("Tab", "Logs"), ("q", "Quit")]);
+
+            let mut spans = vec![
+                Span::styled("Source Filter: ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            ];
+            spans.extend(create_colored_help_line(parts).spans);
+            Line::from(spans)
+        },
     };
 
+    let mut help_line = help_line;
+    let left = status_segment_spans(app, &app.status_left);
+    if !left.is_empty() {
+        let mut spans = left;
+        spans.push(Span::raw(" "));
+        spans.extend(help_line.spans);
+        help_line = Line::from(spans);
+    }
+
+    // 右側のセグメント（既定では有効なフィルタ）は右端に表示
+    let mut right = Vec::new();
+    if app.frozen {
+        // 凍結中は設定に関係なく常に表示する
+        right.push(Span::styled(
+            app.frozen_badge(),
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+    }
+    // ポーリングに切り替えたファイルがあれば常に表示する
+    if let Some(badge) = app.polling_badge() {
+        if !right.is_empty() {
+            right.push(Span::raw(" "));
+        }
+        right.push(Span::styled(badge, Style::default().fg(Color::Black).bg(Color::Yellow)));
+    }
+    // フィルタのセグメントを表示しない設定でも、最低レベルのしきい値は常に表示する
+    let shows_filters = app.status_left.iter().chain(&app.status_right).any(|segment| *segment == StatusSegment::Filters);
+    if let Some(level) = app.level_threshold()
+        && !shows_filters
+    {
+        if !right.is_empty() {
+            right.push(Span::raw(" "));
+        }
+        right.push(Span::styled(format!("{}+", level), app.theme.level_style(level).add_modifier(Modifier::REVERSED | Modifier::BOLD)));
+    }
+    if let Some(summary) = app.mark_summary() {
+        if !right.is_empty() {
+            right.push(Span::raw(" "));
+        }
+        right.push(Span::styled(summary, Style::default().fg(Color::Black).bg(Color::Magenta).add_modifier(Modifier::BOLD)));
+    }
+    // ハイライト中の語をそれぞれの色で並べて凡例にする
+    for (term, style) in &app.highlight_terms {
+        if !right.is_empty() {
+            right.push(Span::raw(" "));
+        }
+        right.push(Span::styled(term.clone(), *style));
+    }
+    let segments = status_segment_spans(app, &app.status_right);
+    if !right.is_empty() && !segments.is_empty() {
+        right.push(Span::raw(" "));
+    }
+    right.extend(segments);
+    let right = Line::from(right);
+    let right_width = (right.width() as u16).min(area.width / 2);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(right_width)])
+        .split(area);
+
     let status_paragraph = Paragraph::new(help_line);
-    f.render_widget(status_paragraph, area);
-}
\ No newline at end of file
+    f.render_widget(status_paragraph, chunks[0]);
+
+    if right_width > 0 {
+        f.render_widget(Paragraph::new(right), chunks[1]);
+    }
+}
+
+/// Styled spans for `segments`, separated by spaces; segments with nothing to show are skipped
+fn status_segment_spans(app: &App, segments: &[StatusSegment]) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    for segment in segments {
+        let Some(text) = app.status_segment_text(*segment) else {
+            continue;
+        };
+        if !spans.is_empty() {
+            spans.push(Span::raw(" "));
+        }
+        let style = match segment {
+            StatusSegment::Filters => Style::default().fg(Color::Black).bg(Color::Yellow),
+            StatusSegment::Follow if !app.auto_follow => Style::default().fg(Color::Black).bg(Color::Magenta),
+            _ => Style::default().fg(Color::Black).bg(Color::Gray),
+        };
+        spans.push(Span::styled(text, style));
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_parser::{SeverityOrder, SeverityPreset};
+
+    fn entry(level: &str, target: &str, message: &str) -> LogEntry {
+        LogEntry {
+            level: level.to_string(),
+            target: target.to_string(),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_visible_counts_follow_filters() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app::db", ""), entry("WARN", "app::db::pool", ""), entry("INFO", "app::http", "")]);
+        assert_eq!(app.module_counts["app"], 3);
+        assert_eq!(app.module_counts["app::db"], 2);
+        assert_eq!(app.visible_stats.by_level["INFO"], 2);
+
+        app.add_logs(vec![entry("ERROR", "app::db", "")]);
+        assert_eq!(app.module_counts["app::db"], 3);
+
+        app.log_level_filter.remove("INFO");
+        app.filter_logs();
+        assert_eq!(app.module_counts["app"], 2);
+        assert_eq!(app.visible_stats.by_level.get("INFO"), None);
+    }
+
+    #[test]
+    fn test_target_cache_matches_uncached_filter() {
+        let targets = ["app", "app::db", "app::db::pool", "hyper"];
+        let levels = SeverityOrder::default();
+        let logs: Vec<LogEntry> = (0..50).map(|i| entry(&levels.levels()[i % levels.levels().len()], targets[i % targets.len()], "")).collect();
+        let mut app = App::new();
+        app.update_logs(logs);
+
+        let uncached = |app: &App| -> Vec<(String, String)> {
+            app.logs
+                .iter()
+                .filter(|log| app.module_tree.is_module_selected(&log.target))
+                .filter(|log| match app.module_tree.module_min_level(&log.target) {
+                    Some(min_level) => levels.rank(&log.level) <= levels.rank(min_level),
+                    None => app.log_level_filter.contains(&log.level),
+                })
+                .map(|log| (log.level.clone(), log.target.clone()))
+                .collect()
+        };
+        let cached = |app: &App| -> Vec<(String, String)> {
+            app.filtered_logs.iter().map(|log| (log.level.clone(), log.target.clone())).collect()
+        };
+
+        // 木を変更するたびにキャッシュが作り直されることを確かめる
+        app.toggle_log_level("TRACE");
+        assert_eq!(cached(&app), uncached(&app));
+        app.module_tree.toggle_selection("hyper");
+        app.filter_logs();
+        assert_eq!(cached(&app), uncached(&app));
+        app.module_tree.set_min_level("app::db", Some("ERROR".to_string()));
+        app.filter_logs();
+        assert_eq!(cached(&app), uncached(&app));
+        assert_eq!(app.target_cache["app::db::pool"], (true, Some("ERROR".to_string())));
+        app.module_tree.toggle_selection("hyper");
+        app.filter_logs();
+        assert_eq!(cached(&app), uncached(&app));
+        app.add_logs(vec![entry("INFO", "hyper", ""), entry("INFO", "app::cache", "")]);
+        assert_eq!(cached(&app), uncached(&app));
+    }
+
+    #[test]
+    fn test_module_visual_range_toggles_once() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "a", ""), entry("INFO", "b", ""), entry("INFO", "c", ""), entry("INFO", "d", "")]);
+
+        app.module_list_state.select(Some(1));
+        app.start_module_visual();
+        app.next_module();
+        assert_eq!(app.module_visual_range(), Some((1, 2)));
+        app.toggle_module_visual_range();
+
+        let selected: Vec<bool> = app.module_items.iter().map(|item| item.is_selected).collect();
+        assert_eq!(selected, vec![true, false, false, true]);
+        assert_eq!(app.filtered_logs.len(), 2);
+        assert!(app.module_visual_anchor.is_none());
+    }
+
+    #[test]
+    fn test_wheel_scroll_keeps_focus() {
+        let mut app = App::new();
+        let logs = (0..50)
+            .map(|i| entry("INFO", "app", &i.to_string()))
+            .collect();
+        app.update_logs(logs);
+        app.log_scroll_position = 0;
+        app.current_log_line = 10;
+        app.auto_follow = false;
+
+        app.wheel_down();
+        app.update_scroll_position_with_height(20);
+        assert_eq!(app.log_scroll_position, 3);
+        assert_eq!(app.current_log_line, 10);
+
+        app.wheel_up();
+        app.update_scroll_position_with_height(20);
+        assert_eq!(app.log_scroll_position, 0);
+        assert_eq!(app.current_log_line, 10);
+    }
+
+    #[test]
+    fn test_paging_counts_display_lines_of_tall_entries() {
+        let mut app = App::new();
+        let tall = |first: &str| {
+            let message = std::iter::once(first.to_string()).chain((1..25).map(|line| format!("  at frame {}", line))).collect::<Vec<_>>().join("\n");
+            entry("INFO", "app", &message)
+        };
+        app.update_logs(vec![tall("a"), tall("b"), tall("c")]);
+        app.switch_to_log_mode();
+        app.current_log_line = 0;
+        app.log_scroll_position = 0;
+
+        // 1ページは表示行で数え、1件が画面より高くても飛ばさない
+        let mut positions = Vec::new();
+        for _ in 0..3 {
+            app.page_down(10);
+            app.update_scroll_position_with_height(10);
+            positions.push((app.log_scroll_position, app.current_log_line));
+        }
+        assert_eq!(positions, [(9, 0), (18, 0), (27, 1)]);
+        for _ in 0..10 {
+            app.page_down(10);
+        }
+        assert_eq!(app.log_scroll_position, 65);
+        app.page_up(10);
+        app.update_scroll_position_with_height(10);
+        assert_eq!((app.log_scroll_position, app.current_log_line), (56, 2));
+
+        // 区切り線の行も数える
+        app.entry_separator = EntrySeparator::Rule;
+        assert_eq!(app.total_display_lines(), 77);
+        for _ in 0..10 {
+            app.page_down(10);
+        }
+        assert_eq!(app.log_scroll_position, 67);
+
+        // 半ページ送りも表示行で数える
+        app.entry_separator = EntrySeparator::None;
+        app.log_scroll_position = 0;
+        let mut positions = Vec::new();
+        for _ in 0..3 {
+            app.half_page_down(10);
+            app.update_scroll_position_with_height(10);
+            positions.push((app.log_scroll_position, app.current_log_line));
+        }
+        assert_eq!(positions, [(5, 0), (10, 0), (15, 0)]);
+        for _ in 0..3 {
+            app.half_page_down(10);
+        }
+        app.update_scroll_position_with_height(10);
+        assert_eq!((app.log_scroll_position, app.current_log_line), (30, 1));
+        for _ in 0..20 {
+            app.half_page_down(10);
+        }
+        assert_eq!(app.log_scroll_position, 65);
+        app.half_page_up(10);
+        assert_eq!(app.log_scroll_position, 60);
+        app.half_page_up(1);
+        assert_eq!(app.log_scroll_position, 59);
+    }
+
+    #[test]
+    fn test_empty_messages_and_day_headers_take_display_lines() {
+        let at = |timestamp: &str, message: &str| LogEntry { timestamp: timestamp.to_string(), ..entry("INFO", "app", message) };
+        let mut app = App::new();
+        app.update_logs(vec![
+            at("2024-01-01T23:59:00Z", ""),
+            at("2024-01-02T00:00:01Z", "first\nsecond"),
+            at("2024-01-02T00:00:02Z", ""),
+        ]);
+        // 空のメッセージも1行として描画される
+        assert_eq!((0..3).map(|index| app.calculate_display_lines(index)).collect::<Vec<_>>(), [1, 2, 1]);
+        app.show_day_separators = true;
+        assert_eq!((0..3).map(|index| app.calculate_display_lines(index)).collect::<Vec<_>>(), [1, 3, 1]);
+        assert_eq!(app.get_entry_display_position(2), 4);
+
+        app.show_filter_panel = false;
+        let backend = ratatui::backend::TestBackend::new(100, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..12u16).map(|y| (0..100).map(|x| buffer[(x, y)].symbol()).collect()).collect();
+        let header = rows.iter().position(|row| row.contains("── 2024-01-02 ──")).unwrap();
+        assert!(rows[header - 1].contains("INFO"));
+        assert!(rows[header + 1].contains("first") && rows[header + 2].contains("second"));
+        assert!(rows[header + 3].contains("INFO"));
+    }
+
+    #[test]
+    fn test_sticky_follow_snaps_only_near_bottom() {
+        let log = entry("INFO", "app", "");
+        let mut app = App::new();
+        app.follow_stickiness = Some(2);
+        app.update_logs(vec![log.clone(); 10]);
+        assert_eq!(app.log_scroll_position, 9);
+
+        // 最終行から2行以内なら追従を続ける
+        app.scroll_up(2);
+        assert!(!app.auto_follow);
+        app.add_logs(vec![log.clone()]);
+        assert_eq!((app.log_scroll_position, app.current_log_line), (10, 10));
+        assert!(app.auto_follow);
+
+        app.scroll_up(5);
+        app.add_logs(vec![log.clone()]);
+        assert_eq!((app.log_scroll_position, app.current_log_line), (5, 10));
+
+        // 離れていれば自動追従が有効でも表示位置は動かない
+        app.auto_follow = true;
+        app.add_logs(vec![log]);
+        assert_eq!(app.log_scroll_position, 5);
+    }
+
+    #[test]
+    fn test_sticky_follow_measures_from_the_bottom_row() {
+        let log = entry("INFO", "app", "");
+        let mut app = App::new();
+        app.follow_stickiness = Some(3);
+        app.update_logs(vec![log.clone(); 100]);
+        app.switch_to_log_mode();
+        let backend = ratatui::backend::TestBackend::new(120, 30);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let bottom_scroll = app.log_scroll_position;
+        assert_eq!(bottom_scroll + app.log_view_height, 100);
+
+        // 最下行が最終行から2行上なら、画面の高さに関係なく追従する
+        app.scroll_up(2);
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        app.add_logs(vec![log.clone()]);
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        assert_eq!(app.log_scroll_position, bottom_scroll + 1);
+
+        app.scroll_up(10);
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        app.add_logs(vec![log]);
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        assert_eq!(app.log_scroll_position, bottom_scroll - 9);
+    }
+
+    #[test]
+    fn test_entry_navigation_toggles_auto_follow() {
+        let log = entry("INFO", "app", "");
+        let mut app = App::new();
+        app.update_logs(vec![log.clone(), log.clone(), log]);
+        app.current_log_line = 2;
+        app.auto_follow = true;
+
+        app.previous_log_entry();
+        assert_eq!(app.current_log_line, 1);
+        assert!(!app.auto_follow);
+        assert!(app.last_action_was_focus_move);
+
+        app.mode = AppMode::TextSelection;
+        app.selection_start = Some(1);
+        app.next_log_entry();
+        assert_eq!(app.current_log_line, 2);
+        assert_eq!(app.selection_end, Some(2));
+        // 最終行に戻ると自動追従が再開される
+        assert!(app.auto_follow);
+
+        app.next_log_entry();
+        assert_eq!(app.current_log_line, 2);
+    }
+
+    #[test]
+    fn test_reload_source_keeps_module_selection() {
+        let from = |target: &str, source: &str| LogEntry { source: source.to_string(), ..entry("INFO", target, "") };
+        let mut app = App::new();
+        app.update_logs(vec![from("app::db", "app.log"), from("app::http", "app.log"), from("app::db", "stdin")]);
+        app.module_tree.toggle_selection("app::db");
+        app.module_tree.set_min_level("app::http", Some("WARN".to_string()));
+        app.rebuild_module_items();
+
+        app.reload_source("app.log", vec![from("app::db", "app.log"), from("app::cache", "app.log")]);
+        assert_eq!(app.logs.len(), 3);
+        assert_eq!(app.logs[2].source, "stdin");
+        assert!(!app.module_tree.is_module_selected("app::db"));
+        assert!(app.module_tree.is_module_selected("app::cache"));
+        assert_eq!(app.filtered_logs.len(), 1);
+    }
+
+    #[test]
+    fn test_timestamp_precision_is_display_only() {
+        let mut app = App::new();
+        app.update_logs(vec![LogEntry { timestamp: "2024-01-01T12:00:00.123456789Z".to_string(), ..entry("INFO", "app", "ready") }]);
+        // 既定では書かれたまま表示する
+        assert_eq!(app.timestamp_labels(0, 1), ["2024-01-01T12:00:00.123456789Z"]);
+        app.timestamp_precision = Some(3);
+        assert_eq!(app.timestamp_labels(0, 1), ["2024-01-01T12:00:00.123Z"]);
+
+        // オフセット付きのタイムスタンプが混じっても幅をそろえる
+        let mut offsets = App::new();
+        offsets.timestamp_precision = Some(3);
+        offsets.update_logs(
+            ["2024-01-01T12:00:00.5Z", "2024-01-01T21:00:01+09:00", "2024-01-01T12:00:02Z"]
+                .map(|timestamp| LogEntry { timestamp: timestamp.to_string(), ..Default::default() })
+                .to_vec(),
+        );
+        assert_eq!(
+            offsets.timestamp_labels(0, 3),
+            ["2024-01-01T12:00:00.500Z     ", "2024-01-01T21:00:01.000+09:00", "2024-01-01T12:00:02.000Z     "]
+        );
+
+        // コピーには保存されている元の精度のまま出力される
+        app.start_text_selection();
+        let copied = app.copy_selected_logs().unwrap();
+        assert!(copied.contains("2024-01-01T12:00:00.123456789Z"));
+        assert_eq!(app.filtered_logs[0].timestamp, "2024-01-01T12:00:00.123456789Z");
+    }
+
+    #[test]
+    fn test_minimap_bands_and_click() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", ""), entry("ERROR", "app", ""), entry("DEBUG", "app", ""), entry("WARN", "app", ""), entry("INFO", "app", ""), entry("INFO", "app", "")]);
+
+        assert_eq!(app.minimap_bands(3), vec![Some(0), Some(1), Some(2)]);
+        // 行数がエントリ数より多い場合も各行に1件以上割り当てる
+        assert_eq!(app.minimap_bands(12).len(), 12);
+        assert!(app.minimap_bands(12).iter().all(Option::is_some));
+
+        app.minimap_area = Some(Rect::new(79, 0, 1, 3));
+        assert!(app.on_click(79, 1));
+        assert_eq!(app.current_log_line, 2);
+        assert!(!app.on_click(10, 1));
+    }
+
+    #[test]
+    fn test_render_survives_tiny_terminals() {
+        let mut app = App::new();
+        app.update_logs(vec![LogEntry { timestamp: "2024-01-01T12:00:00.123Z".to_string(), ..entry("INFO", "app::db", "connected\nsecond line") }]);
+        app.switch_to_module_mode();
+        app.show_split = true;
+        app.show_minimap = true;
+        app.show_error("watch failed".to_string());
+
+        for (width, height) in [(0, 0), (1, 1), (12, 3), (40, 6), (120, 40)] {
+            let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(width, height)).unwrap();
+            terminal.draw(|f| render(f, &mut app)).unwrap();
+            assert_eq!(app.compact_layout, width < MIN_PANEL_LAYOUT_WIDTH || height < MIN_PANEL_LAYOUT_HEIGHT);
+        }
+        assert_eq!(app.mode, AppMode::LogNavigation);
+    }
+
+    #[test]
+    fn test_search_cycles_through_each_occurrence() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", "retry 1"), entry("INFO", "app", "no match"), entry("INFO", "app", "retry 2\nretry 3")]);
+        app.current_log_line = 0;
+
+        app.apply_search("retry".to_string());
+        assert_eq!(app.search_match, Some((0, 0)));
+        app.search_next();
+        assert_eq!(app.search_match, Some((2, 0)));
+        app.search_next();
+        assert_eq!((app.current_log_line, app.search_match), (2, Some((2, 1))));
+        app.search_next();
+        assert_eq!(app.search_match, Some((0, 0)));
+        app.search_previous();
+        assert_eq!(app.search_match, Some((2, 1)));
+
+        // 元の行の表示では、タイムスタンプなどを含む元の行で数える
+        let mut raw = App::new();
+        let with_raw = |message: &str, raw: &str| LogEntry { raw: raw.to_string(), ..entry("INFO", "retry", message) };
+        raw.update_logs(vec![
+            with_raw("retry 1", "2024-01-01T12:00:00Z INFO retry: retry 1"),
+            with_raw("no match", "2024-01-01T12:00:01Z INFO retry: no match"),
+            with_raw("done", "2024-01-01T12:00:02Z INFO app: done"),
+        ]);
+        raw.current_log_line = 0;
+        raw.toggle_raw_view();
+        raw.apply_search("retry".to_string());
+        assert_eq!(raw.search_match, Some((0, 0)));
+        raw.search_next();
+        assert_eq!(raw.search_match, Some((0, 1)));
+        raw.search_next();
+        assert_eq!(raw.search_match, Some((1, 0)));
+        raw.search_next();
+        assert_eq!(raw.search_match, Some((0, 0)));
+        // 解析後の表示に戻すと数え直し、メッセージに一致しないエントリでは現在のマッチがなくなる
+        raw.search_previous();
+        raw.toggle_raw_view();
+        assert_eq!(raw.search_match, None);
+        raw.search_next();
+        assert_eq!(raw.search_match, Some((0, 0)));
+
+        let base = Style::default();
+        let spans = highlight_spans("retry retry", Some("retry"), Some(1), &[], base);
+        assert_eq!(spans[0].style, base.fg(Color::Black).bg(Color::Yellow));
+        assert_eq!(spans[2].style.bg, Some(Color::LightRed));
+    }
+
+    #[test]
+    fn test_highlight_terms_color_every_occurrence() {
+        let mut app = App::new();
+        app.toggle_highlight_term("timeout");
+        app.toggle_highlight_term("retry");
+        app.toggle_highlight_term("RETRY");
+        assert_eq!(app.highlight_terms.len(), 1);
+        app.add_highlight_term("retry");
+        app.add_highlight_term("req-42");
+        let colors: Vec<Option<Color>> = app.highlight_terms.iter().map(|(_, style)| style.bg).collect();
+        // 消した語の色は次に追加した語が使う
+        assert_eq!(colors, [Some(Color::Cyan), Some(Color::Magenta), Some(Color::Green)]);
+
+        let base = Style::default();
+        let spans = highlight_spans("Timeout on req-42, retry after timeout", Some("on req"), None, &app.highlight_terms, base);
+        let styled: Vec<(&str, Option<Color>)> = spans.iter().map(|span| (span.content.as_ref(), span.style.bg)).collect();
+        assert_eq!(
+            styled,
+            [
+                ("Timeout", Some(Color::Cyan)),
+                (" ", None),
+                // 検索のマッチが重なる語より優先される
+                ("on req", Some(Color::Yellow)),
+                ("-42, ", None),
+                ("retry", Some(Color::Magenta)),
+                (" after ", None),
+                ("timeout", Some(Color::Cyan)),
+            ]
+        );
+        assert!(app.view_command().ends_with("--highlight timeout --highlight retry --highlight req-42"));
+    }
+
+    #[test]
+    fn test_log_lines_are_cached_between_draws() {
+        let mut app = App::new();
+        app.mode = AppMode::LogNavigation;
+        app.update_logs(vec![entry("INFO", "app", "first"), entry("INFO", "app", "second")]);
+        let backend = ratatui::backend::TestBackend::new(60, 8);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let mut draw = |app: &mut App| {
+            terminal.draw(|f| render(f, app)).unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            let key = app.log_view_cache.as_ref().unwrap().0;
+            let focused_row = (0..8).find(|&y| buffer[(59, y)].bg == app.focus_color);
+            let text: String = (0..8).flat_map(|y| (0..60).map(move |x| (x, y))).map(|(x, y)| buffer[(x, y)].symbol().to_string()).collect();
+            (key, focused_row, text)
+        };
+
+        let (key, focused_row, _) = draw(&mut app);
+        // 何も変わっていなければ同じキーで作り直さない
+        assert_eq!(draw(&mut app).0, key);
+
+        // フォーカスの移動と新しいエントリはどちらも描画に反映される
+        app.current_log_line = 0;
+        let (moved_key, moved_row, _) = draw(&mut app);
+        assert_ne!(moved_key, key);
+        assert_ne!(moved_row, focused_row);
+        app.add_logs(vec![entry("INFO", "app", "third")]);
+        let (_, _, text) = draw(&mut app);
+        assert!(text.contains("third"));
+    }
+
+    #[test]
+    fn test_pinned_fields_come_first() {
+        let mut entry = LogEntry::default();
+        for key in ["user", "error", "path", "trace_id"] {
+            entry.fields.insert(key.to_string(), "x".to_string());
+        }
+        let pinned = vec!["trace_id".to_string(), "missing".to_string(), "error".to_string()];
+        let keys: Vec<&str> = ordered_fields(&entry, &pinned).map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["trace_id", "error", "user", "path"]);
+        assert!(ordered_fields(&entry, &[]).map(|(key, _)| key).eq(entry.fields.keys()));
+    }
+
+    #[test]
+    fn test_entry_marks_measure_time_between() {
+        let at = |timestamp: &str, level: &str| LogEntry { timestamp: timestamp.to_string(), ..entry(level, "app", "") };
+        let mut app = App::new();
+        let logs = vec![at("2024-01-01T12:00:00Z", "INFO"), at("2024-01-01T12:00:00.500Z", "DEBUG"), at("2024-01-01T12:00:01.250Z", "ERROR")];
+        app.update_logs(logs.clone());
+        app.current_log_line = 0;
+        app.toggle_entry_mark(0);
+        assert_eq!(app.mark_summary().as_deref(), Some("mark A"));
+        app.current_log_line = 2;
+        app.toggle_entry_mark(1);
+        assert_eq!(app.mark_summary().as_deref(), Some("A→B +1.250s, 2 entries"));
+        assert_eq!(app.status_message, app.mark_summary());
+
+        // 間のエントリが隠れると距離も縮む
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.mark_summary().as_deref(), Some("A→B +1.250s, 1 entry"));
+        app.toggle_entry_mark(1);
+        assert_eq!(app.mark_summary().as_deref(), Some("mark A"));
+        // 読み直したエントリには印が残らない
+        app.update_logs(logs);
+        assert_eq!(app.mark_summary(), None);
+    }
+
+    #[test]
+    fn test_notes_show_in_gutter_and_come_back_for_the_same_file() {
+        let path = std::env::temp_dir().join(format!("tracing-viewer-noted-{}.log", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let source = path.display().to_string();
+        let logs: Vec<LogEntry> = ["first", "second"]
+            .iter()
+            .enumerate()
+            .map(|(index, message)| LogEntry {
+                source: source.clone(),
+                byte_offset: Some(index as u64 * 10),
+                ..entry("INFO", "app", message)
+            })
+            .collect();
+        let mut app = App::new();
+        app.update_logs(logs.clone());
+        app.switch_to_log_mode();
+        app.current_log_line = 1;
+        app.start_note();
+        for c in "broke here".chars() {
+            app.input_char(c);
+        }
+        app.commit_input();
+        assert_eq!(app.notes.get(&1).map(String::as_str), Some("broke here"));
+        assert!(app.input_history.entries("note").is_empty());
+
+        // コピーに含めるのは--export-notesの指定があるときだけ
+        app.copy_template = EntryTemplate::parse("{message}").unwrap();
+        (app.selection_start, app.selection_end) = (Some(0), Some(1));
+        assert_eq!(app.copy_selected_logs().unwrap(), "first\nsecond");
+        app.export_notes = true;
+        assert_eq!(app.copy_selected_logs().unwrap(), "first\nsecond\n    ✎ broke here");
+        (app.selection_start, app.selection_end) = (None, None);
+
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(60, 8)).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..8).map(|y| (0..60).map(|x| buffer[(x, y)].symbol()).collect()).collect();
+        assert!(rows.iter().any(|row| row.starts_with("✎ ") && row.contains("second")));
+        assert!(rows.iter().any(|row| row.starts_with("  ") && row.contains("first")));
+
+        // 同じファイルを開き直すとメモが戻り、空のメモで消える
+        let mut reopened = App::new();
+        reopened.saved_notes = app.saved_notes.clone();
+        reopened.update_logs(logs.clone());
+        assert_eq!(reopened.notes.get(&1).map(String::as_str), Some("broke here"));
+        reopened.current_log_line = 1;
+        reopened.start_note();
+        assert_eq!(reopened.input.as_ref().map(|input| input.buffer.as_str()), Some("broke here"));
+        reopened.set_note("");
+        assert!(reopened.notes.is_empty() && reopened.saved_notes.is_empty());
+
+        // 書き換わったファイルでは同じ位置でもメッセージが違えばメモを付けない
+        let mut rewritten = App::new();
+        rewritten.saved_notes = app.saved_notes.clone();
+        let mut changed = logs.clone();
+        changed[1].message = "something else".to_string();
+        rewritten.update_logs(changed);
+        assert!(rewritten.notes.is_empty());
+
+        // ファイルでないエントリのメモは読み直すと消える
+        let streamed = vec![LogEntry { message: "streamed".to_string(), source: "stdin".to_string(), ..Default::default() }];
+        rewritten.update_logs(streamed.clone());
+        rewritten.current_log_line = 0;
+        rewritten.set_note("gone after a reload");
+        assert_eq!(rewritten.notes.len(), 1);
+        rewritten.update_logs(streamed);
+        assert!(rewritten.notes.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_trace_filter_follows_trace_and_child_spans() {
+        let with_fields = |message: &str, fields: &[(&str, &str)]| LogEntry {
+            fields: fields.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+            ..entry("INFO", "app", message)
+        };
+        let mut app = App::new();
+        app.update_logs(vec![
+            with_fields("grandchild", &[("span_id", "3"), ("parent_span_id", "2")]),
+            with_fields("request", &[("span_id", "1"), ("trace_id", "t1")]),
+            with_fields("child", &[("span_id", "2"), ("parent_span_id", "1")]),
+            with_fields("other", &[("span_id", "9"), ("trace_id", "t2")]),
+            with_fields("plain", &[]),
+        ]);
+        let messages = |app: &App| app.filtered_logs.iter().map(|log| log.message.clone()).collect::<Vec<_>>();
+
+        app.current_log_line = 1;
+        app.toggle_trace_filter();
+        assert_eq!(messages(&app), ["request"]);
+        assert!(app.active_filter_labels().contains(&"[trace_id:t1]".to_string()));
+        app.toggle_trace_filter();
+        assert_eq!(app.filtered_logs.len(), 5);
+
+        // トレースIDがなければスパンとその子孫をたどる（親より先に記録された子も含む）
+        app.current_log_line = 2;
+        app.toggle_trace_filter();
+        assert_eq!(messages(&app), ["grandchild", "child"]);
+        app.add_logs(vec![with_fields("late", &[("span_id", "4"), ("parent_span_id", "3")]), with_fields("sibling", &[("span_id", "5"), ("parent_span_id", "1")])]);
+        assert_eq!(messages(&app), ["grandchild", "child", "late"]);
+
+        app.clear_all_filters();
+        app.current_log_line = 4;
+        app.toggle_trace_filter();
+        assert!(app.trace_filter.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("The focused entry has no trace or span id"));
+    }
+
+    #[test]
+    fn test_tabs_expand_to_tab_stops() {
+        assert_eq!(expand_tabs("id\tname\tok", 4), "id  name    ok");
+        assert_eq!(expand_tabs("\tx", 8), "        x");
+        assert!(matches!(expand_tabs("no tabs", 4), Cow::Borrowed(_)));
+
+        let mut app = App::new();
+        app.message_only = true;
+        app.tab_width = 4;
+        app.update_logs(vec![entry("INFO", "", "a\tb")]);
+        let backend = ratatui::backend::TestBackend::new(40, 6);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..6).map(|y| (0..40).map(|x| buffer[(x, y)].symbol()).collect()).collect();
+        assert!(rows.iter().any(|row| row.starts_with("a   b")), "{:?}", rows);
+        // コピーされる内容はタブのまま
+        assert_eq!(app.filtered_logs[0].message, "a\tb");
+    }
+
+    #[test]
+    fn test_line_number_gutter() {
+        let numbered = |line_number: Option<usize>| LogEntry { line_number, ..entry("INFO", "app", "") };
+        let mut app = App::new();
+        app.update_logs((0..12).map(|i| numbered(Some(i * 100))).chain([numbered(None)]).collect());
+        assert_eq!(app.gutter_width(), 0);
+
+        app.cycle_line_number_mode();
+        assert_eq!((app.line_number(11), app.gutter_width()), (Some(12), 3));
+        app.cycle_line_number_mode();
+        assert_eq!((app.line_number(11), app.gutter_width()), (Some(1100), 5));
+        assert_eq!(app.line_number(12), None);
+
+        let backend = ratatui::backend::TestBackend::new(60, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        app.switch_to_log_mode();
+        app.scroll_to_bottom();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains(" 1100 "));
+    }
+
+    #[test]
+    fn test_continuation_marker() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("WARN", "app", "panicked\n  at src/main.rs:3")]);
+        app.continuation_marker = "│".to_string();
+        app.switch_to_log_mode();
+
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let (x, y) = (0..12u16)
+            .find_map(|y| {
+                let row: Vec<&str> = (0..80).map(|x| buffer[(x, y)].symbol()).collect();
+                let x = (0..73).find(|&x| row[x..x + 7].concat() == "│     a")?;
+                Some((x as u16, y))
+            })
+            .expect("marker before the continuation line");
+        assert_eq!(buffer[(x, y)].fg, Color::Yellow);
+        assert!(buffer[(x, y)].modifier.contains(Modifier::DIM));
+
+        // コピー内容にはマーカーが含まれない
+        app.start_text_selection();
+        assert!(!app.copy_selected_logs().unwrap().contains('│'));
+    }
+
+    #[test]
+    fn test_custom_severity_order() {
+        let mut app = App::new();
+        app.set_severity_order(SeverityOrder::preset(SeverityPreset::Syslog));
+        app.update_logs(vec![entry("NOTICE", "app", ""), entry("CRITICAL", "app", ""), entry("INFO", "app", ""), entry("RAW", "app", "")]);
+        // 順序にないレベルも一覧の末尾に加わり、既定では表示される
+        assert_eq!(app.available_log_levels.last().map(String::as_str), Some("RAW"));
+        assert_eq!(app.filtered_logs.len(), 4);
+
+        // NOTICEはWARNとINFOの間に入る
+        for _ in 0..3 {
+            app.cycle_min_level();
+        }
+        assert_eq!(app.level_threshold(), Some("NOTICE"));
+        let levels: Vec<&str> = app.filtered_logs.iter().map(|log| log.level.as_str()).collect();
+        assert_eq!(levels, ["NOTICE", "CRITICAL"]);
+
+        // しきい値より下の新しいレベルは無効のまま追加される
+        app.add_logs(vec![entry("VERBOSE", "app", ""), entry("ALERT", "app", "")]);
+        assert_eq!(app.filtered_logs.len(), 3);
+        assert!(!app.log_level_filter.contains("VERBOSE"));
+    }
+
+    #[test]
+    fn test_freeze_holds_incoming_entries() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", "a")]);
+        app.toggle_freeze();
+        app.add_logs(vec![entry("INFO", "app", "b"), entry("INFO", "app", "c")]);
+        assert_eq!((app.logs.len(), app.filtered_logs.len(), app.frozen_logs.len()), (1, 1, 2));
+
+        app.status_message = None;
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let status: String = (0..80).map(|x| buffer[(x, 11)].symbol()).collect();
+        assert!(status.contains("FROZEN +2"), "{}", status);
+
+        app.toggle_freeze();
+        let messages: Vec<&str> = app.logs.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, ["a", "b", "c"]);
+        assert_eq!(app.filtered_logs.len(), 3);
+        assert!(app.frozen_logs.is_empty());
+    }
+
+    #[test]
+    fn test_freeze_holds_reloads_and_caps_held_entries() {
+        let from = |source: &str, message: &str| LogEntry { source: source.to_string(), ..entry("INFO", "app", message) };
+        let mut app = App::new();
+        app.update_logs(vec![from("a.log", "old"), from("stdin", "piped")]);
+        app.toggle_freeze();
+        app.add_logs(vec![from("a.log", "held before reload")]);
+        app.reload_source("a.log", vec![from("a.log", "reloaded")]);
+        app.add_logs(vec![from("a.log", "after reload")]);
+        // 凍結中の読み直しは表示に反映しない
+        let shown: Vec<&str> = app.logs.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(shown, ["old", "piped"]);
+        assert_eq!(app.frozen_badge(), "FROZEN +1 reloaded");
+
+        app.toggle_freeze();
+        let shown: Vec<&str> = app.logs.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(shown, ["reloaded", "piped", "after reload"]);
+        assert!(app.frozen_reloads.is_empty());
+
+        // 保留する件数には上限があり、古いものから捨てる
+        app.frozen_log_limit = 2;
+        app.toggle_freeze();
+        app.add_logs(vec![from("a.log", "1"), from("a.log", "2"), from("a.log", "3")]);
+        assert_eq!((app.frozen_logs.len(), app.frozen_dropped), (2, 1));
+        assert_eq!(app.frozen_badge(), "FROZEN +2 (1 dropped)");
+        app.toggle_freeze();
+        let shown: Vec<&str> = app.logs.iter().skip(3).map(|log| log.message.as_str()).collect();
+        assert_eq!(shown, ["2", "3"]);
+        assert_eq!(app.frozen_dropped, 0);
+    }
+
+    #[test]
+    fn test_polling_fallback_badge() {
+        let mut app = App::new();
+        app.note_polling_fallback("app.log".to_string(), "inotify limit reached");
+        app.note_polling_fallback("app.log".to_string(), "inotify limit reached");
+        assert_eq!(app.polled_files, ["app.log"]);
+        assert!(app.error_banner.is_none());
+
+        app.status_message = None;
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let status: String = (0..80).map(|x| buffer[(x, 11)].symbol()).collect();
+        assert!(status.contains("POLLING") && !status.contains("POLLING 2"), "{}", status);
+
+        app.note_polling_fallback("other.log".to_string(), "inotify limit reached");
+        app.status_message = None;
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let status: String = (0..80).map(|x| buffer[(x, 11)].symbol()).collect();
+        assert!(status.contains("POLLING 2"), "{}", status);
+    }
+
+    #[test]
+    fn test_header_line() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", "hello")]);
+        app.input_label = "app.log, stdin".to_string();
+        app.format_label = "json".to_string();
+        app.toggle_header();
+        app.toggle_freeze();
+
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| (0..80).map(|x| buffer[(x, y)].symbol()).collect::<String>();
+        let header = row(0);
+        assert!(header.contains("app.log, stdin") && header.contains("json"), "{}", header);
+        assert!(header.contains("FOLLOW") && header.contains("FROZEN +0"), "{}", header);
+        // 本文はヘッダーの下から始まる
+        assert!(!header.contains("hello"));
+        assert!((1..11).any(|y| row(y).contains("hello")));
+    }
+
+    #[test]
+    fn test_view_command() {
+        let mut app = App::new();
+        app.launch_args = vec!["--input".to_string(), "my app.log".to_string()];
+        app.pending_hidden_modules = vec!["app::db".to_string(), "later".to_string()];
+        app.update_logs(vec![entry("ERROR", "app::db::pool", "timeout"), entry("WARN", "app::http", "timeout")]);
+        // 現れたモジュールだけが隠され、残りは後で現れるのを待つ
+        assert!(!app.module_tree.is_module_selected("app::db::pool"));
+        assert!(app.module_tree.is_module_selected("app::http"));
+        assert_eq!(app.pending_hidden_modules, ["later"]);
+        assert_eq!(app.view_command(), "tracing-viewer --input 'my app.log' --hide-module app::db --hide-module later");
+
+        app.set_shown_levels(&["error".to_string(), "warn".to_string()]);
+        app.grep_query = Some("it's".to_string());
+        app.apply_search("timeout".to_string());
+        assert_eq!(
+            app.view_command(),
+            "tracing-viewer --input 'my app.log' --show-levels ERROR,WARN --hide-module app::db --hide-module later --grep 'it'\\''s' --search timeout"
+        );
+        assert_eq!(app.filtered_logs.len(), 1);
+    }
+
+    #[test]
+    fn test_message_only_view() {
+        let mut app = App::new();
+        app.update_logs(vec![LogEntry { timestamp: "2024-01-01T12:00:00Z".to_string(), ..entry("ERROR", "app::db", "connection lost") }]);
+        app.switch_to_log_mode();
+        app.toggle_message_only();
+
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: String = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains("connection lost"));
+        assert!(!screen.contains("app::db:"));
+        let (x, y) = (0..12u16)
+            .find_map(|y| {
+                let row: String = (0..80).map(|x| buffer[(x, y)].symbol()).collect();
+                let byte = row.find("connection lost")?;
+                Some((row[..byte].chars().count(), y))
+            })
+            .unwrap();
+        assert_eq!(buffer[(x as u16, y)].fg, Color::Red);
+
+        // コピーはテンプレートに従う
+        app.start_text_selection();
+        assert_eq!(app.copy_selected_logs().unwrap(), "[2024-01-01T12:00:00Z] ERROR app::db: connection lost");
+    }
+
+    #[test]
+    fn test_raw_view_shows_and_copies_original_lines() {
+        let parser = crate::log_parser::LogParser::new().unwrap();
+        let mut app = App::new();
+        app.update_logs(parser.parse_multiline_logs("2024-01-01T12:00:00.000000Z  ERROR app::db: connection lost\n  caused by: reset\n"));
+        app.switch_to_log_mode();
+        app.toggle_raw_view();
+        assert_eq!(app.status_message.as_deref(), Some("Showing raw lines"));
+
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..12u16).map(|y| (0..80).map(|x| buffer[(x, y)].symbol()).collect()).collect();
+        // 解析時に詰めた空白もそのまま表示する
+        assert!(rows.iter().any(|row| row.contains("2024-01-01T12:00:00.000000Z  ERROR app::db: connection lost")));
+        assert!(rows.iter().any(|row| row.contains("  caused by: reset")));
+        assert_eq!(app.total_display_lines(), 2);
+
+        app.start_text_selection();
+        assert_eq!(app.copy_selected_logs_with_fields().unwrap(), "2024-01-01T12:00:00.000000Z  ERROR app::db: connection lost\n  caused by: reset");
+    }
+
+    #[test]
+    fn test_focus_bar_fills_the_row() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", "short"), entry("INFO", "app", "two\nlines")]);
+        app.switch_to_log_mode();
+        app.focus_color = Color::Indexed(24);
+
+        let backend = ratatui::backend::TestBackend::new(80, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let focused_rows: Vec<u16> = (0..12).filter(|&y| buffer[(79, y)].bg == Color::Indexed(24)).collect();
+        // 継続行も含めてエントリの全行が右端まで塗られる
+        assert_eq!(focused_rows.len(), 2);
+        let rows: Vec<String> = focused_rows.iter().map(|&y| (0..80).map(|x| buffer[(x, y)].symbol()).collect()).collect();
+        assert!(rows[0].contains("two") && rows[1].contains("lines"));
+    }
+
+    #[test]
+    fn test_entry_separators() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", "first"), entry("INFO", "app", "second"), entry("INFO", "app", "third"), entry("INFO", "app", "fourth")]);
+        app.switch_to_log_mode();
+        app.current_log_line = 0;
+        let render_rows = |app: &mut App| {
+            let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 12)).unwrap();
+            terminal.draw(|f| render(f, app)).unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            let find = |text: &str| (0..12u16).find(|&y| (0..80).map(|x| buffer[(x, y)].symbol()).collect::<String>().contains(text)).unwrap();
+            let rows: Vec<u16> = ["first", "second", "third", "fourth"].iter().map(|text| find(text)).collect();
+            (buffer, rows)
+        };
+
+        // 縞模様はフォーカスの背景の下に敷かれ、行の右端まで伸びる
+        app.entry_separator = EntrySeparator::Zebra;
+        app.current_log_line = 3;
+        let (buffer, rows) = render_rows(&mut app);
+        assert_eq!(buffer[(79, rows[1])].bg, ZEBRA_BACKGROUND);
+        assert_ne!(buffer[(79, rows[2])].bg, ZEBRA_BACKGROUND);
+        assert_eq!(buffer[(79, rows[3])].bg, app.focus_color);
+
+        app.entry_separator = EntrySeparator::Rule;
+        app.current_log_line = 0;
+        let (buffer, rows) = render_rows(&mut app);
+        assert_eq!(rows[1] - rows[0], 2);
+        assert_eq!(buffer[(0, rows[0] + 1)].symbol(), "─");
+        assert_eq!(app.total_display_lines(), 7);
+        assert_eq!(app.get_entry_display_position(2), 3);
+    }
+
+    #[test]
+    fn test_selection_follows_entries_across_filter_changes() {
+        let mut app = App::new();
+        app.update_logs(vec![
+            entry("DEBUG", "app", "a"),
+            entry("INFO", "app", "b"),
+            entry("DEBUG", "app", "c"),
+            entry("INFO", "app", "d"),
+            entry("DEBUG", "app", "e"),
+        ]);
+        app.switch_to_log_mode();
+        app.current_log_line = 1;
+        app.start_text_selection();
+        app.next_log_entry();
+        app.next_log_entry();
+        assert_eq!((app.selection_start, app.selection_end), (Some(1), Some(3)));
+
+        // DEBUGを隠すと b..d は可視エントリの 0..1 に移る
+        app.toggle_log_level("DEBUG");
+        assert_eq!((app.selection_start, app.selection_end), (Some(0), Some(1)));
+        assert_eq!(app.current_log_line, 1);
+        assert_eq!(app.copy_selected_logs().unwrap().lines().count(), 2);
+
+        app.add_logs(vec![entry("INFO", "app", "f")]);
+        app.toggle_log_level("DEBUG");
+        assert_eq!((app.selection_start, app.selection_end), (Some(1), Some(3)));
+        let messages: Vec<&str> = app.filtered_logs[1..=3].iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, ["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_focus_follows_entry_across_filter_changes() {
+        let mut app = App::new();
+        app.update_logs(vec![
+            entry("DEBUG", "app", "a"),
+            entry("DEBUG", "app", "b"),
+            entry("INFO", "app", "c"),
+            entry("DEBUG", "app", "d"),
+            entry("INFO", "app", "e"),
+        ]);
+        app.switch_to_log_mode();
+        app.current_log_line = 2;
+        app.auto_follow = false;
+
+        // 前のエントリが消えてもフォーカスは同じ行に残る
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "c");
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "c");
+
+        // フォーカス中のエントリが消えた場合は直前の可視エントリへ
+        app.current_log_line = 3;
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "c");
+    }
+
+    #[test]
+    fn test_refocus_on_nearest_entry_in_time_when_focus_is_filtered_out() {
+        let at = |timestamp: &str, level: &str, message: &str| LogEntry { timestamp: timestamp.to_string(), ..entry(level, "app", message) };
+        let mut app = App::new();
+        app.update_logs(vec![
+            at("2024-01-01T12:00:00Z", "INFO", "early"),
+            at("2024-01-01T12:00:09Z", "DEBUG", "focused"),
+            at("2024-01-01T12:00:10Z", "INFO", "close after"),
+            at("2024-01-01T12:00:11Z", "DEBUG", "last"),
+        ]);
+        app.switch_to_log_mode();
+        app.auto_follow = false;
+        app.current_log_line = 1;
+
+        // 直後のエントリの方が時間的に近い
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "close after");
+        assert!(app.status_message.as_deref().is_some_and(|message| message.contains("filtered out")));
+
+        // 後ろに可視エントリがなければ直前へ、何も残らなければ位置は変えない
+        app.toggle_log_level("DEBUG");
+        app.current_log_line = 3;
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "close after");
+        app.toggle_log_level("INFO");
+        assert!(app.filtered_logs.is_empty());
+        assert_eq!(app.current_log_line, 0);
+    }
+
+    #[test]
+    fn test_goto_entry_by_id() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "", "a"), entry("DEBUG", "", "b"), entry("INFO", "", "c"), entry("INFO", "", "d")]);
+        app.goto_entry(&EntryAnchor::Id(2));
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "c");
+        assert!(!app.auto_follow);
+
+        // 隠れているエントリは近くの可視エントリへ
+        app.toggle_log_level("DEBUG");
+        app.goto_entry(&EntryAnchor::Id(1));
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "a");
+        assert_eq!(app.status_message.as_deref(), Some("Entry e1 is hidden by the filters"));
+
+        app.goto_entry(&EntryAnchor::Id(9));
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "a");
+        assert_eq!(app.status_message.as_deref(), Some("No entry e9"));
+    }
+
+    #[test]
+    fn test_line_anchor_survives_reload() {
+        let located = |source: &str, line_number: usize, message: &str| LogEntry {
+            source: source.to_string(),
+            line_number: Some(line_number),
+            ..entry("INFO", "", message)
+        };
+        let file = || vec![located("/var/log/a.log", 1, "a1"), located("/var/log/a.log", 2, "a2\n  detail"), located("/var/log/a.log", 4, "a4")];
+        let mut app = App::new();
+        app.update_logs(file().into_iter().chain([located("b.log", 1, "b1")]).collect());
+        app.add_logs(vec![located("b.log", 2, "b2")]);
+        let anchor = entry_anchor(&app.logs[2]).to_string();
+        assert_eq!(anchor, "a.log:4");
+
+        // 読み直すとIDは振り直されるが、行のアンカーは同じエントリを指す
+        app.reload_source("/var/log/a.log", file());
+        app.goto_entry(&crate::export::parse_entry_anchor(&format!("report.html#{}", anchor)).unwrap());
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "a4");
+        app.goto_entry(&crate::export::parse_entry_anchor("b.log:2").unwrap());
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "b2");
+        // 複数行のメッセージの途中の行はそのエントリに
+        app.goto_entry(&crate::export::parse_entry_anchor("a.log:3").unwrap());
+        assert_eq!(app.filtered_logs[app.current_log_line].message, "a2\n  detail");
+
+        let mut fresh = App::new();
+        fresh.update_logs(file());
+        fresh.goto_entry(&crate::export::parse_entry_anchor(&anchor).unwrap());
+        assert_eq!(fresh.filtered_logs[fresh.current_log_line].message, "a4");
+        fresh.goto_entry(&crate::export::parse_entry_anchor("c.log:1").unwrap());
+        assert_eq!(fresh.status_message.as_deref(), Some("No entry c.log:1"));
+    }
+
+    #[test]
+    fn test_grep_filter_matches_target_or_message() {
+        let mut app = App::new();
+        app.update_logs(vec![
+            entry("INFO", "app::db", "connected"),
+            entry("INFO", "app::http", "GET /users returned 500"),
+            entry("INFO", "hyper", "Connection reset"),
+        ]);
+
+        app.set_grep_query("CONN");
+        let messages: Vec<&str> = app.filtered_logs.iter().map(|log| log.message.as_str()).collect();
+        assert_eq!(messages, ["connected", "Connection reset"]);
+        assert!(app.active_filter_labels().contains(&"[grep:CONN]".to_string()));
+
+        app.set_grep_query("db");
+        assert_eq!(app.filtered_logs.len(), 1);
+        app.grep_case_sensitive = true;
+        app.set_grep_query("conn");
+        assert_eq!(app.filtered_logs.len(), 1);
+
+        app.toggle_grep();
+        assert_eq!(app.grep_query, None);
+        assert_eq!(app.filtered_logs.len(), 3);
+        app.toggle_grep();
+        assert_eq!(app.input.as_ref().map(|input| input.kind), Some(InputKind::Grep));
+    }
+
+    #[test]
+    fn test_cycle_min_level() {
+        let mut app = App::new();
+        let levels = app.severity.levels().to_vec();
+        app.update_logs(
+            levels
+                .iter()
+                .map(|level| entry(level, "app", ""))
+                .collect(),
+        );
+        assert_eq!(app.level_threshold(), None);
+
+        let mut visible = Vec::new();
+        for _ in 0..levels.len() {
+            app.cycle_min_level();
+            visible.push((app.level_threshold().map(str::to_string), app.filtered_logs.len()));
+        }
+        assert_eq!(
+            visible.iter().map(|(level, count)| (level.as_deref(), *count)).collect::<Vec<_>>(),
+            [(Some("DEBUG"), 4), (Some("INFO"), 3), (Some("WARN"), 2), (Some("ERROR"), 1), (None, 5)]
+        );
+        assert_eq!(app.status_message.as_deref(), Some("Showing all levels"));
+
+        // 逆方向は「すべて → ERROR → WARN → INFO → DEBUG → すべて」
+        let mut lowered = Vec::new();
+        for _ in 0..levels.len() {
+            app.cycle_min_level_back();
+            lowered.push(app.level_threshold().map(str::to_string));
+        }
+        assert_eq!(lowered.iter().map(Option::as_deref).collect::<Vec<_>>(), [Some("ERROR"), Some("WARN"), Some("INFO"), Some("DEBUG"), None]);
+
+        // フィルタのセグメントがなくてもしきい値はステータスバーに残る
+        app.cycle_min_level_back();
+        app.cycle_min_level_back();
+        app.status_message = None;
+        app.status_right.clear();
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 10)).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let status_row: String = (0..120).map(|x| buffer[(x, 9)].symbol()).collect();
+        assert!(status_row.trim_end().ends_with("WARN+"), "{}", status_row);
+
+        // 個別に切り替えてしきい値でなくなった場合は「すべて」からやり直す
+        app.toggle_log_level("ERROR");
+        app.cycle_min_level();
+        assert_eq!(app.level_threshold(), Some("DEBUG"));
+    }
+
+    #[test]
+    fn test_status_segments() {
+        let mut app = App::new();
+        app.update_logs(vec![entry("INFO", "app", ""), entry("DEBUG", "app", ""), entry("INFO", "app", "")]);
+        app.toggle_log_level("DEBUG");
+        app.switch_to_log_mode();
+        assert_eq!(app.status_segment_text(StatusSegment::Counts).as_deref(), Some("2/3"));
+        assert!(!app.has_live_status());
+
+        app.status_left = vec![StatusSegment::Follow];
+        app.status_right = vec![StatusSegment::Counts, StatusSegment::Filters];
+        let backend = ratatui::backend::TestBackend::new(100, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| render(f, &mut app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let status_row: String = (0..100).map(|x| buffer[(x, 11)].symbol()).collect();
+        assert!(status_row.starts_with("FOLLOW Log Navigation"));
+        assert!(status_row.trim_end().ends_with("2/3 [lvl:ERROR,WARN,INFO,TRACE]"));
+
+        app.status_right.push(StatusSegment::Clock);
+        assert!(app.has_live_status());
+    }
+
+    #[test]
+    fn test_lowercase_levels_filter_and_color() {
+        let parser = crate::log_parser::LogParser::new().unwrap();
+        let logs = parser.parse_multiline_logs("2024-01-01T12:00:00Z info app: up\n2024-01-01T12:00:01Z Warn app: slow\n");
+        assert_eq!(logs[0].level, "INFO");
+        assert_eq!(level_style(&logs[1].level), Style::default().fg(Color::Yellow));
+
+        let mut app = App::new();
+        app.update_logs(logs);
+        app.toggle_log_level("INFO");
+        assert_eq!(app.filtered_logs.len(), 1);
+        assert_eq!(app.filtered_logs[0].level, "WARN");
+    }
+
+    #[test]
+    fn test_truncate_middle_keeps_crate_and_leaf() {
+        let target = "myapp::services::http::handlers::v2::users";
+        assert_eq!(truncate_middle(target, None), target);
+        assert_eq!(truncate_middle(target, Some(100)), target);
+        assert_eq!(truncate_middle(target, Some(16)), "myapp::…::users");
+        assert_eq!(truncate_middle(target, Some(20)), "myapp::…::v2::users");
+    }
+
+    #[test]
+    fn test_truncate_middle_falls_back_to_characters() {
+        assert_eq!(truncate_middle("very_long_crate_name", Some(9)), "very…name");
+        assert_eq!(truncate_middle("a::b::very_long_leaf_name", Some(8)), "a::b…ame");
+        assert_eq!(truncate_middle("abcdef", Some(2)), "ab");
+    }
+}
@@ -0,0 +1,534 @@
+//! Following input files for appended lines and turning received lines into entries.
+
+use crate::log_parser::{LogEntry, LogParser};
+use indexmap::IndexMap;
+use log::{debug, error};
+use notify::{Config, Event as NotifyEvent, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep_until};
+use tokio_util::sync::CancellationToken;
+
+/// A raw input line tagged with the source it was read from
+pub struct SourceLine {
+    pub source: Arc<str>,
+    pub line: String,
+    /// Byte offset and 1-based line number within the source, when known
+    pub position: Option<FilePosition>,
+}
+
+/// Position of a line within a file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FilePosition {
+    pub offset: u64,
+    pub line: usize,
+}
+
+/// Parse lines received from watchers and streams into entries, keeping each entry's
+/// source and its position within that source
+pub fn parse_logs_from_lines(parser: &LogParser, lines: &[SourceLine]) -> Vec<LogEntry> {
+    let mut assembler = EntryAssembler::default();
+    let mut logs = assembler.push(parser, lines, Instant::now());
+    logs.extend(assembler.flush());
+    logs
+}
+
+/// Assembles entries from lines arriving in batches. Each source's last entry is held back
+/// until its next entry starts, so continuation lines (stack traces and the like) that
+/// arrive in a later batch are still merged into it instead of being dropped.
+#[derive(Default)]
+pub struct EntryAssembler {
+    /// Held entry of each source and when its last line arrived
+    pending: IndexMap<Arc<str>, (LogEntry, Instant)>,
+    flush_timeout: Duration,
+}
+
+impl EntryAssembler {
+    /// Release a held entry once its source has sent nothing for `flush_timeout`, even
+    /// though no following entry has started (zero by default: at the next quiet batch)
+    pub fn with_flush_timeout(mut self, flush_timeout: Duration) -> Self {
+        self.flush_timeout = flush_timeout;
+        self
+    }
+
+    /// Parse a batch of lines received by `now`, returning the entries it completed. Entries
+    /// held for sources that sent nothing in this batch are released too once they are
+    /// older than the flush timeout; pass empty batches on idle ticks so the last entry
+    /// still shows up.
+    pub fn push(&mut self, parser: &LogParser, lines: &[SourceLine], now: Instant) -> Vec<LogEntry> {
+        let mut logs: Vec<LogEntry> = Vec::new();
+        let idle: Vec<Arc<str>> = self
+            .pending
+            .iter()
+            .filter(|(source, (_, updated))| {
+                now.duration_since(*updated) >= self.flush_timeout && !lines.iter().any(|line| line.source == **source)
+            })
+            .map(|(source, _)| source.clone())
+            .collect();
+        for source in idle {
+            logs.extend(self.pending.shift_remove(&source).map(|(entry, _)| entry));
+        }
+
+        // 同じ入力元の連続した行ごとにまとめて解析する
+        for group in lines.chunk_by(|a, b| a.source == b.source) {
+            let source = &group[0].source;
+            let content = group
+                .iter()
+                .map(|source_line| source_line.line.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let pending = self.pending.shift_remove(source).map(|(entry, _)| entry);
+            // 持ち越したエントリの位置は前のバッチで変換済み
+            let carried = pending.is_some() as usize;
+            let (mut entries, last) = parser.continue_multiline_logs(pending, &content);
+            entries.extend(last);
+            for entry in entries.iter_mut().skip(carried) {
+                entry.source = source.to_string();
+                // 解析結果の行番号はグループ内の相対位置なので、元の位置に置き換える
+                let position = entry
+                    .line_number
+                    .and_then(|line| group.get(line - 1))
+                    .and_then(|source_line| source_line.position);
+                entry.line_number = position.map(|position| position.line);
+                entry.byte_offset = position.map(|position| position.offset);
+            }
+            if let Some(last) = entries.pop() {
+                self.pending.insert(source.clone(), (last, now));
+            }
+            logs.extend(entries);
+        }
+        logs
+    }
+
+    /// Release every held entry
+    pub fn flush(&mut self) -> Vec<LogEntry> {
+        self.pending.drain(..).map(|(_, (entry, _))| entry).collect()
+    }
+
+    /// Discard the entry held for `source`, e.g. before the source is re-read
+    pub fn forget(&mut self, source: &str) {
+        self.pending.shift_remove(source);
+    }
+}
+
+/// Bytes parsed between progress updates during the initial load
+pub const LOAD_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+/// Parse `content` in chunks of about `chunk_bytes` split at entry boundaries, reporting the
+/// bytes parsed and entries found so far after each chunk
+pub fn parse_logs_from_content(
+    parser: &LogParser,
+    content: &str,
+    chunk_bytes: usize,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Vec<LogEntry> {
+    let mut logs = Vec::new();
+    let mut start = 0;
+    let mut line_base = 0;
+
+    while start < content.len() {
+        // エントリのJSON配列は途中で区切れないので一度に解析する
+        let chunk_bytes = if parser.is_entry_array(content) { content.len() } else { chunk_bytes.max(1) };
+        let end = next_entry_boundary(parser, content, start + chunk_bytes);
+        let chunk = &content[start..end];
+        let mut entries = parser.parse_multiline_logs(chunk);
+        // チャンク内の相対位置をファイル全体での位置に直す
+        for entry in &mut entries {
+            entry.line_number = entry.line_number.map(|line| line + line_base);
+            entry.byte_offset = entry.byte_offset.map(|offset| offset + start as u64);
+        }
+        logs.extend(entries);
+        line_base += chunk.matches('\n').count();
+        start = end;
+        on_progress(start, logs.len());
+    }
+    logs
+}
+
+/// Start of the first line at or after `from` that begins a new entry, or the end of `content`.
+/// Splitting there keeps continuation lines together with their entry.
+fn next_entry_boundary(parser: &LogParser, content: &str, from: usize) -> usize {
+    if from >= content.len() {
+        return content.len();
+    }
+    // fromは文字の途中かもしれないのでバイト列で改行を探す（改行の直後は必ず文字境界）
+    let mut position = match content.as_bytes()[from..].iter().position(|&byte| byte == b'\n') {
+        Some(newline) => from + newline + 1,
+        None => return content.len(),
+    };
+    while position < content.len() {
+        let line_end = content[position..].find('\n').map_or(content.len(), |newline| position + newline);
+        if parser.parse_line(&content[position..line_end]).is_some() {
+            return position;
+        }
+        position = line_end + 1;
+    }
+    content.len()
+}
+
+/// Sent by `watch_file` when it has to poll a file because native change events don't work
+/// for it. It's not an error, so the viewer shows it as a status rather than a banner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollingFallback {
+    pub path: String,
+    pub reason: String,
+}
+
+/// How often a file is checked for appended lines when native change events are unavailable
+pub const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a trailing line without a newline is held back before it is sent as it is
+pub const PARTIAL_LINE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Follow lines appended to `file_path` by polling the file size every `poll_interval` when
+/// given, otherwise through native change events, falling back to polling every
+/// `FALLBACK_POLL_INTERVAL` (reported through `fallback_sender`) when the platform backend
+/// doesn't work
+pub async fn watch_file(
+    file_path: &str,
+    start: FilePosition,
+    poll_interval: Option<Duration>,
+    log_sender: mpsc::UnboundedSender<SourceLine>,
+    error_sender: mpsc::UnboundedSender<String>,
+    fallback_sender: mpsc::UnboundedSender<PollingFallback>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<()> {
+    let path = Path::new(file_path);
+    if !path.exists() {
+        return Err(anyhow::anyhow!("ファイルが存在しません: {}", file_path));
+    }
+
+    debug!("ファイル監視を開始: {}", file_path);
+    let source: Arc<str> = Arc::from(file_path);
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher_error_sender = error_sender.clone();
+    let new_watcher = move || RecommendedWatcher::new(
+        move |res: Result<NotifyEvent, notify::Error>| {
+            match res {
+                Ok(event) => {
+                    debug!("ファイルイベント受信: {:?}", event);
+                    let _ = tx.send(event);
+                }
+                Err(e) => {
+                    error!("ファイル監視エラー: {}", e);
+                    let _ = watcher_error_sender.send(format!("File watch error: {}", e));
+                }
+            }
+        },
+        Config::default(),
+    )
+    .and_then(|mut watcher| watcher.watch(path, RecursiveMode::NonRecursive).map(|_| watcher));
+    // ポーリングが指定されていればネイティブの監視は作らない
+    let watcher = poll_interval.is_none().then(new_watcher);
+
+    // NFSやinotifyの上限などでネイティブの監視が使えない場合はサイズのポーリングに切り替える
+    let polling = match &watcher {
+        None => true,
+        Some(Ok(_)) => false,
+        Some(Err(e)) => {
+            error!("ネイティブのファイル監視を利用できません: {}", e);
+            let _ = fallback_sender.send(PollingFallback { path: file_path.to_string(), reason: e.to_string() });
+            true
+        }
+    };
+    let mut poll = interval(poll_interval.unwrap_or(FALLBACK_POLL_INTERVAL));
+
+    let mut position = start;
+    debug!("監視開始位置: {} bytes ({} lines)", position.offset, position.line);
+
+    // 改行のない末尾の行をそのまま送る期限
+    let mut partial_deadline: Option<tokio::time::Instant> = None;
+    // 読み込みの失敗は一時的なものとして画面に表示し、監視は続ける。送信先がなくなるとfalse
+    let read = |position: &mut FilePosition, partial_deadline: &mut Option<tokio::time::Instant>, flush_partial: bool| {
+        match read_appended_lines(path, position, &source, &log_sender, flush_partial) {
+            Ok(ReadOutcome::Closed) => return false,
+            Ok(ReadOutcome::Partial) => {
+                partial_deadline.get_or_insert_with(|| tokio::time::Instant::now() + PARTIAL_LINE_TIMEOUT);
+            }
+            Ok(ReadOutcome::Done) => *partial_deadline = None,
+            Err(e) => {
+                error!("ファイル読み込みエラー: {}", e);
+                let _ = error_sender.send(format!("Failed to read {}: {}", file_path, e));
+            }
+        }
+        true
+    };
+
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                debug!("ファイル監視がキャンセルされました");
+                break;
+            }
+            _ = poll.tick(), if polling => {
+                if !read(&mut position, &mut partial_deadline, false) {
+                    debug!("ログ送信失敗、監視を終了");
+                    return Ok(());
+                }
+            }
+            event = rx.recv(), if !polling => {
+                match event {
+                    Some(event) => {
+                        debug!("イベント処理中: {:?}", event.kind);
+                        if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) && !read(&mut position, &mut partial_deadline, false) {
+                            debug!("ログ送信失敗、監視を終了");
+                            return Ok(());
+                        }
+                    }
+                    None => {
+                        debug!("ファイル監視チャンネルがクローズされました");
+                        break;
+                    }
+                }
+            }
+            _ = sleep_until(partial_deadline.unwrap_or_else(tokio::time::Instant::now)), if partial_deadline.is_some() => {
+                // 書き込みが止まったまま改行が来ない最後の行も表示する
+                partial_deadline = None;
+                if !read(&mut position, &mut partial_deadline, true) {
+                    debug!("ログ送信失敗、監視を終了");
+                    return Ok(());
+                }
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Send lines appended to `path` since `position` and advance it. A shrunk file is
+/// treated as rotated and read again from the start on the next event, and a trailing
+/// line without a newline is left for a later call since it may still be being written,
+/// unless `flush_partial` is set. Invalid UTF-8 is replaced rather than stopping the read.
+fn read_appended_lines(
+    path: &Path,
+    position: &mut FilePosition,
+    source: &Arc<str>,
+    log_sender: &mpsc::UnboundedSender<SourceLine>,
+    flush_partial: bool,
+) -> anyhow::Result<ReadOutcome> {
+    let mut file = File::open(path)?;
+    let current_size = file.metadata()?.len();
+    debug!("現在のファイルサイズ: {} bytes (前回: {} bytes)", current_size, position.offset);
+
+    if current_size > position.offset {
+        file.seek(SeekFrom::Start(position.offset))?;
+        let mut new_content = Vec::new();
+        file.read_to_end(&mut new_content)?;
+        debug!("新しいコンテンツ読み込み: {} bytes", new_content.len());
+
+        for raw_line in new_content.split_inclusive(|byte| *byte == b'\n') {
+            // 改行で終わっていない末尾の行は書き込み途中の可能性があるので次回に回す
+            if !raw_line.ends_with(b"\n") && !flush_partial {
+                return Ok(ReadOutcome::Partial);
+            }
+            let line_position = FilePosition { offset: position.offset, line: position.line + 1 };
+            position.offset += raw_line.len() as u64;
+            position.line += 1;
+            // 不正なUTF-8で止まらないよう置換文字にして送る
+            let line = String::from_utf8_lossy(raw_line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            // 空行もそのまま送り、残すかどうかはパーサーに任せる
+            if log_sender
+                .send(SourceLine { source: source.clone(), line: line.to_string(), position: Some(line_position) })
+                .is_err()
+            {
+                return Ok(ReadOutcome::Closed);
+            }
+        }
+    } else if current_size < position.offset {
+        // ファイルが縮小された場合（ローテーションなど）
+        debug!("ファイルが縮小されました。リセット中...");
+        *position = FilePosition::default();
+    }
+    Ok(ReadOutcome::Done)
+}
+
+/// How far `read_appended_lines` got
+#[derive(Debug, PartialEq)]
+enum ReadOutcome {
+    /// Every appended line was sent
+    Done,
+    /// A trailing line without a newline was held back
+    Partial,
+    /// The receiver is gone
+    Closed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::App;
+    use std::io::Write;
+
+    /// Start watching `file` from its current end, polling so the tests don't depend on the platform backend
+    fn spawn_watch(file: &tempfile::NamedTempFile) -> (mpsc::UnboundedReceiver<SourceLine>, CancellationToken) {
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        let start = FilePosition { offset: content.len() as u64, line: content.lines().count() };
+        let path = file.path().to_str().unwrap().to_string();
+        let (log_sender, log_receiver) = mpsc::unbounded_channel();
+        let (error_sender, _) = mpsc::unbounded_channel();
+        let (fallback_sender, _) = mpsc::unbounded_channel();
+        let token = CancellationToken::new();
+        let task_token = token.clone();
+        tokio::spawn(async move {
+            watch_file(&path, start, Some(Duration::from_millis(10)), log_sender, error_sender, fallback_sender, task_token).await
+        });
+        (log_receiver, token)
+    }
+
+    async fn receive(receiver: &mut mpsc::UnboundedReceiver<SourceLine>, count: usize) -> Vec<SourceLine> {
+        let mut lines = Vec::new();
+        while lines.len() < count {
+            let line = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn append(file: &tempfile::NamedTempFile, text: &str) {
+        let mut handle = std::fs::OpenOptions::new().append(true).open(file.path()).unwrap();
+        handle.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watched_lines_reach_the_app() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        append(&file, "2024-01-01T12:00:00Z INFO app: started\n");
+        let (mut receiver, token) = spawn_watch(&file);
+
+        append(&file, "2024-01-01T12:00:01Z ERROR app::db: query failed\n    caused by: timeout\n2024-01-01T12:00:02Z DEBUG hyper: poll\n");
+        let lines = receive(&mut receiver, 3).await;
+        token.cancel();
+
+        let logs = parse_logs_from_lines(&LogParser::new().unwrap(), &lines);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].message, "query failed\n    caused by: timeout");
+        assert_eq!((logs[0].line_number, logs[1].line_number), (Some(2), Some(4)));
+        assert_eq!(logs[1].byte_offset, Some(111));
+
+        let mut app = App::new();
+        app.add_logs(logs);
+        let modules: Vec<&str> = app.module_items.iter().map(|item| item.full_path.as_str()).collect();
+        assert_eq!(modules, ["app", "app::db", "hyper"]);
+        assert_eq!(app.current_log_line, 1);
+        app.toggle_log_level("DEBUG");
+        assert_eq!(app.filtered_logs.len(), 1);
+        assert_eq!(app.current_log_line, 0);
+    }
+
+    #[tokio::test]
+    async fn test_partial_lines_and_rotation() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        append(&file, "2024-01-01T12:00:00Z INFO app: before rotation\n");
+        let (mut receiver, token) = spawn_watch(&file);
+
+        // 改行が書かれるまでは送られない
+        append(&file, "2024-01-01T12:00:01Z INFO app: ha");
+        assert!(tokio::time::timeout(Duration::from_millis(100), receiver.recv()).await.is_err());
+        append(&file, "lf\n");
+        let lines = receive(&mut receiver, 1).await;
+        assert_eq!(lines[0].line, "2024-01-01T12:00:01Z INFO app: half");
+        assert_eq!(lines[0].position.map(|position| position.line), Some(2));
+
+        // 改行が来ないまま書き込みが止まった行も待てば送られ、不正なUTF-8は置き換えられる
+        let mut handle = std::fs::OpenOptions::new().append(true).open(file.path()).unwrap();
+        handle.write_all(b"2024-01-01T12:00:02Z INFO app: bad \xff\n2024-01-01T12:00:03Z INFO app: last").unwrap();
+        let lines = receive(&mut receiver, 2).await;
+        assert_eq!(lines[0].line, "2024-01-01T12:00:02Z INFO app: bad \u{fffd}");
+        assert_eq!(lines[1].line, "2024-01-01T12:00:03Z INFO app: last");
+        assert_eq!(lines[1].position.map(|position| position.line), Some(4));
+
+        // 縮小されたファイルは先頭から読み直す
+        std::fs::write(file.path(), "2024-01-01T13:00:00Z WARN app: new\n").unwrap();
+        let lines = receive(&mut receiver, 1).await;
+        token.cancel();
+        assert_eq!(lines[0].line, "2024-01-01T13:00:00Z WARN app: new");
+        assert_eq!(lines[0].position.map(|position| (position.offset, position.line)), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_chunked_load_splits_between_entries() {
+        let parser = LogParser::new().unwrap();
+        let content = "2024-01-01T12:00:00Z INFO app: café ☕\n  détail\n2024-01-01T12:00:01Z WARN app: 日本語\n2024-01-01T12:00:02Z INFO app: end\n";
+        let second = content.find("2024-01-01T12:00:01Z").unwrap() as u64;
+        let third = content.find("2024-01-01T12:00:02Z").unwrap() as u64;
+        // どのチャンク幅でも、多バイト文字の途中で区切られても同じ結果になる
+        for chunk_bytes in 1..=content.len() + 1 {
+            let mut progress = Vec::new();
+            let logs = parse_logs_from_content(&parser, content, chunk_bytes, |parsed, _| progress.push(parsed));
+            let summary: Vec<_> = logs.iter().map(|log| (log.message.as_str(), log.line_number, log.byte_offset)).collect();
+            assert_eq!(
+                summary,
+                [("café ☕\n  détail", Some(1), Some(0)), ("日本語", Some(3), Some(second)), ("end", Some(4), Some(third))],
+                "chunk_bytes = {}",
+                chunk_bytes
+            );
+            assert!(progress.is_sorted() && progress.last() == Some(&content.len()));
+        }
+    }
+
+    #[test]
+    fn test_continuation_lines_in_a_later_batch() {
+        let parser = LogParser::new().unwrap();
+        let file: Arc<str> = Arc::from("app.log");
+        let stdin: Arc<str> = Arc::from("stdin");
+        let line = |source: &Arc<str>, text: &str, line: usize| SourceLine {
+            source: source.clone(),
+            line: text.to_string(),
+            position: Some(FilePosition { offset: 0, line }),
+        };
+        let mut assembler = EntryAssembler::default();
+        let now = Instant::now();
+
+        let batch = [line(&file, "2024-01-01T12:00:00Z ERROR app: panicked", 7), line(&file, "  at main.rs:1", 8)];
+        assert!(assembler.push(&parser, &batch, now).is_empty());
+
+        // 別の入力元の行が届いても、ファイルの保留中のエントリはそのまま
+        let batch = [line(&stdin, "2024-01-01T12:00:01Z INFO cli: hi", 1), line(&file, "  at lib.rs:2", 9)];
+        assert!(assembler.push(&parser, &batch, now).is_empty());
+
+        let batch = [line(&file, "2024-01-01T12:00:02Z INFO app: next", 10)];
+        let logs = assembler.push(&parser, &batch, now);
+        let summary: Vec<_> = logs.iter().map(|entry| (entry.source.as_str(), entry.message.as_str(), entry.line_number)).collect();
+        assert_eq!(
+            summary,
+            [("stdin", "hi", Some(1)), ("app.log", "panicked\n  at main.rs:1\n  at lib.rs:2", Some(7))]
+        );
+        // 元の行もバッチをまたいでつながる
+        assert_eq!(logs[1].raw, "2024-01-01T12:00:00Z ERROR app: panicked\n  at main.rs:1\n  at lib.rs:2");
+
+        // 静かになった入力元の最後のエントリは空のバッチで解放される
+        let logs = assembler.push(&parser, &[], now);
+        assert_eq!(logs.len(), 1);
+        assert_eq!((logs[0].message.as_str(), logs[0].line_number), ("next", Some(10)));
+        assert!(assembler.flush().is_empty());
+    }
+
+    #[test]
+    fn test_flush_timeout_releases_trailing_entry() {
+        let parser = LogParser::new().unwrap();
+        let source: Arc<str> = Arc::from("app.log");
+        let line = |text: &str| SourceLine { source: source.clone(), line: text.to_string(), position: None };
+        let timeout = Duration::from_millis(300);
+        let mut assembler = EntryAssembler::default().with_flush_timeout(timeout);
+        let start = Instant::now();
+
+        let batch = [line("2024-01-01T12:00:00Z ERROR app: panicked"), line("  at main.rs:1")];
+        assert!(assembler.push(&parser, &batch, start).is_empty());
+        // タイムアウト前なら続きの行を待つ
+        assert!(assembler.push(&parser, &[], start + timeout / 2).is_empty());
+        assert!(assembler.push(&parser, &[line("  at lib.rs:2")], start + timeout).is_empty());
+        assert!(assembler.push(&parser, &[], start + timeout * 2 - Duration::from_millis(1)).is_empty());
+
+        // 後続のエントリがなくても、最後の行からタイムアウトが経てば解放される
+        let logs = assembler.push(&parser, &[], start + timeout * 2);
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].message, "panicked\n  at main.rs:1\n  at lib.rs:2");
+        assert!(assembler.flush().is_empty());
+    }
+}